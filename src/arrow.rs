@@ -0,0 +1,424 @@
+//! Conversion of query results to arrow [`RecordBatch`]es (feature `arrow`).
+//!
+//! ```ignore
+//! use druid_io::arrow::{group_by_schema, ToRecordBatch};
+//!
+//! let responses = druid_client.group_by::<Value>(&query).await?;
+//! let batch = responses.to_record_batch(Arc::new(group_by_schema(&query)))?;
+//! ```
+//!
+//! Aggregated results (GroupBy, Timeseries, TopN) get their timestamp in a `timestamp` column,
+//! TopN results one row per entry of each result list. `compactedList` scan events are zipped
+//! with the response's `columns`.
+//!
+//! Inferred schemas keep the order of the results, the feature turns on
+//! `serde_json/preserve_order` for that. Cargo unifies features, so json maps keep their
+//! insertion order in every crate of the build.
+
+use crate::query::definitions::{Aggregation, Dimension, OutputType, PostAggregation};
+use crate::query::group_by::GroupBy;
+use crate::query::response::SegmentMetadataResponse;
+use crate::query::timeseries::Timeseries;
+use crate::query::top_n::TopN;
+use crate::serialization::table::{ColumnKind, JsonTable, ToJsonTable, TIMESTAMP, TIME_COLUMN};
+use crate::serialization::values::{as_f64, as_i64, as_text, as_timestamp_millis};
+use arrow::array::{
+    ArrayRef, BooleanBuilder, Float32Builder, Float64Builder, Int64Builder, ListBuilder,
+    StringBuilder, TimestampMillisecondBuilder,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::error::ArrowError;
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Results convertible to a [`RecordBatch`]: slices of `ScanResponse`, `GroupByResponse`,
+/// `TimeseriesResponse`, `TopNResponse`, `TimeBoundaryResponse`, `SegmentMetadataResponse`,
+/// `MetadataResponse` (DataSourceMetadata) and of the [`Row`](crate::query::row::Row)s of
+/// `query_rows`.
+pub trait ToRecordBatch {
+    /// Converts the results to a batch of `schema`. Columns of the schema missing from the
+    /// results are null, columns of the results missing from the schema are left out.
+    fn to_record_batch(&self, schema: SchemaRef) -> Result<RecordBatch, ArrowError>;
+
+    /// Schema guessed from the values: json integers become `Int64`, other numbers `Float64`,
+    /// lists `List<Utf8>`, ISO 8601 texts and `__time` `Timestamp(ms, UTC)` and anything else
+    /// `Utf8`.
+    fn infer_schema(&self) -> Result<Schema, ArrowError>;
+}
+
+impl<R: ToJsonTable + ?Sized> ToRecordBatch for R {
+    fn to_record_batch(&self, schema: SchemaRef) -> Result<RecordBatch, ArrowError> {
+        record_batch(&json_table(self)?, schema)
+    }
+
+    fn infer_schema(&self) -> Result<Schema, ArrowError> {
+        Ok(infer_schema(&json_table(self)?))
+    }
+}
+
+fn json_table<R: ToJsonTable + ?Sized>(results: &R) -> Result<JsonTable, ArrowError> {
+    results
+        .to_json_table()
+        .map_err(|err| ArrowError::ExternalError(Box::new(err)))
+}
+
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+}
+
+fn string_list_type() -> DataType {
+    DataType::List(Arc::new(Field::new_list_field(DataType::Utf8, true)))
+}
+
+/// Schema of the columns of a segment, `__time` first and the others by name. Multi-value
+/// string columns become `List<Utf8>`, complex columns (sketches...) `Utf8` json.
+pub fn segment_metadata_schema(metadata: &SegmentMetadataResponse) -> Schema {
+    let mut names: Vec<&String> = metadata.columns.keys().collect();
+    names.sort_by_key(|name| (name.as_str() != TIME_COLUMN, name.as_str()));
+    let fields: Vec<Field> = names
+        .into_iter()
+        .map(|name| {
+            let column = &metadata.columns[name];
+            let data_type = match column.column_type.as_str() {
+                _ if name == TIME_COLUMN => timestamp_type(),
+                "LONG" => DataType::Int64,
+                "FLOAT" => DataType::Float32,
+                "DOUBLE" => DataType::Float64,
+                "STRING" if column.has_multiple_values => string_list_type(),
+                _ => DataType::Utf8,
+            };
+            Field::new(name, data_type, true)
+        })
+        .collect();
+    Schema::new(fields)
+}
+
+/// Schema of GroupBy results: timestamp, dimensions, aggregations and post aggregations.
+pub fn group_by_schema(query: &GroupBy) -> Schema {
+    let mut fields = vec![Field::new(TIMESTAMP, timestamp_type(), true)];
+    fields.extend(query.dimensions.iter().map(dimension_field));
+    fields.extend(query.aggregations.iter().map(aggregation_field));
+    fields.extend(query.post_aggregations.iter().map(post_aggregation_field));
+    Schema::new(fields)
+}
+
+/// Schema of Timeseries results: timestamp, aggregations and post aggregations.
+pub fn timeseries_schema(query: &Timeseries) -> Schema {
+    let mut fields = vec![Field::new(TIMESTAMP, timestamp_type(), true)];
+    fields.extend(query.aggregations.iter().map(aggregation_field));
+    fields.extend(query.post_aggregations.iter().map(post_aggregation_field));
+    Schema::new(fields)
+}
+
+/// Schema of TopN results: timestamp, dimension and aggregations.
+pub fn top_n_schema(query: &TopN) -> Schema {
+    let mut fields = vec![
+        Field::new(TIMESTAMP, timestamp_type(), true),
+        dimension_field(&query.dimension),
+    ];
+    fields.extend(query.aggregations.iter().map(aggregation_field));
+    Schema::new(fields)
+}
+
+fn output_type(output_type: &OutputType) -> DataType {
+    match output_type {
+        OutputType::STRING => DataType::Utf8,
+        OutputType::LONG => DataType::Int64,
+        OutputType::FLOAT => DataType::Float32,
+    }
+}
+
+fn dimension_field(dimension: &Dimension) -> Field {
+    match dimension {
+        Dimension::Default {
+            output_name,
+            output_type: data_type,
+            ..
+        }
+        | Dimension::Extraction {
+            output_name,
+            output_type: data_type,
+            ..
+        } => Field::new(output_name, output_type(data_type), true),
+        Dimension::ListFiltered { delegate, .. }
+        | Dimension::RegexFiltered { delegate, .. }
+        | Dimension::PrefixFiltered { delegate, .. } => dimension_field(delegate),
+        Dimension::LookupMap { output_name, .. } | Dimension::Lookup { output_name, .. } => {
+            Field::new(output_name, DataType::Utf8, true)
+        }
+    }
+}
+
+fn aggregation_field(aggregation: &Aggregation) -> Field {
+    use Aggregation::*;
+    let (name, data_type) = match aggregation {
+        Count { name }
+        | LongSum { name, .. }
+        | LongMax { name, .. }
+        | LongMin { name, .. }
+        | LongFirst { name, .. }
+        | LongLast { name, .. }
+        | LongAny { name, .. } => (name, DataType::Int64),
+        FloatSum { name, .. }
+        | FloatMax { name, .. }
+        | FloatMin { name, .. }
+        | FloatFirst { name, .. }
+        | FloatLast { name, .. }
+        | FloatAny { name, .. } => (name, DataType::Float32),
+        StringFirst { name, .. } | StringLast { name, .. } | StringAny { name, .. } => {
+            (name, DataType::Utf8)
+        }
+        // sketches and cardinality estimates are finalized to doubles
+        DoubleSum { name, .. }
+        | DoubleMax { name, .. }
+        | DoubleMin { name, .. }
+        | DoubleFirst { name, .. }
+        | DoubleLast { name, .. }
+        | DoubleAny { name, .. }
+        | Javascript { name, .. }
+        | ThetaSketch { name, .. }
+        | HLLSketchBuild { name, .. }
+        | Cardinality { name, .. }
+        | HyperUnique { name, .. } => (name, DataType::Float64),
+        Filtered { aggregator, .. } => return aggregation_field(aggregator),
+    };
+    Field::new(name, data_type, true)
+}
+
+fn post_aggregation_field(post_aggregation: &PostAggregation) -> Field {
+    match post_aggregation {
+        PostAggregation::LongGreatest { name, .. } | PostAggregation::LongLeast { name, .. } => {
+            Field::new(name, DataType::Int64, true)
+        }
+        PostAggregation::Arithmetic { name, .. }
+        | PostAggregation::DoubleGreatest { name, .. }
+        | PostAggregation::DoubleLeast { name, .. }
+        | PostAggregation::Javascript { name, .. } => Field::new(name, DataType::Float64, true),
+    }
+}
+
+fn infer_schema(table: &JsonTable) -> Schema {
+    let fields: Vec<Field> = table
+        .columns
+        .iter()
+        .map(|name| {
+            let data_type = match table.infer_kind(name) {
+                ColumnKind::Timestamp => timestamp_type(),
+                ColumnKind::Boolean => DataType::Boolean,
+                ColumnKind::Long => DataType::Int64,
+                ColumnKind::Double => DataType::Float64,
+                ColumnKind::StringList => string_list_type(),
+                ColumnKind::String => DataType::Utf8,
+            };
+            Field::new(name, data_type, true)
+        })
+        .collect();
+    Schema::new(fields)
+}
+
+fn record_batch(table: &JsonTable, schema: SchemaRef) -> Result<RecordBatch, ArrowError> {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let values: Vec<&Value> = table.values(field.name()).collect();
+            array(field, &values)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let options = RecordBatchOptions::new().with_row_count(Some(table.num_rows));
+    RecordBatch::try_new_with_options(schema, columns, &options)
+}
+
+fn mismatch(field: &Field, value: &Value) -> ArrowError {
+    ArrowError::CastError(format!(
+        "can't convert {value} to {} for column `{}`",
+        field.data_type(),
+        field.name()
+    ))
+}
+
+macro_rules! build_primitive {
+    ($builder:expr, $field:expr, $values:expr, $convert:expr) => {{
+        let mut builder = $builder;
+        for value in $values {
+            match value {
+                Value::Null => builder.append_null(),
+                value => {
+                    builder.append_value($convert(value).ok_or_else(|| mismatch($field, value))?)
+                }
+            }
+        }
+        Arc::new(builder.finish()) as ArrayRef
+    }};
+}
+
+fn array(field: &Field, values: &[&Value]) -> Result<ArrayRef, ArrowError> {
+    let array = match field.data_type() {
+        DataType::Boolean => build_primitive!(BooleanBuilder::new(), field, values, Value::as_bool),
+        DataType::Int64 => build_primitive!(Int64Builder::new(), field, values, as_i64),
+        DataType::Float64 => build_primitive!(Float64Builder::new(), field, values, as_f64),
+        DataType::Float32 => build_primitive!(Float32Builder::new(), field, values, |value| {
+            as_f64(value).map(|float| float as f32)
+        }),
+        DataType::Timestamp(TimeUnit::Millisecond, _) => build_primitive!(
+            TimestampMillisecondBuilder::new().with_data_type(field.data_type().clone()),
+            field,
+            values,
+            as_timestamp_millis
+        ),
+        DataType::Utf8 => build_primitive!(StringBuilder::new(), field, values, |value| Some(
+            as_text(value)
+        )),
+        DataType::List(item) if item.data_type() == &DataType::Utf8 => {
+            let mut builder = ListBuilder::new(StringBuilder::new()).with_field(item.clone());
+            for value in values {
+                match value {
+                    Value::Null => builder.append_null(),
+                    // druid sends single-valued rows of multi-value columns as plain strings
+                    Value::Array(items) => {
+                        for item in items {
+                            match item {
+                                Value::Null => builder.values().append_null(),
+                                item => builder.values().append_value(as_text(item)),
+                            }
+                        }
+                        builder.append(true);
+                    }
+                    value => {
+                        builder.values().append_value(as_text(value));
+                        builder.append(true);
+                    }
+                }
+            }
+            Arc::new(builder.finish()) as ArrayRef
+        }
+        other => {
+            return Err(ArrowError::NotYetImplemented(format!(
+                "conversion of druid results to {other}"
+            )))
+        }
+    };
+    Ok(array)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::query::definitions::{Granularity, Interval};
+    use crate::query::response::{GroupByResponse, ScanResponse, TopNResponse};
+    use crate::query::DataSource;
+    use arrow::array::{Array, AsArray};
+    use arrow::datatypes::{Float64Type, Int64Type, TimestampMillisecondType};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_group_by_schema() {
+        let query = GroupBy {
+            data_source: DataSource::table("wikipedia"),
+            dimensions: vec![Dimension::default("page")],
+            limit_spec: None,
+            having: None,
+            granularity: Granularity::all(),
+            filter: None,
+            aggregations: vec![
+                Aggregation::count("count"),
+                Aggregation::double_sum("added", "added"),
+            ],
+            post_aggregations: vec![],
+            virtual_columns: vec![],
+            intervals: vec![Interval {
+                from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+                to: NaiveDate::from_ymd_opt(2015, 9, 13)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+            }],
+            subtotal_spec: vec![],
+            context: Default::default(),
+        };
+        let responses: Vec<GroupByResponse<Value>> = serde_json::from_str(
+            r#"[
+                {"timestamp": "2015-09-12T00:00:00.000Z", "event": {"page": "Main", "count": 3, "added": 12.5}},
+                {"timestamp": "2015-09-12T00:00:00.000Z", "event": {"page": null, "count": 1, "added": 1}}
+            ]"#,
+        )
+        .unwrap();
+        let batch = responses
+            .to_record_batch(Arc::new(group_by_schema(&query)))
+            .unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(
+            batch
+                .schema()
+                .fields()
+                .iter()
+                .map(|field| field.name().as_str())
+                .collect::<Vec<_>>(),
+            vec!["timestamp", "page", "count", "added"]
+        );
+        let timestamps = batch.column(0).as_primitive::<TimestampMillisecondType>();
+        assert_eq!(timestamps.value(0), 1442016000000);
+        let pages = batch.column(1).as_string::<i32>();
+        assert_eq!(pages.value(0), "Main");
+        assert!(pages.is_null(1));
+        assert_eq!(batch.column(2).as_primitive::<Int64Type>().value(0), 3);
+        assert_eq!(batch.column(3).as_primitive::<Float64Type>().value(1), 1.0);
+
+        // doubles are only taken for longs when they are whole numbers
+        let responses: Vec<GroupByResponse<Value>> = serde_json::from_str(
+            r#"[{"timestamp": "2015-09-12T00:00:00.000Z", "event": {"page": "Main", "count": 1.7, "added": 1}}]"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            responses.to_record_batch(Arc::new(group_by_schema(&query))),
+            Err(ArrowError::CastError(_))
+        ));
+    }
+
+    #[test]
+    fn test_infer_schema() {
+        let responses: Vec<TopNResponse<Value>> = serde_json::from_str(
+            r#"[{"timestamp": "2015-09-12T00:00:00.000Z", "result": [{"page": "Main", "count": 3, "ratio": 1}, {"page": "Talk", "count": 1, "ratio": 0.5}]}]"#,
+        )
+        .unwrap();
+        let schema = responses.infer_schema().unwrap();
+        assert_eq!(
+            schema.field_with_name("count").unwrap().data_type(),
+            &DataType::Int64
+        );
+        assert_eq!(
+            schema.field_with_name("ratio").unwrap().data_type(),
+            &DataType::Float64
+        );
+        let batch = responses.to_record_batch(Arc::new(schema)).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+    }
+
+    #[test]
+    fn test_compacted_scan() {
+        let metadata: SegmentMetadataResponse = serde_json::from_str(
+            r#"{"id": "merged", "intervals": null, "columns": {
+                "__time": {"type": "LONG", "hasMultipleValues": false, "size": 0, "cardinality": null, "minValue": null, "maxValue": null, "errorMessage": null},
+                "tags": {"type": "STRING", "hasMultipleValues": true, "size": 0, "cardinality": 3, "minValue": "a", "maxValue": "c", "errorMessage": null},
+                "added": {"type": "LONG", "hasMultipleValues": false, "size": 0, "cardinality": null, "minValue": null, "maxValue": null, "errorMessage": null}
+            }, "queryGranularity": null, "rollup": null, "size": 0, "numRows": 2, "timestampSpec": {"column": "time", "format": "auto", "missingValue": null}, "aggregators": null}"#,
+        )
+        .unwrap();
+        let schema = segment_metadata_schema(&metadata);
+        assert_eq!(schema.field(0).name(), "__time");
+
+        let responses: Vec<ScanResponse<Value>> = serde_json::from_str(
+            r#"[{"segmentId": "s1", "columns": ["__time", "added", "tags"], "events": [[1442016000000, 5, ["a", "b"]], [1442016001000, 7, "c"]]}]"#,
+        )
+        .unwrap();
+        let batch = responses.to_record_batch(Arc::new(schema)).unwrap();
+        assert_eq!(batch.column(1).as_primitive::<Int64Type>().value(1), 7);
+        let tags = batch.column(2).as_list::<i32>();
+        assert_eq!(tags.value(0).len(), 2);
+        assert_eq!(tags.value(1).as_string::<i32>().value(0), "c");
+    }
+}
@@ -1,22 +1,62 @@
+use crate::ingestion::msq::{MsqError, MsqTaskReport};
+use crate::ingestion::response::TaskStatus;
 use crate::query::response::GroupByResponse;
 use crate::query::response::MetadataResponse;
 use crate::query::response::ScanResponse;
 use crate::query::response::SearchResponse;
 use crate::query::response::SegmentMetadataResponse;
+use crate::query::response::SqlResultSet;
 use crate::query::response::TimeBoundaryResponse;
+use crate::query::response::{DruidError, ErrorCategory};
+use crate::query::response::{ResponseContext, ResponseMetadata, WithMetadata};
 use crate::query::response::{TimeseriesResponse, TopNResponse};
+use crate::query::row::{self, Row};
+use crate::query::sql::{SqlParseError, SqlQuery};
 use crate::query::timeseries::Timeseries;
 use crate::query::{
     group_by::GroupBy, scan::Scan, search::Search, segment_metadata::SegmentMetadata,
     time_boundary::TimeBoundary, top_n::TopN, DataSource,
 };
 use crate::query::{DataSourceMetadata, Query};
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
-use serde::de::DeserializeOwned;
+use crate::serialization::compact::GroupByRowsSeed;
+use crate::serialization::json_stream::JsonArraySplitter;
+#[cfg(feature = "smile")]
+use crate::serialization::smile;
+use auth::{authorize, send_authorized, CredentialsError};
+use brokers::{BrokerPool, InFlight};
+use cassette::{Cassette, CassetteMode};
+use futures::stream::{BoxStream, Stream, StreamExt};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, RequestBuilder};
+use serde::de::{DeserializeOwned, DeserializeSeed};
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
+pub mod admin;
+pub mod auth;
+pub mod brokers;
+mod cassette;
+pub mod coordinator;
+pub mod lookups;
+pub mod msq;
+pub mod overlord;
+pub mod retry;
+pub mod supervisor;
+
+pub use admin::AdminClientBuilder;
+pub use auth::{Credentials, CredentialsProvider};
+pub use brokers::{BrokerSelector, LeastOutstanding, Random, RoundRobin};
+pub use coordinator::CoordinatorClient;
+pub use lookups::LookupsClient;
+pub use msq::MsqClient;
+pub use overlord::OverlordClient;
+pub use retry::{RetryAttempt, RetryPolicy};
+pub use supervisor::SupervisorClient;
+
 #[derive(Error, Debug)]
 #[non_exhaustive]
 pub enum DruidClientError {
@@ -32,88 +72,771 @@ pub enum DruidClientError {
     ParsingError { source: serde_json::Error },
     #[error("couldn't deserialize json to object")]
     ParsingResponseError { source: serde_json::Error }, // todo: original json but with manageable size
-    #[error("Server responded with an error")]
-    ServerError { response: String },
+    #[error("query timed out: {error}")]
+    QueryTimeout { status: u16, error: Box<DruidError> },
+    #[error("query capacity exceeded: {error}")]
+    QueryCapacityExceeded { status: u16, error: Box<DruidError> },
+    #[error("query is not supported: {error}")]
+    QueryUnsupported { status: u16, error: Box<DruidError> },
+    #[error("resource limit exceeded: {error}")]
+    ResourceLimitExceeded { status: u16, error: Box<DruidError> },
+    #[error("query was cancelled: {error}")]
+    Cancelled { status: u16, error: Box<DruidError> },
+    #[error("Server responded with an error (status {status})")]
+    ServerError {
+        status: u16,
+        response: String,
+        error: Option<Box<DruidError>>,
+    },
+    #[error("query `{query_id}` didn't complete within {timeout:?}")]
+    Timeout { query_id: String, timeout: Duration },
+    #[error("task `{task_id}` failed: {}", error_msg.as_deref().unwrap_or("no error message"))]
+    TaskFailed {
+        task_id: String,
+        error_msg: Option<String>,
+        status: Box<TaskStatus>,
+    },
+    #[error("rows exceed {max_bytes_per_task} bytes, tasks with perfect rollup can't append to each other")]
+    IngestionTooLarge { max_bytes_per_task: usize },
+    #[error("ingestion failed after {} task(s) were committed", completed.len())]
+    PartialIngestion {
+        completed: Vec<TaskStatus>,
+        source: Box<DruidClientError>,
+    },
+    #[error("multi-stage query `{query_id}` failed: {}", error_msg.as_deref().unwrap_or("no error message"))]
+    MsqFailed {
+        query_id: String,
+        error_msg: Option<String>,
+        error: Option<Box<MsqError>>,
+        report: Option<Box<MsqTaskReport>>,
+    },
+    #[error("task `{task_id}` didn't complete within {timeout:?}")]
+    TaskTimeout { task_id: String, timeout: Duration },
+    #[error("task `{task_id}` has no `{report}` report")]
+    MissingTaskReport { task_id: String, report: String },
+    #[error("no recorded response for query {query}")]
+    UnrecordedQuery { query: String },
+    #[error("couldn't use cassette {path:?}")]
+    Cassette {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("couldn't obtain credentials")]
+    Credentials { source: CredentialsError },
+    #[error("response was truncated before it was complete")]
+    TruncatedResponse,
+    #[cfg(feature = "smile")]
+    #[error("couldn't convert smile data")]
+    SmileError { source: smile::Error },
+    #[error("no broker is configured")]
+    NoBrokerAvailable,
     #[error("unknown data store error")]
     Unknown,
 }
 type ClientResult<T> = Result<T, DruidClientError>;
 
+impl DruidClientError {
+    /// Classifies a failed druid response by the category of a `druidException`, or by the
+    /// legacy error code when the broker predates them.
+    pub(crate) fn from_response(status: u16, response: String) -> Self {
+        let error = match serde_json::from_str::<DruidError>(&response) {
+            Ok(error) if error.error.is_some() || error.error_code.is_some() => Box::new(error),
+            _ => {
+                return DruidClientError::ServerError {
+                    status,
+                    response,
+                    error: None,
+                }
+            }
+        };
+        let code = error.error_code.as_deref().or(error.error.as_deref());
+        match (error.category, code) {
+            (_, Some("Resource limit exceeded" | "ResourceLimitExceeded")) => {
+                DruidClientError::ResourceLimitExceeded { status, error }
+            }
+            (Some(ErrorCategory::Timeout), _) | (_, Some("Query timeout")) => {
+                DruidClientError::QueryTimeout { status, error }
+            }
+            (Some(ErrorCategory::CapacityExceeded), _) | (_, Some("Query capacity exceeded")) => {
+                DruidClientError::QueryCapacityExceeded { status, error }
+            }
+            (Some(ErrorCategory::Unsupported), _)
+            | (_, Some("Unsupported operation" | "SQL query is unsupported")) => {
+                DruidClientError::QueryUnsupported { status, error }
+            }
+            (Some(ErrorCategory::Canceled), _) | (_, Some("Query cancelled")) => {
+                DruidClientError::Cancelled { status, error }
+            }
+            _ => DruidClientError::ServerError {
+                status,
+                response,
+                error: Some(error),
+            },
+        }
+    }
+
+    /// Typed druid error carried by the variant, if the server sent one.
+    pub fn druid_error(&self) -> Option<&DruidError> {
+        match self {
+            DruidClientError::QueryTimeout { error, .. }
+            | DruidClientError::QueryCapacityExceeded { error, .. }
+            | DruidClientError::QueryUnsupported { error, .. }
+            | DruidClientError::ResourceLimitExceeded { error, .. }
+            | DruidClientError::Cancelled { error, .. } => Some(error),
+            DruidClientError::ServerError { error, .. } => error.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Whether sending the same request again may succeed: the broker couldn't be reached, a
+    /// proxy in front of it answered `502`/`503`/`504`, or druid was out of query capacity.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            DruidClientError::HttpConnection { source } => is_connect_error(source),
+            DruidClientError::QueryCapacityExceeded { .. } => true,
+            DruidClientError::ServerError { status, .. } => matches!(status, 502..=504),
+            _ => false,
+        }
+    }
+
+    /// HTTP status code of the failed response, if the server answered at all.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            DruidClientError::QueryTimeout { status, .. }
+            | DruidClientError::QueryCapacityExceeded { status, .. }
+            | DruidClientError::QueryUnsupported { status, .. }
+            | DruidClientError::ResourceLimitExceeded { status, .. }
+            | DruidClientError::Cancelled { status, .. }
+            | DruidClientError::ServerError { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+async fn error_from_response(response: reqwest::Response) -> DruidClientError {
+    let status = response.status().as_u16();
+    match response.text().await {
+        Ok(body) => DruidClientError::from_response(status, body),
+        Err(source) => DruidClientError::HttpError { source },
+    }
+}
+
+impl From<SqlParseError> for DruidClientError {
+    fn from(err: SqlParseError) -> Self {
+        match err {
+            SqlParseError::Json(source) => DruidClientError::ParsingResponseError { source },
+            SqlParseError::Truncated => DruidClientError::TruncatedResponse,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct DruidClient {
-    url: String,
+    brokers: Arc<BrokerPool>,
+    http_client: ClientWithMiddleware,
+    credentials: Option<Arc<dyn CredentialsProvider>>,
+    retry: RetryPolicy,
+    timeout: Option<Duration>,
+    cassette: Option<Arc<Cassette>>,
+    cancel_on_drop: bool,
+    /// Whether native queries are sent as smile, to the brokers that didn't refuse it.
+    #[cfg(feature = "smile")]
+    smile: bool,
+}
+
+const QUERY_ID: &str = "queryId";
+const SQL_QUERY_ID: &str = "sqlQueryId";
+const TIMEOUT: &str = "timeout";
+const QUERY_ID_HEADER: &str = "X-Druid-Query-Id";
+const RESPONSE_CONTEXT_HEADER: &str = "X-Druid-Response-Context";
+const SQL_PATH: &str = "/sql";
+const DEFAULT_UNHEALTHY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Sends `DELETE druid/v2/{queryId}` (`druid/v2/sql/{sqlQueryId}` for sql) when dropped while
+/// still armed, so a query future that is abandoned half way doesn't keep running on the broker.
+struct CancelGuard {
     http_client: ClientWithMiddleware,
+    credentials: Option<Arc<dyn CredentialsProvider>>,
+    brokers: Arc<BrokerPool>,
+    path: &'static str,
+    query_id: String,
+    /// Whether there is a broker to cancel on, i.e. the client isn't replaying a cassette.
+    cancellable: bool,
+    armed: bool,
+}
+
+impl CancelGuard {
+    /// Disarms the guard once the query is over. A query abandoned on its client-side deadline
+    /// still runs on the broker, so it is cancelled whether `cancel_on_drop` is set or not.
+    fn finish(&mut self, error: Option<&DruidClientError>) {
+        self.armed = self.cancellable && matches!(error, Some(DruidClientError::Timeout { .. }));
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        // the future may be dropped outside of a runtime (e.g. during shutdown), nothing to do then
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            for url in self.brokers.urls() {
+                let request = self
+                    .http_client
+                    .delete(cancel_url(url, self.path, &self.query_id));
+                let credentials = self.credentials.clone();
+                handle.spawn(async move {
+                    if let Ok(request) = authorize(&credentials, request).await {
+                        let _ = request.send().await;
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Body of a request, native queries are sent as smile when the feature is enabled.
+#[derive(Clone, Copy)]
+enum Payload<'a> {
+    Json(&'a str),
+    /// Smile encoding of `query`, which is sent as json to brokers refusing smile.
+    #[cfg(feature = "smile")]
+    Smile {
+        smile: &'a [u8],
+        query: &'a serde_json::Value,
+    },
+}
+
+impl<'a> Payload<'a> {
+    /// Json text of the request, cassettes only hold json requests.
+    fn json(self) -> Option<&'a str> {
+        match self {
+            Payload::Json(request) => Some(request),
+            #[cfg(feature = "smile")]
+            Payload::Smile { .. } => None,
+        }
+    }
+}
+
+/// Body of a response, in the format given by its `Content-Type`.
+enum ResponseBody {
+    Json(String),
+    #[cfg(feature = "smile")]
+    Smile(bytes::Bytes),
+}
+
+impl ResponseBody {
+    async fn read(response: reqwest::Response) -> ClientResult<Self> {
+        #[cfg(feature = "smile")]
+        if response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .is_some_and(|content_type| {
+                content_type
+                    .as_bytes()
+                    .starts_with(smile::CONTENT_TYPE.as_bytes())
+            })
+        {
+            return response
+                .bytes()
+                .await
+                .map(ResponseBody::Smile)
+                .map_err(|source| DruidClientError::HttpError { source });
+        }
+        response
+            .text()
+            .await
+            .map(ResponseBody::Json)
+            .map_err(|source| DruidClientError::HttpError { source })
+    }
+
+    /// Whether the body is a druid error sent with a successful status.
+    fn is_error(&self) -> bool {
+        match self {
+            // sql responses are not necessarily json (csv, json lines), only json bodies can carry an error
+            ResponseBody::Json(response) => serde_json::from_str::<serde_json::Value>(response)
+                .is_ok_and(|json_value| json_value.get("error").is_some()),
+            #[cfg(feature = "smile")]
+            ResponseBody::Smile(response) => {
+                smile::is_object(response)
+                    && smile::from_slice::<serde_json::Value>(response)
+                        .is_ok_and(|value| value.get("error").is_some())
+            }
+        }
+    }
+
+    /// Body as json text, smile is transcoded.
+    fn into_json(self) -> String {
+        match self {
+            ResponseBody::Json(response) => response,
+            #[cfg(feature = "smile")]
+            ResponseBody::Smile(response) => smile::from_slice::<serde_json::Value>(&response)
+                .map(|value| value.to_string())
+                .unwrap_or_else(|_| String::from_utf8_lossy(&response).into_owned()),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self) -> ClientResult<T> {
+        self.decode_seed(PhantomData)
+    }
+
+    fn decode_seed<'de, S: DeserializeSeed<'de>>(&'de self, seed: S) -> ClientResult<S::Value> {
+        match self {
+            ResponseBody::Json(response) => {
+                let mut deserializer = serde_json::Deserializer::from_str(response);
+                seed.deserialize(&mut deserializer)
+                    .and_then(|value| deserializer.end().map(|_| value))
+                    .map_err(|source| DruidClientError::ParsingResponseError { source })
+            }
+            #[cfg(feature = "smile")]
+            ResponseBody::Smile(response) => smile::from_slice_seed(response, seed)
+                .map_err(|source| DruidClientError::SmileError { source }),
+        }
+    }
+}
+
+/// Response of the broker that served a request, keeps the broker's in-flight count until dropped.
+struct BrokerResponse {
+    response: reqwest::Response,
+    in_flight: InFlight,
+}
+
+fn endpoint_url(url: &str, endpoint: &str) -> String {
+    let mut url = url.to_string();
+    if !url.ends_with('/') {
+        url.push('/');
+    }
+    url.push_str(endpoint);
+    url
+}
+
+fn cancel_url(url: &str, path: &str, query_id: &str) -> String {
+    format!("{}{}/{}", url.trim_end_matches('/'), path, query_id)
+}
+
+fn is_connect_error(err: &reqwest_middleware::Error) -> bool {
+    matches!(err, reqwest_middleware::Error::Reqwest(err) if err.is_connect())
 }
 
 impl DruidClient {
     pub fn new(url: &str, endpoint: &str, client: ClientWithMiddleware) -> Self {
-        let mut url = url.to_string();
-        if !url.ends_with('/') {
-            url.push('/');
-        }
-        url.push_str(endpoint);
+        DruidClient::with_brokers(
+            BrokerPool::new(
+                vec![endpoint_url(url, endpoint)],
+                Box::<RoundRobin>::default(),
+                DEFAULT_UNHEALTHY_BACKOFF,
+            ),
+            client,
+        )
+    }
 
+    fn with_brokers(brokers: BrokerPool, client: ClientWithMiddleware) -> Self {
         DruidClient {
-            url,
+            brokers: Arc::new(brokers),
             http_client: client,
+            credentials: None,
+            retry: RetryPolicy::new(1),
+            timeout: None,
+            cassette: None,
+            cancel_on_drop: false,
+            #[cfg(feature = "smile")]
+            smile: true,
+        }
+    }
+
+    /// Copy of the client bounding the queries sent through it by `timeout`, e.g.
+    /// `client.with_timeout(Duration::from_secs(5)).group_by(&query)`. Brokers and connections
+    /// are shared with the original client. A `timeout` set in the query context still wins.
+    pub fn with_timeout(&self, timeout: Duration) -> DruidClient {
+        DruidClient {
+            timeout: Some(timeout),
+            ..self.clone()
         }
     }
 
-    async fn http_query(&self, request: &str) -> Result<String, DruidClientError> {
-        let response_str = self
-            .http_client
-            .post(&self.url)
-            .body(request.to_string())
-            .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .send()
+    /// Timeout of the query: the one already in its context, otherwise the client's which is
+    /// then written to the context so druid gives up at the same time as the client does.
+    fn apply_timeout(&self, request: &mut serde_json::Value) -> Option<Duration> {
+        let context = request.get_mut("context")?.as_object_mut()?;
+        match context.get(TIMEOUT).and_then(|timeout| timeout.as_u64()) {
+            // druid treats 0 as no timeout
+            Some(0) => None,
+            Some(millis) => Some(Duration::from_millis(millis)),
+            None => {
+                let timeout = self.timeout?;
+                context.insert(TIMEOUT.into(), (timeout.as_millis() as u64).into());
+                Some(timeout)
+            }
+        }
+    }
+
+    /// Asks the brokers to cancel the running query with the given id (`context.queryId`).
+    /// With several brokers configured the request goes to all of them, as any of them may be
+    /// running the query. Fails only when no broker accepted it.
+    pub async fn cancel(&self, query_id: &str) -> ClientResult<()> {
+        if self.is_replaying() {
+            return Ok(());
+        }
+        // druid accepts the cancellation of any query id, so every broker is asked
+        let results = futures::future::join_all(self.brokers.urls().map(|url| async move {
+            let request = self.http_client.delete(cancel_url(url, "", query_id));
+            let response = self
+                .authorize(request)
+                .await?
+                .send()
+                .await
+                .map_err(|source| DruidClientError::HttpConnection { source })?;
+            if response.status().is_success() {
+                Ok(())
+            } else {
+                Err(error_from_response(response).await)
+            }
+        }))
+        .await;
+        if results.iter().any(Result::is_ok) {
+            return Ok(());
+        }
+        results.into_iter().last().unwrap_or(Ok(()))
+    }
+
+    fn cancel_guard(&self, path: &'static str, query_id: String) -> CancelGuard {
+        let cancellable = !self.is_replaying();
+        CancelGuard {
+            http_client: self.http_client.clone(),
+            credentials: self.credentials.clone(),
+            brokers: self.brokers.clone(),
+            path,
+            query_id,
+            cancellable,
+            armed: self.cancel_on_drop && cancellable,
+        }
+    }
+
+    fn is_replaying(&self) -> bool {
+        self.cassette
+            .as_ref()
+            .is_some_and(|cassette| cassette.is_replay())
+    }
+
+    async fn authorize(&self, request: RequestBuilder) -> ClientResult<RequestBuilder> {
+        authorize(&self.credentials, request)
             .await
-            .map_err(|source| DruidClientError::HttpConnection { source })?
-            .text()
+            .map_err(|source| DruidClientError::Credentials { source })
+    }
+
+    async fn http_post(
+        &self,
+        url: &str,
+        request: Payload<'_>,
+    ) -> ClientResult<Result<reqwest::Response, reqwest_middleware::Error>> {
+        send_authorized(&self.credentials, || {
+            let builder = self.http_client.post(url);
+            match request {
+                Payload::Json(request) => builder
+                    .body(request.to_string())
+                    .header(reqwest::header::CONTENT_TYPE, "application/json"),
+                #[cfg(feature = "smile")]
+                Payload::Smile { smile: request, .. } => builder
+                    .body(request.to_vec())
+                    .header(reqwest::header::CONTENT_TYPE, smile::CONTENT_TYPE)
+                    .header(reqwest::header::ACCEPT, smile::CONTENT_TYPE),
+            }
+        })
+        .await
+    }
+
+    /// Posts the request to one of the brokers. Brokers that can't be connected to are put on
+    /// backoff, idempotent requests are then transparently sent to the next broker. A broker
+    /// answering a smile request with `406` or `415` gets it again as json, and json from then on.
+    async fn http_send(
+        &self,
+        path: &str,
+        request: Payload<'_>,
+        idempotent: bool,
+    ) -> ClientResult<BrokerResponse> {
+        #[cfg(feature = "smile")]
+        let json_fallback = std::cell::OnceCell::new();
+        let mut tried = vec![];
+        loop {
+            let (index, in_flight) = match self.brokers.acquire(&tried) {
+                Some(broker) => broker,
+                None => return Err(DruidClientError::NoBrokerAvailable),
+            };
+            let replaying = self.cassette.as_ref().filter(|c| c.is_replay());
+            if let Some((cassette, request)) = replaying.zip(request.json()) {
+                return Ok(BrokerResponse {
+                    response: cassette.replay(path, request).await?,
+                    in_flight,
+                });
+            }
+            let url = format!("{}{}", in_flight.broker().url.trim_end_matches('/'), path);
+            #[cfg(feature = "smile")]
+            let mut request = match request {
+                Payload::Smile { query, .. } if !in_flight.broker().accepts_smile() => {
+                    Payload::Json(json_fallback.get_or_init(|| query.to_string()))
+                }
+                request => request,
+            };
+            let response = self.http_post(&url, request).await?;
+            #[cfg(feature = "smile")]
+            let response = match (request, response) {
+                (Payload::Smile { query, .. }, Ok(refused))
+                    if matches!(refused.status().as_u16(), 406 | 415) =>
+                {
+                    in_flight.broker().refuse_smile();
+                    request = Payload::Json(json_fallback.get_or_init(|| query.to_string()));
+                    self.http_post(&url, request).await?
+                }
+                (_, response) => response,
+            };
+            match response {
+                Ok(response) => {
+                    in_flight.broker().mark_healthy();
+                    let response = match (&self.cassette, request.json()) {
+                        (Some(cassette), Some(request)) => {
+                            cassette.record(path, request, response).await?
+                        }
+                        _ => response,
+                    };
+                    return Ok(BrokerResponse {
+                        response,
+                        in_flight,
+                    });
+                }
+                Err(source) => {
+                    let connect_error = is_connect_error(&source);
+                    if connect_error {
+                        self.brokers.mark_unhealthy(index);
+                    }
+                    tried.push(index);
+                    if !connect_error || !idempotent || tried.len() == self.brokers.len() {
+                        return Err(DruidClientError::HttpConnection { source });
+                    }
+                }
+            }
+        }
+    }
+
+    async fn http_query(
+        &self,
+        path: &str,
+        request: &str,
+        idempotent: bool,
+    ) -> Result<String, DruidClientError> {
+        let (response, _) = self
+            .retry
+            .run(idempotent, || {
+                self.http_query_attempt(path, Payload::Json(request), idempotent)
+            })
+            .await?;
+        Ok(response.into_json())
+    }
+
+    /// Sends a native query as smile, or as json to the brokers that answered they don't accept
+    /// smile (`406`/`415`, e.g. from a proxy in front of them).
+    async fn native_query(
+        &self,
+        request: &serde_json::Value,
+    ) -> ClientResult<(ResponseBody, reqwest::header::HeaderMap)> {
+        let idempotent = is_idempotent(request);
+        #[cfg(feature = "smile")]
+        if self.smile {
+            let smile =
+                smile::to_vec(request).map_err(|source| DruidClientError::SmileError { source })?;
+            let payload = Payload::Smile {
+                smile: &smile,
+                query: request,
+            };
+            return self
+                .retry
+                .run(idempotent, || {
+                    self.http_query_attempt("", payload, idempotent)
+                })
+                .await;
+        }
+        let request = serde_json::to_string(request)
+            .map_err(|source| DruidClientError::ParsingError { source })?;
+        self.retry
+            .run(idempotent, || {
+                self.http_query_attempt("", Payload::Json(&request), idempotent)
+            })
             .await
-            .map_err(|source| DruidClientError::HttpError { source })?;
+    }
 
-        let json_value = serde_json::from_str::<serde_json::Value>(&response_str)
-            .map_err(|err| DruidClientError::ParsingError { source: err });
-        if json_value?.get("error").is_some() {
-            return Err(DruidClientError::ServerError {
-                response: response_str,
-            });
+    async fn http_query_attempt(
+        &self,
+        path: &str,
+        request: Payload<'_>,
+        idempotent: bool,
+    ) -> ClientResult<(ResponseBody, reqwest::header::HeaderMap)> {
+        let response = self.http_send(path, request, idempotent).await?.response;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = ResponseBody::read(response).await?;
+
+        if !status.is_success() || body.is_error() {
+            return Err(DruidClientError::from_response(
+                status.as_u16(),
+                body.into_json(),
+            ));
         }
-        Ok(response_str)
+        Ok((body, headers))
     }
 
-    pub async fn query<'a, T: DeserializeOwned + std::fmt::Debug + Serialize>(
+    pub async fn query<T: DeserializeOwned + std::fmt::Debug + Serialize>(
         &self,
         query: &Query,
     ) -> ClientResult<Vec<T>> {
         self._query(query).await
     }
-    pub async fn top_n<'a, T: DeserializeOwned + std::fmt::Debug + Serialize>(
+
+    /// Runs any native query without a compile-time row type, see [`Row`].
+    pub async fn query_rows(&self, query: &Query) -> ClientResult<Vec<Row>> {
+        let results: Vec<serde_json::Value> = self._query(query).await?;
+        row::rows(query, results)
+            .map_err(|source| DruidClientError::ParsingResponseError { source })
+    }
+
+    pub async fn top_n<T: DeserializeOwned + std::fmt::Debug + Serialize>(
         &self,
         query: &TopN,
     ) -> ClientResult<Vec<TopNResponse<T>>> {
         self._query(query).await
     }
 
-    pub async fn search<'a, T: DeserializeOwned + std::fmt::Debug + Serialize>(
-        &self,
-        query: &Search,
-    ) -> ClientResult<Vec<SearchResponse>> {
+    pub async fn search(&self, query: &Search) -> ClientResult<Vec<SearchResponse>> {
         self._query(query).await
     }
 
-    pub async fn group_by<'a, T: DeserializeOwned + std::fmt::Debug + Serialize>(
+    /// With the `resultAsArray` context key set, the positional rows are decoded into `T` as
+    /// the object ones would be.
+    pub async fn group_by<T: DeserializeOwned + std::fmt::Debug + Serialize>(
         &self,
         query: &GroupBy,
     ) -> ClientResult<Vec<GroupByResponse<T>>> {
-        self._query(query).await
+        self._group_by(query).await.map(|(rows, _)| rows)
     }
-    pub async fn scan<'a, T: DeserializeOwned + std::fmt::Debug + Serialize>(
+
+    async fn _group_by<T: DeserializeOwned>(
+        &self,
+        query: &GroupBy,
+    ) -> ClientResult<(Vec<GroupByResponse<T>>, reqwest::header::HeaderMap)> {
+        if !query.result_as_array() {
+            return self._query_with_headers(query).await;
+        }
+        let (response, headers) = self._query_body(query).await?;
+        let columns = query.array_columns();
+        let timestamp = query.all_granularity_timestamp();
+        let seed = GroupByRowsSeed(&columns, timestamp.as_deref(), PhantomData);
+        Ok((response.decode_seed(seed)?, headers))
+    }
+    pub async fn scan<T: DeserializeOwned + std::fmt::Debug + Serialize>(
         &self,
         query: &Scan,
     ) -> ClientResult<Vec<ScanResponse<T>>> {
         self._query(query).await
     }
-    pub async fn time_boundary<'a, T: DeserializeOwned + std::fmt::Debug + Serialize>(
+    /// Streams the rows of a scan query as they arrive instead of buffering the whole response.
+    /// The response is decoded one `ScanResponse` batch (`batch_size` rows) at a time.
+    pub fn scan_stream<'a, T: DeserializeOwned + 'a>(
+        &'a self,
+        query: &'a Scan,
+    ) -> impl Stream<Item = ClientResult<T>> + 'a {
+        futures::stream::unfold(ScanStreamState::Start, move |state| async move {
+            let mut reading = match state {
+                ScanStreamState::Start => match self.start_scan_stream(query).await {
+                    Ok(reading) => Box::new(reading),
+                    Err(err) => return Some((Err(err), ScanStreamState::Done)),
+                },
+                ScanStreamState::Reading(reading) => reading,
+                ScanStreamState::Done => return None,
+            };
+            loop {
+                if let Some(row) = reading.rows.pop_front() {
+                    return Some((Ok(row), ScanStreamState::Reading(reading)));
+                }
+                let next = Deadline::run(reading.deadline.as_ref(), async {
+                    Ok(reading.body.next().await)
+                });
+                let chunk = match next.await {
+                    Err(err) => {
+                        reading.guard.finish(Some(&err));
+                        return Some((Err(err), ScanStreamState::Done));
+                    }
+                    Ok(Some(Ok(chunk))) => chunk,
+                    Ok(Some(Err(source))) => {
+                        return Some((
+                            Err(DruidClientError::HttpError { source }),
+                            ScanStreamState::Done,
+                        ))
+                    }
+                    Ok(None) => {
+                        reading.guard.finish(None);
+                        if reading.splitter.is_finished() {
+                            return None;
+                        }
+                        return Some((
+                            Err(DruidClientError::TruncatedResponse),
+                            ScanStreamState::Done,
+                        ));
+                    }
+                };
+                let batches = reading
+                    .splitter
+                    .feed(&chunk)
+                    .and_then(|elements| {
+                        elements
+                            .iter()
+                            .map(|element| serde_json::from_slice::<ScanResponse<T>>(element))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .map_err(|source| DruidClientError::ParsingResponseError { source });
+                match batches {
+                    Ok(batches) => reading
+                        .rows
+                        .extend(batches.into_iter().flat_map(|batch| batch.events)),
+                    Err(err) => return Some((Err(err), ScanStreamState::Done)),
+                }
+            }
+        })
+    }
+
+    async fn start_scan_stream<T>(&self, query: &Scan) -> ClientResult<ScanStreamReading<T>> {
+        let mut request = serde_json::to_value(query)
+            .map_err(|source| DruidClientError::ParsingError { source })?;
+        let query_id = ensure_query_id(&mut request, QUERY_ID);
+        let deadline = self
+            .apply_timeout(&mut request)
+            .map(|timeout| Deadline::new(query_id.clone(), timeout));
+        let mut guard = self.cancel_guard("", query_id);
+        let idempotent = is_idempotent(&request);
+        let request = request.to_string();
+        let sending = self.retry.run(idempotent, || async {
+            let sent = self
+                .http_send("", Payload::Json(&request), idempotent)
+                .await?;
+            if !sent.response.status().is_success() {
+                return Err(error_from_response(sent.response).await);
+            }
+            Ok(sent)
+        });
+        let BrokerResponse {
+            response,
+            in_flight,
+        } = Deadline::run(deadline.as_ref(), sending)
+            .await
+            .inspect_err(|err| guard.finish(Some(err)))?;
+        Ok(ScanStreamReading {
+            body: response.bytes_stream().boxed(),
+            splitter: JsonArraySplitter::default(),
+            rows: VecDeque::new(),
+            guard,
+            deadline,
+            _in_flight: in_flight,
+        })
+    }
+
+    pub async fn time_boundary(
         &self,
         query: &TimeBoundary,
     ) -> ClientResult<Vec<TimeBoundaryResponse>> {
@@ -126,7 +849,7 @@ impl DruidClient {
         self._query(query).await
     }
 
-    pub async fn timeseries<'a, T: DeserializeOwned + std::fmt::Debug + Serialize>(
+    pub async fn timeseries<T: DeserializeOwned + std::fmt::Debug + Serialize>(
         &self,
         query: &Timeseries,
     ) -> ClientResult<Vec<TimeseriesResponse<T>>> {
@@ -138,20 +861,145 @@ impl DruidClient {
         Req: Serialize,
         Resp: DeserializeOwned,
     {
-        let request = serde_json::to_string(&query)
-            .map_err(|err| DruidClientError::ParsingError { source: err });
+        self._query_with_headers(query)
+            .await
+            .map(|(response, _)| response)
+    }
 
-        let response = match request {
-            Ok(str) => self.http_query(&str).await,
-            Err(e) => Err(e),
-        };
+    async fn _query_with_headers<Req, Resp>(
+        &self,
+        query: &Req,
+    ) -> ClientResult<(Resp, reqwest::header::HeaderMap)>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let (response, headers) = self._query_body(query).await?;
+        Ok((response.decode()?, headers))
+    }
 
-        response.and_then(|str| {
-            serde_json::from_str::<Resp>(&str)
-                .map_err(|source| DruidClientError::ParsingResponseError { source })
+    async fn _query_body<Req: Serialize>(
+        &self,
+        query: &Req,
+    ) -> ClientResult<(ResponseBody, reqwest::header::HeaderMap)> {
+        let mut request = serde_json::to_value(query)
+            .map_err(|err| DruidClientError::ParsingError { source: err })?;
+        let query_id = ensure_query_id(&mut request, QUERY_ID);
+        let deadline = self
+            .apply_timeout(&mut request)
+            .map(|timeout| Deadline::new(query_id.clone(), timeout));
+        let mut guard = self.cancel_guard("", query_id);
+        let response = Deadline::run(deadline.as_ref(), self.native_query(&request)).await;
+        guard.finish(response.as_ref().err());
+        response
+    }
+
+    async fn _rows_with_metadata<Req, Resp>(&self, query: &Req) -> ClientResult<WithMetadata<Resp>>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let (rows, headers) = self._query_with_headers(query).await?;
+        Ok(WithMetadata {
+            rows,
+            metadata: response_metadata(&headers)?,
         })
     }
 
+    /// Same as [`query`](Self::query) but also returns the query id and response context
+    /// druid sent back, e.g. to detect missing segments.
+    pub async fn query_with_metadata<T: DeserializeOwned>(
+        &self,
+        query: &Query,
+    ) -> ClientResult<WithMetadata<T>> {
+        self._rows_with_metadata(query).await
+    }
+
+    pub async fn top_n_with_metadata<T: DeserializeOwned>(
+        &self,
+        query: &TopN,
+    ) -> ClientResult<WithMetadata<TopNResponse<T>>> {
+        self._rows_with_metadata(query).await
+    }
+
+    pub async fn search_with_metadata(
+        &self,
+        query: &Search,
+    ) -> ClientResult<WithMetadata<SearchResponse>> {
+        self._rows_with_metadata(query).await
+    }
+
+    pub async fn group_by_with_metadata<T: DeserializeOwned>(
+        &self,
+        query: &GroupBy,
+    ) -> ClientResult<WithMetadata<GroupByResponse<T>>> {
+        let (rows, headers) = self._group_by(query).await?;
+        Ok(WithMetadata {
+            rows,
+            metadata: response_metadata(&headers)?,
+        })
+    }
+
+    pub async fn scan_with_metadata<T: DeserializeOwned>(
+        &self,
+        query: &Scan,
+    ) -> ClientResult<WithMetadata<ScanResponse<T>>> {
+        self._rows_with_metadata(query).await
+    }
+
+    pub async fn time_boundary_with_metadata(
+        &self,
+        query: &TimeBoundary,
+    ) -> ClientResult<WithMetadata<TimeBoundaryResponse>> {
+        self._rows_with_metadata(query).await
+    }
+
+    pub async fn segment_metadata_with_metadata(
+        &self,
+        query: &SegmentMetadata,
+    ) -> ClientResult<WithMetadata<SegmentMetadataResponse>> {
+        self._rows_with_metadata(query).await
+    }
+
+    pub async fn timeseries_with_metadata<T: DeserializeOwned>(
+        &self,
+        query: &Timeseries,
+    ) -> ClientResult<WithMetadata<TimeseriesResponse<T>>> {
+        self._rows_with_metadata(query).await
+    }
+
+    /// Runs a [Druid SQL](https://druid.apache.org/docs/latest/querying/sql-api.html) query and
+    /// deserializes every returned row into `T`, whatever `resultFormat` the query asks for.
+    /// Header rows are skipped.
+    pub async fn sql<T: DeserializeOwned>(&self, query: &SqlQuery) -> ClientResult<Vec<T>> {
+        let response = self.sql_query(query).await?;
+        query
+            .parse_response(&response)
+            .map_err(DruidClientError::from)
+    }
+
+    /// Runs a Druid SQL query without a compile-time row type. Rows are returned as json values
+    /// in column order together with the column names and types.
+    pub async fn sql_result_set(&self, query: &SqlQuery) -> ClientResult<SqlResultSet> {
+        let response = self.sql_query(&query.with_full_header()).await?;
+        SqlQuery::parse_result_set(&response).map_err(DruidClientError::from)
+    }
+
+    async fn sql_query(&self, query: &SqlQuery) -> ClientResult<String> {
+        let mut request = serde_json::to_value(query)
+            .map_err(|source| DruidClientError::ParsingError { source })?;
+        let query_id = ensure_query_id(&mut request, SQL_QUERY_ID);
+        let deadline = self
+            .apply_timeout(&mut request)
+            .map(|timeout| Deadline::new(query_id.clone(), timeout));
+        let mut guard = self.cancel_guard(SQL_PATH, query_id);
+        let request = request.to_string();
+        let sending = self.http_query(SQL_PATH, &request, query.is_idempotent());
+        let response = Deadline::run(deadline.as_ref(), sending).await;
+        guard.finish(response.as_ref().err());
+        response
+    }
+
     pub async fn datasource_metadata(
         self,
         data_source: DataSource,
@@ -165,21 +1013,174 @@ impl DruidClient {
     }
 }
 
+enum ScanStreamState<T> {
+    Start,
+    Reading(Box<ScanStreamReading<T>>),
+    Done,
+}
+
+struct ScanStreamReading<T> {
+    body: BoxStream<'static, reqwest::Result<bytes::Bytes>>,
+    splitter: JsonArraySplitter,
+    rows: VecDeque<T>,
+    guard: CancelGuard,
+    deadline: Option<Deadline>,
+    _in_flight: InFlight,
+}
+
+/// Point in time by which a query has to complete, including retries and reading the response.
+struct Deadline {
+    query_id: String,
+    timeout: Duration,
+    at: tokio::time::Instant,
+}
+
+impl Deadline {
+    fn new(query_id: String, timeout: Duration) -> Self {
+        Deadline {
+            query_id,
+            timeout,
+            at: tokio::time::Instant::now() + timeout,
+        }
+    }
+
+    async fn run<T>(
+        deadline: Option<&Deadline>,
+        future: impl std::future::Future<Output = ClientResult<T>>,
+    ) -> ClientResult<T> {
+        match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.at, future)
+                .await
+                .unwrap_or_else(|_| {
+                    Err(DruidClientError::Timeout {
+                        query_id: deadline.query_id.clone(),
+                        timeout: deadline.timeout,
+                    })
+                }),
+            None => future.await,
+        }
+    }
+}
+
+fn response_metadata(headers: &reqwest::header::HeaderMap) -> ClientResult<ResponseMetadata> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    let context = match header(RESPONSE_CONTEXT_HEADER) {
+        Some(context) => serde_json::from_str::<ResponseContext>(&context)
+            .map_err(|source| DruidClientError::ParsingResponseError { source })?,
+        None => ResponseContext::default(),
+    };
+    Ok(ResponseMetadata {
+        query_id: header(QUERY_ID_HEADER),
+        context,
+    })
+}
+
+/// Native queries only read, yet a query type the client doesn't know isn't sent twice.
+fn is_idempotent(request: &serde_json::Value) -> bool {
+    matches!(
+        request
+            .get("queryType")
+            .and_then(|query_type| query_type.as_str()),
+        Some(
+            "timeseries"
+                | "topN"
+                | "groupBy"
+                | "scan"
+                | "search"
+                | "timeBoundary"
+                | "segmentMetadata"
+                | "dataSourceMetadata"
+        )
+    )
+}
+
+/// Context keys holding the id of a native and a sql query.
+pub(crate) const QUERY_ID_KEYS: [&str; 2] = [QUERY_ID, SQL_QUERY_ID];
+
+/// Query json without the ids and the timeout the client adds to every query (and without a
+/// context left empty by that), so the same query sent twice compares equal.
+pub(crate) fn normalize_query(query: &serde_json::Value) -> serde_json::Value {
+    let mut query = query.clone();
+    if let Some(fields) = query.as_object_mut() {
+        if let Some(context) = fields.get_mut("context").and_then(|c| c.as_object_mut()) {
+            for key in QUERY_ID_KEYS.into_iter().chain([TIMEOUT]) {
+                context.remove(key);
+            }
+            if context.is_empty() {
+                fields.remove("context");
+            }
+        }
+    }
+    query
+}
+
+/// Makes sure the query carries its id (`context.queryId`, `context.sqlQueryId` for sql),
+/// generating one when the caller didn't set it.
+fn ensure_query_id(request: &mut serde_json::Value, key: &str) -> String {
+    let context = match request.as_object_mut() {
+        Some(query) => query
+            .entry("context")
+            .or_insert_with(|| serde_json::Value::Object(Default::default())),
+        None => return uuid::Uuid::new_v4().to_string(),
+    };
+    if let Some(serde_json::Value::String(query_id)) = context.get(key) {
+        return query_id.clone();
+    }
+    let query_id = uuid::Uuid::new_v4().to_string();
+    if let Some(context) = context.as_object_mut() {
+        context.insert(key.into(), query_id.clone().into());
+    }
+    query_id
+}
+
 pub struct DruidClientBuilder {
-    url: String,
+    urls: Vec<String>,
     endpoint: Option<String>,
     client: Option<ClientWithMiddleware>,
+    cancel_on_drop: bool,
+    selector: Option<Box<dyn BrokerSelector>>,
+    unhealthy_backoff: Duration,
+    credentials: Option<Arc<dyn CredentialsProvider>>,
+    retry: Option<RetryPolicy>,
+    timeout: Option<Duration>,
+    cassette: Option<(PathBuf, CassetteMode)>,
+    #[cfg(feature = "smile")]
+    smile: bool,
 }
 
 impl DruidClientBuilder {
     pub fn new(url: &str) -> Self {
+        DruidClientBuilder::with_brokers(&[url])
+    }
+
+    /// Builder for a client spreading queries over a static list of brokers or routers.
+    pub fn with_brokers(urls: &[&str]) -> Self {
         Self {
-            url: url.to_string(),
+            urls: urls.iter().map(|url| url.to_string()).collect(),
             endpoint: None,
             client: None,
+            cancel_on_drop: false,
+            selector: None,
+            unhealthy_backoff: DEFAULT_UNHEALTHY_BACKOFF,
+            credentials: None,
+            retry: None,
+            timeout: None,
+            cassette: None,
+            #[cfg(feature = "smile")]
+            smile: true,
         }
     }
 
+    pub fn broker(mut self, url: &str) -> Self {
+        self.urls.push(url.to_string());
+        self
+    }
+
     pub fn endpoint(mut self, endpoint: &str) -> Self {
         self.endpoint = Some(endpoint.to_string());
         self
@@ -190,12 +1191,231 @@ impl DruidClientBuilder {
         self
     }
 
+    /// When enabled, dropping the future of a native query before it completes sends a cancel
+    /// request for its query id to the broker.
+    pub fn cancel_on_drop(mut self, cancel_on_drop: bool) -> Self {
+        self.cancel_on_drop = cancel_on_drop;
+        self
+    }
+
+    /// How the broker for each query is picked, round robin by default.
+    pub fn selection_strategy<S: BrokerSelector + 'static>(mut self, selector: S) -> Self {
+        self.selector = Some(Box::new(selector));
+        self
+    }
+
+    /// How long a broker that refused a connection is skipped before being tried again.
+    pub fn unhealthy_backoff(mut self, backoff: Duration) -> Self {
+        self.unhealthy_backoff = backoff;
+        self
+    }
+
+    /// Authenticates every request with HTTP basic auth (druid's basic-security extension).
+    pub fn basic_auth(self, username: &str, password: &str) -> Self {
+        self.credentials_provider(Credentials::basic(username, password))
+    }
+
+    /// Sends `Authorization: Bearer <token>` with every request.
+    pub fn bearer_token(self, token: &str) -> Self {
+        self.credentials_provider(Credentials::bearer(token))
+    }
+
+    /// Asks the provider for credentials before every request, for tokens that expire.
+    pub fn credentials_provider<P: CredentialsProvider + 'static>(mut self, provider: P) -> Self {
+        self.credentials = Some(Arc::new(provider));
+        self
+    }
+
+    /// Retries failed queries according to the policy, by default queries are sent only once.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Default time queries may take, sent to druid as the `timeout` context key and enforced
+    /// on the client as well, queries it abandons are cancelled on the broker. See
+    /// [`DruidClient::with_timeout`] for a per-call value.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Writes every query and the response druid sent to the cassette file, e.g. to capture
+    /// fixtures for tests. Queries are keyed by their json without the generated query ids.
+    pub fn record<P: AsRef<Path>>(mut self, cassette: P) -> Self {
+        self.cassette = Some((cassette.as_ref().to_path_buf(), CassetteMode::Record));
+        self
+    }
+
+    /// Answers queries from a cassette written by [`record`](Self::record) without contacting
+    /// druid. A query that wasn't recorded fails with [`DruidClientError::UnrecordedQuery`].
+    pub fn replay<P: AsRef<Path>>(mut self, cassette: P) -> Self {
+        self.cassette = Some((cassette.as_ref().to_path_buf(), CassetteMode::Replay));
+        self
+    }
+
+    /// Sends native queries as smile and asks for smile responses, enabled by default. Brokers
+    /// answering `406` or `415` get json from then on, as do clients recording or replaying a
+    /// cassette.
+    #[cfg(feature = "smile")]
+    pub fn smile(mut self, smile: bool) -> Self {
+        self.smile = smile;
+        self
+    }
+
     pub fn build(self) -> DruidClient {
         let endpoint = self.endpoint.unwrap_or("druid/v2".into());
         let client = self
             .client
             .unwrap_or(ClientBuilder::new(reqwest::Client::new()).build());
+        let brokers = BrokerPool::new(
+            self.urls
+                .iter()
+                .map(|url| endpoint_url(url, &endpoint))
+                .collect(),
+            self.selector
+                .unwrap_or_else(|| Box::<RoundRobin>::default()),
+            self.unhealthy_backoff,
+        );
+
+        let mut druid_client = DruidClient::with_brokers(brokers, client);
+        druid_client.cancel_on_drop = self.cancel_on_drop;
+        druid_client.credentials = self.credentials;
+        druid_client.timeout = self.timeout;
+        #[cfg(feature = "smile")]
+        {
+            druid_client.smile = self.smile && self.cassette.is_none();
+        }
+        druid_client.cassette = self
+            .cassette
+            .map(|(path, mode)| Arc::new(Cassette::new(&path, mode)));
+        if let Some(retry) = self.retry {
+            druid_client.retry = retry;
+        }
+        druid_client
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ensure_query_id() {
+        let mut request = serde_json::json!({"queryType": "scan", "context": {"queryId": "abc"}});
+        assert_eq!(ensure_query_id(&mut request, QUERY_ID), "abc");
+
+        let mut request = serde_json::json!({"queryType": "scan"});
+        let query_id = ensure_query_id(&mut request, QUERY_ID);
+        assert_eq!(request["context"][QUERY_ID], query_id.as_str());
+    }
+
+    #[test]
+    fn test_apply_timeout() {
+        let client = DruidClientBuilder::new("http://localhost:8082")
+            .timeout(Duration::from_secs(5))
+            .build();
+        let mut request = serde_json::json!({"queryType": "scan", "context": {}});
+        assert_eq!(
+            client.apply_timeout(&mut request),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(request["context"][TIMEOUT], 5000);
+
+        let mut request = serde_json::json!({"queryType": "scan", "context": {"timeout": 100}});
+        assert_eq!(
+            client.apply_timeout(&mut request),
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn test_cancel_url() {
+        let url = "http://localhost:8082/druid/v2/";
+        assert_eq!(
+            cancel_url(url, "", "abc"),
+            "http://localhost:8082/druid/v2/abc"
+        );
+        assert_eq!(
+            cancel_url(url, SQL_PATH, "abc"),
+            "http://localhost:8082/druid/v2/sql/abc"
+        );
+    }
+
+    #[test]
+    fn test_deadline() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let deadline = Deadline::new("abc".into(), Duration::from_millis(1));
+        let result: ClientResult<()> =
+            runtime.block_on(Deadline::run(Some(&deadline), futures::future::pending()));
+        assert!(
+            matches!(result, Err(DruidClientError::Timeout { query_id, .. }) if query_id == "abc")
+        );
+    }
+
+    #[test]
+    fn test_response_metadata() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(QUERY_ID_HEADER, "abc".parse().unwrap());
+        headers.insert(
+            RESPONSE_CONTEXT_HEADER,
+            r#"{"uncoveredIntervals":["2015-09-12T00:00:00.000Z/2015-09-13T00:00:00.000Z"],"uncoveredIntervalsOverflowed":false,"missingSegments":[{"itvl":"2015-09-12T00:00:00.000Z/2015-09-13T00:00:00.000Z","ver":"2020-10-01T12:00:00.000Z","part":0}],"ETag":"ab12"}"#
+                .parse()
+                .unwrap(),
+        );
+        let metadata = response_metadata(&headers).unwrap();
+        assert_eq!(metadata.query_id.as_deref(), Some("abc"));
+        assert_eq!(metadata.context.etag.as_deref(), Some("ab12"));
+        assert_eq!(metadata.context.missing_segments[0].part, 0);
+        assert!(metadata.is_partial());
+
+        let metadata = response_metadata(&reqwest::header::HeaderMap::new()).unwrap();
+        assert!(!metadata.is_partial());
+    }
+
+    #[test]
+    fn test_legacy_error() {
+        let body = r#"{
+            "error": "Query timeout",
+            "errorMessage": "Timeout waiting for task.",
+            "errorClass": "java.util.concurrent.TimeoutException",
+            "host": "druid1.example.com:8083"
+        }"#;
+        let err = DruidClientError::from_response(504, body.into());
+        assert!(matches!(
+            err,
+            DruidClientError::QueryTimeout { status: 504, .. }
+        ));
+        assert_eq!(
+            err.druid_error().unwrap().host.as_deref(),
+            Some("druid1.example.com:8083")
+        );
+    }
+
+    #[test]
+    fn test_druid_exception() {
+        let body = r#"{
+            "error": "druidException",
+            "errorCode": "invalidInput",
+            "persona": "USER",
+            "category": "CAPACITY_EXCEEDED",
+            "errorMessage": "Too many concurrent queries",
+            "context": {"lane": "default"}
+        }"#;
+        let err = DruidClientError::from_response(429, body.into());
+        assert!(matches!(
+            err,
+            DruidClientError::QueryCapacityExceeded { status: 429, .. }
+        ));
+        assert_eq!(err.druid_error().unwrap().context["lane"], "default");
 
-        DruidClient::new(&self.url, &endpoint, client)
+        let err = DruidClientError::from_response(500, "<html>oops</html>".into());
+        assert!(matches!(
+            err,
+            DruidClientError::ServerError { error: None, .. }
+        ));
     }
 }
@@ -0,0 +1,200 @@
+use super::auth::{send_authorized, Credentials, CredentialsProvider};
+use super::coordinator::CoordinatorClient;
+use super::lookups::LookupsClient;
+use super::msq::MsqClient;
+use super::overlord::OverlordClient;
+use super::supervisor::SupervisorClient;
+use super::{error_from_response, ClientResult, DruidClientError};
+use reqwest::Method;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// HTTP plumbing shared by the clients of druid's management APIs (overlord, coordinator...):
+/// credentials, json bodies and mapping of error responses.
+#[derive(Clone)]
+pub(crate) struct AdminTransport {
+    url: String,
+    http_client: ClientWithMiddleware,
+    credentials: Option<Arc<dyn CredentialsProvider>>,
+}
+
+impl AdminTransport {
+    /// Sends the request and returns the body of a successful response. Query parameters with
+    /// an empty value are sent as flags.
+    pub(crate) async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, String)],
+        headers: &[(&str, String)],
+        body: Option<String>,
+    ) -> ClientResult<String> {
+        let mut url = format!("{}{}", self.url.trim_end_matches('/'), path);
+        // flags such as `?full` are sent without a value
+        let (flags, params): (Vec<_>, Vec<_>) =
+            query.iter().partition(|(_, value)| value.is_empty());
+        if !flags.is_empty() {
+            let flags: Vec<&str> = flags.iter().map(|(name, _)| *name).collect();
+            url = format!("{}?{}", url, flags.join("&"));
+        }
+        let response = send_authorized(&self.credentials, || {
+            let mut request = self
+                .http_client
+                .request(method.clone(), &url)
+                .query(&params);
+            for (name, value) in headers {
+                request = request.header(*name, value.as_str());
+            }
+            match &body {
+                Some(body) => request
+                    .body(body.clone())
+                    .header(reqwest::header::CONTENT_TYPE, "application/json"),
+                None => request,
+            }
+        })
+        .await?
+        .map_err(|source| DruidClientError::HttpConnection { source })?;
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
+        }
+        response
+            .text()
+            .await
+            .map_err(|source| DruidClientError::HttpError { source })
+    }
+
+    pub(crate) async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> ClientResult<T> {
+        let response = self.send(Method::GET, path, query, &[], None).await?;
+        parse(&response)
+    }
+
+    pub(crate) async fn post<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> ClientResult<T> {
+        let body = serde_json::to_string(body)
+            .map_err(|source| DruidClientError::ParsingError { source })?;
+        let response = self.send(Method::POST, path, &[], &[], Some(body)).await?;
+        parse(&response)
+    }
+}
+
+/// Percent-encodes a path segment, e.g. a task or segment id.
+pub(crate) fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Parses a json response body. Some endpoints answer with an empty body, which is read as
+/// `null` so `()` and `Option` targets work.
+pub(crate) fn parse<T: DeserializeOwned>(response: &str) -> ClientResult<T> {
+    let response = if response.trim().is_empty() {
+        "null"
+    } else {
+        response
+    };
+    serde_json::from_str(response)
+        .map_err(|source| DruidClientError::ParsingResponseError { source })
+}
+
+/// Builds clients for druid's management APIs. The url is the one of the service exposing the
+/// API (overlord, coordinator) or of a router, which proxies all of them.
+pub struct AdminClientBuilder {
+    url: String,
+    client: Option<ClientWithMiddleware>,
+    credentials: Option<Arc<dyn CredentialsProvider>>,
+}
+
+impl AdminClientBuilder {
+    pub fn new(url: &str) -> Self {
+        AdminClientBuilder {
+            url: url.to_string(),
+            client: None,
+            credentials: None,
+        }
+    }
+
+    pub fn client(mut self, client: ClientWithMiddleware) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn basic_auth(self, username: &str, password: &str) -> Self {
+        self.credentials_provider(Credentials::basic(username, password))
+    }
+
+    pub fn bearer_token(self, token: &str) -> Self {
+        self.credentials_provider(Credentials::bearer(token))
+    }
+
+    pub fn credentials_provider<P: CredentialsProvider + 'static>(mut self, provider: P) -> Self {
+        self.credentials = Some(Arc::new(provider));
+        self
+    }
+
+    fn transport(self) -> AdminTransport {
+        AdminTransport {
+            url: self.url,
+            http_client: self
+                .client
+                .unwrap_or_else(|| ClientBuilder::new(reqwest::Client::new()).build()),
+            credentials: self.credentials,
+        }
+    }
+
+    /// Client for the overlord's task API (`/druid/indexer/v1`).
+    pub fn overlord(self) -> OverlordClient {
+        OverlordClient::new(self.transport())
+    }
+
+    /// Client for the coordinator's API (`/druid/coordinator/v1`).
+    pub fn coordinator(self) -> CoordinatorClient {
+        CoordinatorClient::new(self.transport())
+    }
+
+    /// Client for the coordinator's lookup API (`/druid/coordinator/v1/lookups`).
+    pub fn lookups(self) -> LookupsClient {
+        LookupsClient::new(self.transport())
+    }
+
+    /// Client for the multi-stage query task engine (`/druid/v2/sql/task`,
+    /// `/druid/v2/sql/statements`), which needs the url of a router.
+    pub fn msq(self) -> MsqClient {
+        MsqClient::new(self.transport())
+    }
+
+    /// Client for the overlord's supervisor API (`/druid/indexer/v1/supervisor`).
+    pub fn supervisors(self) -> SupervisorClient {
+        SupervisorClient::new(self.transport())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode_segment() {
+        assert_eq!(
+            encode_segment("wikipedia_2015-09-12T00:00:00.000Z_v1"),
+            "wikipedia_2015-09-12T00%3A00%3A00.000Z_v1"
+        );
+        assert_eq!(encode_segment("a b/c"), "a%20b%2Fc");
+    }
+}
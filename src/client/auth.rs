@@ -0,0 +1,123 @@
+use super::{ClientResult, DruidClientError};
+use async_trait::async_trait;
+use reqwest_middleware::RequestBuilder;
+use std::sync::Arc;
+
+pub type CredentialsError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Clone, Debug)]
+pub enum Credentials {
+    /// HTTP basic authentication, as used by druid's basic-security extension.
+    Basic { username: String, password: String },
+    /// `Authorization: Bearer <token>`
+    Bearer(String),
+    /// Any other header a gateway in front of druid expects.
+    Header { name: String, value: String },
+}
+
+impl Credentials {
+    pub fn basic(username: &str, password: &str) -> Self {
+        Credentials::Basic {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    pub fn bearer(token: &str) -> Self {
+        Credentials::Bearer(token.to_string())
+    }
+
+    pub fn header(name: &str, value: &str) -> Self {
+        Credentials::Header {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub(crate) fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            Credentials::Basic { username, password } => {
+                request.basic_auth(username, Some(password))
+            }
+            Credentials::Bearer(token) => request.bearer_auth(token),
+            Credentials::Header { name, value } => request.header(name.as_str(), value.as_str()),
+        }
+    }
+}
+
+/// Source of the credentials attached to every request the clients send. It is asked before
+/// each request, so implementations can cache a token and refresh it once it expires.
+#[async_trait]
+pub trait CredentialsProvider: Send + Sync {
+    async fn credentials(&self) -> Result<Credentials, CredentialsError>;
+
+    /// Called when druid answered `401 Unauthorized`, before the request is retried once with
+    /// freshly obtained credentials. Providers caching a token should drop it here.
+    async fn invalidate(&self) {}
+}
+
+#[async_trait]
+impl CredentialsProvider for Credentials {
+    async fn credentials(&self) -> Result<Credentials, CredentialsError> {
+        Ok(self.clone())
+    }
+}
+
+pub(crate) async fn authorize(
+    provider: &Option<Arc<dyn CredentialsProvider>>,
+    request: RequestBuilder,
+) -> Result<RequestBuilder, CredentialsError> {
+    match provider {
+        Some(provider) => Ok(provider.credentials().await?.apply(request)),
+        None => Ok(request),
+    }
+}
+
+/// Sends the request built by `request` with fresh credentials. A `401 Unauthorized`
+/// invalidates them and the request is sent once more, druid rejected it before doing anything.
+pub(crate) async fn send_authorized(
+    provider: &Option<Arc<dyn CredentialsProvider>>,
+    request: impl Fn() -> RequestBuilder,
+) -> ClientResult<Result<reqwest::Response, reqwest_middleware::Error>> {
+    let mut reauthorized = false;
+    loop {
+        let response = authorize(provider, request())
+            .await
+            .map_err(|source| DruidClientError::Credentials { source })?
+            .send()
+            .await;
+        match (&response, provider) {
+            (Ok(unauthorized), Some(provider))
+                if unauthorized.status() == reqwest::StatusCode::UNAUTHORIZED && !reauthorized =>
+            {
+                provider.invalidate().await;
+                reauthorized = true;
+            }
+            _ => return Ok(response),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::AUTHORIZATION;
+    use reqwest_middleware::ClientBuilder;
+
+    fn authorized(credentials: Credentials) -> reqwest::Request {
+        let client = ClientBuilder::new(reqwest::Client::new()).build();
+        let provider: Option<Arc<dyn CredentialsProvider>> = Some(Arc::new(credentials));
+        let request = tokio_test::block_on(authorize(&provider, client.get("http://broker")));
+        request.unwrap().build().unwrap()
+    }
+
+    #[test]
+    fn test_apply_credentials() {
+        let request = authorized(Credentials::basic("admin", "secret"));
+        assert_eq!(request.headers()[AUTHORIZATION], "Basic YWRtaW46c2VjcmV0");
+        let request = authorized(Credentials::bearer("token"));
+        assert_eq!(request.headers()[AUTHORIZATION], "Bearer token");
+        let request = authorized(Credentials::header("X-Api-Key", "key"));
+        assert_eq!(request.headers()["X-Api-Key"], "key");
+    }
+}
@@ -0,0 +1,217 @@
+use rand::Rng;
+#[cfg(feature = "smile")]
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A broker (or router) as seen by a [`BrokerSelector`].
+#[derive(Debug)]
+pub struct BrokerCandidate<'a> {
+    pub url: &'a str,
+    /// Requests currently in flight to this broker from this client.
+    pub outstanding: usize,
+}
+
+/// Strategy picking the broker the next query is sent to. Only healthy brokers are offered
+/// as candidates, the returned value is a position in `candidates`.
+pub trait BrokerSelector: Send + Sync {
+    fn select(&self, candidates: &[BrokerCandidate]) -> usize;
+}
+
+#[derive(Default)]
+pub struct RoundRobin {
+    next: AtomicUsize,
+}
+
+impl BrokerSelector for RoundRobin {
+    fn select(&self, candidates: &[BrokerCandidate]) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed) % candidates.len()
+    }
+}
+
+#[derive(Default)]
+pub struct Random;
+
+impl BrokerSelector for Random {
+    fn select(&self, candidates: &[BrokerCandidate]) -> usize {
+        rand::thread_rng().gen_range(0..candidates.len())
+    }
+}
+
+#[derive(Default)]
+pub struct LeastOutstanding;
+
+impl BrokerSelector for LeastOutstanding {
+    fn select(&self, candidates: &[BrokerCandidate]) -> usize {
+        candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, candidate)| candidate.outstanding)
+            .map(|(i, _)| i)
+            .unwrap_or_default()
+    }
+}
+
+pub(crate) struct Broker {
+    /// native query endpoint of the broker, e.g. `http://localhost:8082/druid/v2`
+    pub(crate) url: String,
+    outstanding: AtomicUsize,
+    unhealthy_until: Mutex<Option<Instant>>,
+    /// Cleared once the broker (or a proxy in front of it) refused a smile request.
+    #[cfg(feature = "smile")]
+    accepts_smile: AtomicBool,
+}
+
+impl Broker {
+    fn is_healthy(&self, now: Instant) -> bool {
+        match *self.unhealthy_until.lock().unwrap() {
+            Some(until) => until <= now,
+            None => true,
+        }
+    }
+
+    pub(crate) fn mark_healthy(&self) {
+        *self.unhealthy_until.lock().unwrap() = None;
+    }
+
+    #[cfg(feature = "smile")]
+    pub(crate) fn accepts_smile(&self) -> bool {
+        self.accepts_smile.load(Ordering::Relaxed)
+    }
+
+    #[cfg(feature = "smile")]
+    pub(crate) fn refuse_smile(&self) {
+        self.accepts_smile.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Keeps the in-flight counter of a broker up to date for as long as a request is running.
+pub(crate) struct InFlight {
+    broker: Arc<Broker>,
+}
+
+impl InFlight {
+    pub(crate) fn broker(&self) -> &Broker {
+        &self.broker
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.broker.outstanding.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub(crate) struct BrokerPool {
+    brokers: Vec<Arc<Broker>>,
+    selector: Box<dyn BrokerSelector>,
+    backoff: Duration,
+}
+
+impl BrokerPool {
+    pub(crate) fn new(
+        urls: Vec<String>,
+        selector: Box<dyn BrokerSelector>,
+        backoff: Duration,
+    ) -> Self {
+        BrokerPool {
+            brokers: urls
+                .into_iter()
+                .map(|url| {
+                    Arc::new(Broker {
+                        url,
+                        outstanding: AtomicUsize::new(0),
+                        unhealthy_until: Mutex::new(None),
+                        #[cfg(feature = "smile")]
+                        accepts_smile: AtomicBool::new(true),
+                    })
+                })
+                .collect(),
+            selector,
+            backoff,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.brokers.len()
+    }
+
+    pub(crate) fn urls(&self) -> impl Iterator<Item = &str> {
+        self.brokers.iter().map(|broker| broker.url.as_str())
+    }
+
+    /// Picks a broker that wasn't tried yet for the current request. Healthy brokers go to
+    /// the selector, when every remaining broker is backing off the one recovering first is used.
+    pub(crate) fn acquire(&self, tried: &[usize]) -> Option<(usize, InFlight)> {
+        let now = Instant::now();
+        let remaining: Vec<usize> = (0..self.brokers.len())
+            .filter(|i| !tried.contains(i))
+            .collect();
+        let healthy: Vec<usize> = remaining
+            .iter()
+            .copied()
+            .filter(|&i| self.brokers[i].is_healthy(now))
+            .collect();
+
+        let index = if healthy.is_empty() {
+            remaining
+                .into_iter()
+                .min_by_key(|&i| *self.brokers[i].unhealthy_until.lock().unwrap())?
+        } else {
+            let candidates: Vec<BrokerCandidate> = healthy
+                .iter()
+                .map(|&i| BrokerCandidate {
+                    url: &self.brokers[i].url,
+                    outstanding: self.brokers[i].outstanding.load(Ordering::Relaxed),
+                })
+                .collect();
+            healthy[self.selector.select(&candidates).min(healthy.len() - 1)]
+        };
+
+        let broker = self.brokers[index].clone();
+        broker.outstanding.fetch_add(1, Ordering::Relaxed);
+        Some((index, InFlight { broker }))
+    }
+
+    pub(crate) fn mark_unhealthy(&self, index: usize) {
+        *self.brokers[index].unhealthy_until.lock().unwrap() = Some(Instant::now() + self.backoff);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pool(selector: Box<dyn BrokerSelector>) -> BrokerPool {
+        BrokerPool::new(
+            vec!["http://b1/druid/v2".into(), "http://b2/druid/v2".into()],
+            selector,
+            Duration::from_secs(60),
+        )
+    }
+
+    #[test]
+    fn test_round_robin_skips_unhealthy() {
+        let pool = pool(Box::<RoundRobin>::default());
+        assert_eq!(pool.acquire(&[]).unwrap().0, 0);
+        assert_eq!(pool.acquire(&[]).unwrap().0, 1);
+
+        pool.mark_unhealthy(1);
+        assert_eq!(pool.acquire(&[]).unwrap().0, 0);
+        assert_eq!(pool.acquire(&[]).unwrap().0, 0);
+        // every remaining broker is backing off, the unhealthy one is still tried
+        assert_eq!(pool.acquire(&[0]).unwrap().0, 1);
+        assert!(pool.acquire(&[0, 1]).is_none());
+    }
+
+    #[test]
+    fn test_least_outstanding() {
+        let pool = pool(Box::new(LeastOutstanding));
+        let (first, _in_flight) = pool.acquire(&[]).unwrap();
+        let (second, in_flight) = pool.acquire(&[]).unwrap();
+        assert_ne!(first, second);
+        drop(in_flight);
+        assert_eq!(pool.acquire(&[]).unwrap().0, second);
+    }
+}
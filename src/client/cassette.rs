@@ -0,0 +1,206 @@
+use super::{normalize_query, ClientResult, DruidClientError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Response headers worth keeping, the others depend on the broker that answered.
+const RECORDED_HEADERS: [&str; 3] = [
+    "content-type",
+    super::QUERY_ID_HEADER,
+    super::RESPONSE_CONTEXT_HEADER,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CassetteMode {
+    Record,
+    Replay,
+}
+
+/// A query sent to druid and the answer it got.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Interaction {
+    /// path relative to the native query endpoint, empty for native queries
+    path: String,
+    /// query json without generated query ids
+    query: Value,
+    status: u16,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    body: String,
+}
+
+/// File of recorded interactions. In record mode every response received is added to the file
+/// (replacing an earlier recording of the same query), in replay mode responses come from the
+/// file and nothing is sent to druid.
+pub(crate) struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    // loaded on first use so building a client can't fail
+    interactions: tokio::sync::Mutex<Option<Vec<Interaction>>>,
+    /// Held while the file is written, so writes land in the order their snapshots were taken.
+    writing: tokio::sync::Mutex<()>,
+}
+
+impl Cassette {
+    pub(crate) fn new(path: &Path, mode: CassetteMode) -> Self {
+        Cassette {
+            path: path.to_path_buf(),
+            mode,
+            interactions: tokio::sync::Mutex::new(None),
+            writing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn is_replay(&self) -> bool {
+        self.mode == CassetteMode::Replay
+    }
+
+    fn error(&self, source: io::Error) -> DruidClientError {
+        DruidClientError::Cassette {
+            path: self.path.clone(),
+            source,
+        }
+    }
+
+    async fn load(&self) -> io::Result<Vec<Interaction>> {
+        match tokio::fs::read(&self.path).await {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            // recording starts a new cassette
+            Err(err) if err.kind() == io::ErrorKind::NotFound && !self.is_replay() => Ok(vec![]),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn with_interactions<T>(
+        &self,
+        f: impl FnOnce(&mut Vec<Interaction>) -> ClientResult<T>,
+    ) -> ClientResult<T> {
+        let mut interactions = self.interactions.lock().await;
+        if interactions.is_none() {
+            *interactions = Some(self.load().await.map_err(|err| self.error(err))?);
+        }
+        f(interactions.as_mut().unwrap())
+    }
+
+    /// Recorded response to the query, as if the broker had sent it.
+    pub(crate) async fn replay(
+        &self,
+        path: &str,
+        request: &str,
+    ) -> ClientResult<reqwest::Response> {
+        let query = query_key(request)?;
+        let interaction = self
+            .with_interactions(|interactions| {
+                interactions
+                    .iter()
+                    .find(|interaction| interaction.path == path && interaction.query == query)
+                    .cloned()
+                    .ok_or_else(|| DruidClientError::UnrecordedQuery {
+                        query: query.to_string(),
+                    })
+            })
+            .await?;
+
+        let mut response = http::Response::builder().status(interaction.status);
+        for (name, value) in &interaction.headers {
+            response = response.header(name.as_str(), value.as_str());
+        }
+        response
+            .body(interaction.body)
+            .map(reqwest::Response::from)
+            .map_err(|err| self.error(io::Error::new(io::ErrorKind::InvalidData, err)))
+    }
+
+    /// Reads the whole response to store it, and hands back an identical one.
+    pub(crate) async fn record(
+        &self,
+        path: &str,
+        request: &str,
+        response: reqwest::Response,
+    ) -> ClientResult<reqwest::Response> {
+        let status = response.status().as_u16();
+        let headers: BTreeMap<String, String> = RECORDED_HEADERS
+            .iter()
+            .filter_map(|&name| {
+                let value = response.headers().get(name)?.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+        let body = response
+            .text()
+            .await
+            .map_err(|source| DruidClientError::HttpError { source })?;
+        let interaction = Interaction {
+            path: path.to_string(),
+            query: query_key(request)?,
+            status,
+            headers,
+            body,
+        };
+
+        let writing = self.writing.lock().await;
+        let content = self
+            .with_interactions(|interactions| {
+                interactions.retain(|recorded| {
+                    recorded.path != interaction.path || recorded.query != interaction.query
+                });
+                interactions.push(interaction.clone());
+                serde_json::to_vec_pretty(interactions)
+                    .map_err(|source| DruidClientError::ParsingError { source })
+            })
+            .await?;
+        tokio::fs::write(&self.path, content)
+            .await
+            .map_err(|err| self.error(err))?;
+        drop(writing);
+        self.replay(path, request).await
+    }
+}
+
+fn query_key(request: &str) -> ClientResult<Value> {
+    let query = serde_json::from_str::<Value>(request)
+        .map_err(|source| DruidClientError::ParsingError { source })?;
+    Ok(normalize_query(&query))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record_and_replay() {
+        let path = std::env::temp_dir().join(format!("cassette-{}.json", uuid::Uuid::new_v4()));
+        let query =
+            r#"{"queryType":"timeBoundary","dataSource":"wikipedia","context":{"queryId":"a"}}"#;
+        let response = http::Response::builder()
+            .status(200)
+            .header(super::super::QUERY_ID_HEADER, "a")
+            .body(r#"[{"result":{}}]"#)
+            .unwrap();
+
+        let recorder = Cassette::new(&path, CassetteMode::Record);
+        let recorded =
+            tokio_test::block_on(recorder.record("", query, reqwest::Response::from(response)));
+        assert_eq!(recorded.unwrap().status(), 200);
+
+        let player = Cassette::new(&path, CassetteMode::Replay);
+        // ids and the timeout the client adds don't matter
+        let same_query = r#"{"queryType":"timeBoundary","dataSource":"wikipedia","context":{"queryId":"b","timeout":1000}}"#;
+        let replayed = tokio_test::block_on(player.replay("", same_query)).unwrap();
+        assert_eq!(replayed.headers()[super::super::QUERY_ID_HEADER], "a");
+        assert_eq!(
+            tokio_test::block_on(replayed.text()).unwrap(),
+            r#"[{"result":{}}]"#
+        );
+
+        let other_query = r#"{"queryType":"timeBoundary","dataSource":"koalas"}"#;
+        assert!(matches!(
+            tokio_test::block_on(player.replay("", other_query)),
+            Err(DruidClientError::UnrecordedQuery { .. })
+        ));
+        let _ = std::fs::remove_file(path);
+    }
+}
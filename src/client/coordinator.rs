@@ -0,0 +1,208 @@
+use super::admin::{encode_segment, parse, AdminTransport};
+use super::{ClientResult, DruidClientError};
+use crate::coordinator::{
+    AuditInfo, DataSegment, DataSourceCompactionConfig, DataSourceSummary, Rule, SegmentSelection,
+    SegmentsChanged,
+};
+use crate::query::definitions::Interval;
+use reqwest::Method;
+use serde::Serialize;
+use std::collections::HashMap;
+
+const COORDINATOR_PATH: &str = "/druid/coordinator/v1";
+
+/// Client of the coordinator, which manages segments, their retention and their loading on
+/// historicals. Built with [`AdminClientBuilder::coordinator`](super::AdminClientBuilder::coordinator).
+#[derive(Clone)]
+pub struct CoordinatorClient {
+    transport: AdminTransport,
+}
+
+impl CoordinatorClient {
+    pub(crate) fn new(transport: AdminTransport) -> Self {
+        CoordinatorClient { transport }
+    }
+
+    /// Names of the datasources with segments loaded on the cluster.
+    pub async fn data_sources(&self) -> ClientResult<Vec<String>> {
+        self.transport
+            .get(&format!("{COORDINATOR_PATH}/datasources"), &[])
+            .await
+    }
+
+    /// Datasources with the counts and sizes of their loaded segments, overall and per tier.
+    pub async fn data_sources_summary(&self) -> ClientResult<Vec<DataSourceSummary>> {
+        self.transport
+            .get(
+                &format!("{COORDINATOR_PATH}/datasources"),
+                &[("simple", String::new())],
+            )
+            .await
+    }
+
+    /// Used segments of the datasource overlapping `intervals`, all of them when empty.
+    pub async fn segments(
+        &self,
+        data_source: &str,
+        intervals: &[Interval],
+    ) -> ClientResult<Vec<DataSegment>> {
+        let path = format!(
+            "{}/metadata/datasources/{}/segments",
+            COORDINATOR_PATH,
+            encode_segment(data_source)
+        );
+        let query = [("full", String::new())];
+        if intervals.is_empty() {
+            return self.transport.get(&path, &query).await;
+        }
+        let body = serde_json::to_string(intervals)
+            .map_err(|source| DruidClientError::ParsingError { source })?;
+        let response = self
+            .transport
+            .send(Method::POST, &path, &query, &[], Some(body))
+            .await?;
+        parse(&response)
+    }
+
+    pub async fn segment(&self, data_source: &str, segment_id: &str) -> ClientResult<DataSegment> {
+        let path = format!(
+            "{}/metadata/datasources/{}/segments/{}",
+            COORDINATOR_PATH,
+            encode_segment(data_source),
+            encode_segment(segment_id)
+        );
+        self.transport.get(&path, &[]).await
+    }
+
+    /// Marks segments as used so they get loaded again, returns how many changed.
+    pub async fn mark_used(
+        &self,
+        data_source: &str,
+        selection: &SegmentSelection,
+    ) -> ClientResult<u64> {
+        self.mark(data_source, "markUsed", selection).await
+    }
+
+    /// Marks segments as unused so they get dropped from the historicals, returns how many
+    /// changed. Unused segments stay in deep storage until a kill task deletes them.
+    pub async fn mark_unused(
+        &self,
+        data_source: &str,
+        selection: &SegmentSelection,
+    ) -> ClientResult<u64> {
+        self.mark(data_source, "markUnused", selection).await
+    }
+
+    /// Percentage of the used segments loaded, per datasource.
+    pub async fn load_status(&self) -> ClientResult<HashMap<String, f64>> {
+        self.transport
+            .get(&format!("{COORDINATOR_PATH}/loadstatus"), &[])
+            .await
+    }
+
+    /// Replicas left to load, per tier and datasource.
+    pub async fn load_status_full(&self) -> ClientResult<HashMap<String, HashMap<String, u64>>> {
+        self.transport
+            .get(
+                &format!("{COORDINATOR_PATH}/loadstatus"),
+                &[("full", String::new())],
+            )
+            .await
+    }
+
+    /// Retention rules of the datasource, without the cluster's default ones.
+    pub async fn rules(&self, data_source: &str) -> ClientResult<Vec<Rule>> {
+        let path = format!("{}/rules/{}", COORDINATOR_PATH, encode_segment(data_source));
+        self.transport.get(&path, &[]).await
+    }
+
+    /// Retention rules of every datasource, the defaults under `_default`.
+    pub async fn all_rules(&self) -> ClientResult<HashMap<String, Vec<Rule>>> {
+        self.transport
+            .get(&format!("{COORDINATOR_PATH}/rules"), &[])
+            .await
+    }
+
+    /// Replaces the retention rules of the datasource, `_default` for the cluster's defaults.
+    pub async fn set_rules(
+        &self,
+        data_source: &str,
+        rules: &[Rule],
+        audit: &AuditInfo,
+    ) -> ClientResult<()> {
+        let path = format!("{}/rules/{}", COORDINATOR_PATH, encode_segment(data_source));
+        self.send_audited(Method::POST, &path, Some(rules), audit)
+            .await
+    }
+
+    pub async fn compaction_config(
+        &self,
+        data_source: &str,
+    ) -> ClientResult<DataSourceCompactionConfig> {
+        let path = format!(
+            "{}/config/compaction/{}",
+            COORDINATOR_PATH,
+            encode_segment(data_source)
+        );
+        self.transport.get(&path, &[]).await
+    }
+
+    /// Enables automatic compaction of `config.data_source`, or replaces its config.
+    pub async fn set_compaction_config(
+        &self,
+        config: &DataSourceCompactionConfig,
+        audit: &AuditInfo,
+    ) -> ClientResult<()> {
+        let path = format!("{COORDINATOR_PATH}/config/compaction");
+        self.send_audited(Method::POST, &path, Some(config), audit)
+            .await
+    }
+
+    /// Disables automatic compaction of the datasource.
+    pub async fn delete_compaction_config(
+        &self,
+        data_source: &str,
+        audit: &AuditInfo,
+    ) -> ClientResult<()> {
+        let path = format!(
+            "{}/config/compaction/{}",
+            COORDINATOR_PATH,
+            encode_segment(data_source)
+        );
+        self.send_audited::<()>(Method::DELETE, &path, None, audit)
+            .await
+    }
+
+    async fn send_audited<B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+        audit: &AuditInfo,
+    ) -> ClientResult<()> {
+        let body = body
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|source| DruidClientError::ParsingError { source })?;
+        self.transport
+            .send(method, path, &[], &audit.headers(), body)
+            .await
+            .map(|_| ())
+    }
+
+    async fn mark(
+        &self,
+        data_source: &str,
+        action: &str,
+        selection: &SegmentSelection,
+    ) -> ClientResult<u64> {
+        let path = format!(
+            "{}/datasources/{}/{}",
+            COORDINATOR_PATH,
+            encode_segment(data_source),
+            action
+        );
+        let changed: SegmentsChanged = self.transport.post(&path, selection).await?;
+        Ok(changed.num_changed_segments)
+    }
+}
@@ -0,0 +1,119 @@
+use super::admin::{encode_segment, AdminTransport};
+use super::{ClientResult, DruidClientError};
+use crate::coordinator::{AuditInfo, LookupSpec, LookupStatus};
+use reqwest::Method;
+use std::collections::HashMap;
+
+const CONFIG_PATH: &str = "/druid/coordinator/v1/lookups/config";
+const STATUS_PATH: &str = "/druid/coordinator/v1/lookups/status";
+
+/// Client of the coordinator's lookup API, which registers lookups per tier and pushes them to
+/// the brokers, historicals and peons of the tier. Built with
+/// [`AdminClientBuilder::lookups`](super::AdminClientBuilder::lookups).
+#[derive(Clone)]
+pub struct LookupsClient {
+    transport: AdminTransport,
+}
+
+impl LookupsClient {
+    pub(crate) fn new(transport: AdminTransport) -> Self {
+        LookupsClient { transport }
+    }
+
+    /// Initializes the lookup config of a cluster that never had any, lookups can't be created
+    /// before that.
+    pub async fn initialize(&self, audit: &AuditInfo) -> ClientResult<()> {
+        self.send(
+            Method::POST,
+            CONFIG_PATH.to_string(),
+            Some("{}".into()),
+            audit,
+        )
+        .await
+    }
+
+    /// Tiers with lookups, `__default` being the one of nodes without a configured tier.
+    pub async fn tiers(&self) -> ClientResult<Vec<String>> {
+        self.transport.get(CONFIG_PATH, &[]).await
+    }
+
+    /// Names of the lookups of the tier.
+    pub async fn list(&self, tier: &str) -> ClientResult<Vec<String>> {
+        self.transport
+            .get(&format!("{}/{}", CONFIG_PATH, encode_segment(tier)), &[])
+            .await
+    }
+
+    /// Every lookup, by tier and name.
+    pub async fn all(&self) -> ClientResult<HashMap<String, HashMap<String, LookupSpec>>> {
+        self.transport.get(&format!("{CONFIG_PATH}/all"), &[]).await
+    }
+
+    pub async fn get(&self, tier: &str, name: &str) -> ClientResult<LookupSpec> {
+        self.transport
+            .get(&Self::path(CONFIG_PATH, tier, name), &[])
+            .await
+    }
+
+    /// Registers the lookup in the tier or replaces it. Nodes only reload a replaced lookup
+    /// when its version changed.
+    pub async fn create_or_update(
+        &self,
+        tier: &str,
+        name: &str,
+        spec: &LookupSpec,
+        audit: &AuditInfo,
+    ) -> ClientResult<()> {
+        let body = serde_json::to_string(spec)
+            .map_err(|source| DruidClientError::ParsingError { source })?;
+        self.send(
+            Method::POST,
+            Self::path(CONFIG_PATH, tier, name),
+            Some(body),
+            audit,
+        )
+        .await
+    }
+
+    pub async fn delete(&self, tier: &str, name: &str, audit: &AuditInfo) -> ClientResult<()> {
+        self.send(
+            Method::DELETE,
+            Self::path(CONFIG_PATH, tier, name),
+            None,
+            audit,
+        )
+        .await
+    }
+
+    /// Load status of the lookup with the nodes of the tier that haven't loaded it yet.
+    pub async fn status(&self, tier: &str, name: &str) -> ClientResult<LookupStatus> {
+        self.transport
+            .get(
+                &Self::path(STATUS_PATH, tier, name),
+                &[("detailed", "true".to_string())],
+            )
+            .await
+    }
+
+    /// Load status of every lookup, by tier and name.
+    pub async fn all_status(&self) -> ClientResult<HashMap<String, HashMap<String, LookupStatus>>> {
+        self.transport.get(STATUS_PATH, &[]).await
+    }
+
+    fn path(base: &str, tier: &str, name: &str) -> String {
+        format!("{}/{}/{}", base, encode_segment(tier), encode_segment(name))
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        path: String,
+        body: Option<String>,
+        audit: &AuditInfo,
+    ) -> ClientResult<()> {
+        self.transport
+            .send(method, &path, &[], &audit.headers(), body)
+            .await
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,187 @@
+use super::admin::{encode_segment, parse, AdminTransport};
+use super::overlord::OverlordClient;
+use super::{ClientResult, DruidClientError};
+use crate::ingestion::msq::{MsqTaskReport, SqlStatement, SqlStatementState, SqlTaskSubmitted};
+use crate::query::sql::SqlQuery;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use std::time::{Duration, Instant};
+
+const SQL_TASK_PATH: &str = "/druid/v2/sql/task";
+const STATEMENTS_PATH: &str = "/druid/v2/sql/statements";
+const EXECUTION_MODE: &str = "executionMode";
+
+/// Client of the multi-stage query task engine: SQL-based ingestion (`INSERT`/`REPLACE`) and
+/// asynchronous `SELECT` statements. Queries are accepted by the brokers and run as overlord
+/// tasks, so the client needs the url of a router. Built with
+/// [`AdminClientBuilder::msq`](super::AdminClientBuilder::msq).
+#[derive(Clone)]
+pub struct MsqClient {
+    transport: AdminTransport,
+}
+
+impl MsqClient {
+    pub(crate) fn new(transport: AdminTransport) -> Self {
+        MsqClient { transport }
+    }
+
+    fn overlord(&self) -> OverlordClient {
+        OverlordClient::new(self.transport.clone())
+    }
+
+    /// Submits an `INSERT` or `REPLACE` statement, or a `SELECT` whose results go to the task
+    /// report, and returns the id of its controller task. Context keys such as `maxNumTasks`
+    /// and `finalizeAggregations` are set on the query.
+    pub async fn submit_task(&self, query: &SqlQuery) -> ClientResult<String> {
+        let submitted: SqlTaskSubmitted = self.transport.post(SQL_TASK_PATH, query).await?;
+        Ok(submitted.task_id)
+    }
+
+    /// The `multiStageQuery` report of the task, missing for tasks that aren't multi-stage
+    /// queries.
+    pub async fn task_report(&self, task_id: &str) -> ClientResult<MsqTaskReport> {
+        self.overlord()
+            .task_report(task_id)
+            .await?
+            .multi_stage_query
+            .ok_or_else(|| DruidClientError::MissingTaskReport {
+                task_id: task_id.to_string(),
+                report: "multiStageQuery".into(),
+            })
+    }
+
+    /// Polls the controller task every `poll_interval` until it completes and returns its
+    /// report. A failed query is returned as [`DruidClientError::MsqFailed`] with the fault
+    /// of the report, one still running after `timeout` as [`DruidClientError::TaskTimeout`].
+    pub async fn wait_for_task(
+        &self,
+        task_id: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> ClientResult<MsqTaskReport> {
+        match self
+            .overlord()
+            .wait_for_task(task_id, poll_interval, timeout)
+            .await
+        {
+            Ok(_) => self.task_report(task_id).await,
+            Err(DruidClientError::TaskFailed { error_msg, .. }) => {
+                // the report carries the error code, the task status only a message
+                let report = self.task_report(task_id).await.ok();
+                let error = report
+                    .as_ref()
+                    .and_then(|report| report.payload.status.error_report.as_ref())
+                    .map(|error_report| Box::new(error_report.error.clone()));
+                Err(DruidClientError::MsqFailed {
+                    query_id: task_id.to_string(),
+                    error_msg: error.as_ref().map(|error| error.to_string()).or(error_msg),
+                    error,
+                    report: report.map(Box::new),
+                })
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Submits a statement to the asynchronous SQL API, `SELECT` results are kept by druid and
+    /// fetched by pages with [`MsqClient::statement_results`].
+    pub async fn submit_statement(&self, query: &SqlQuery) -> ClientResult<SqlStatement> {
+        let mut query = query.clone();
+        if query.context.get(EXECUTION_MODE).is_none() {
+            query.context.insert(EXECUTION_MODE, "ASYNC");
+        }
+        self.transport.post(STATEMENTS_PATH, &query).await
+    }
+
+    pub async fn statement_status(&self, query_id: &str) -> ClientResult<SqlStatement> {
+        let path = format!("{}/{}", STATEMENTS_PATH, encode_segment(query_id));
+        self.transport.get(&path, &[]).await
+    }
+
+    /// Rows of one page of the results of a successful statement.
+    pub async fn statement_results<T: DeserializeOwned>(
+        &self,
+        query_id: &str,
+        page: u32,
+    ) -> ClientResult<Vec<T>> {
+        let path = format!("{}/{}/results", STATEMENTS_PATH, encode_segment(query_id));
+        let query = [
+            ("page", page.to_string()),
+            ("resultFormat", "object".to_string()),
+        ];
+        let response = self
+            .transport
+            .send(Method::GET, &path, &query, &[], None)
+            .await?;
+        parse::<Option<Vec<T>>>(&response).map(Option::unwrap_or_default)
+    }
+
+    /// Cancels a running statement.
+    pub async fn cancel_statement(&self, query_id: &str) -> ClientResult<()> {
+        let path = format!("{}/{}", STATEMENTS_PATH, encode_segment(query_id));
+        self.transport
+            .send(Method::DELETE, &path, &[], &[], None)
+            .await
+            .map(|_| ())
+    }
+
+    /// Polls the statement every `poll_interval` until it succeeds, failures are returned as
+    /// [`DruidClientError::MsqFailed`].
+    pub async fn wait_for_statement(
+        &self,
+        query_id: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> ClientResult<SqlStatement> {
+        let started = Instant::now();
+        loop {
+            let statement = self.statement_status(query_id).await?;
+            match statement.state {
+                SqlStatementState::Success => return Ok(statement),
+                SqlStatementState::Failed => {
+                    let error = statement.error_details.map(Box::new);
+                    return Err(DruidClientError::MsqFailed {
+                        query_id: query_id.to_string(),
+                        error_msg: error.as_ref().map(|error| error.to_string()),
+                        error,
+                        report: None,
+                    });
+                }
+                _ => {}
+            }
+            if started.elapsed() + poll_interval > timeout {
+                return Err(DruidClientError::TaskTimeout {
+                    task_id: query_id.to_string(),
+                    timeout,
+                });
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Runs a `SELECT` through the asynchronous SQL API and reads every page of its results.
+    pub async fn select<T: DeserializeOwned>(
+        &self,
+        query: &SqlQuery,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> ClientResult<Vec<T>> {
+        let statement = self.submit_statement(query).await?;
+        let statement = match statement.state {
+            SqlStatementState::Success => statement,
+            _ => {
+                self.wait_for_statement(&statement.query_id, poll_interval, timeout)
+                    .await?
+            }
+        };
+        let pages = statement
+            .result
+            .map(|result| result.pages)
+            .unwrap_or_default();
+        let mut rows = vec![];
+        for page in pages {
+            rows.extend(self.statement_results(&statement.query_id, page.id).await?);
+        }
+        Ok(rows)
+    }
+}
@@ -0,0 +1,178 @@
+use super::admin::{encode_segment, AdminTransport};
+use super::{ClientResult, DruidClientError};
+use crate::ingestion::inline::{InlineIngestion, JsonLinesChunks};
+use crate::ingestion::response::{
+    TaskReport, TaskState, TaskStatus, TaskStatusCode, TaskStatusResponse,
+};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+const TASK_PATH: &str = "/druid/indexer/v1/task";
+const TASKS_PATH: &str = "/druid/indexer/v1/tasks";
+
+#[derive(Deserialize, Debug)]
+struct TaskSubmitted {
+    task: String,
+}
+
+/// Client of the overlord, which runs ingestion tasks. Built with
+/// [`AdminClientBuilder::overlord`](super::AdminClientBuilder::overlord).
+#[derive(Clone)]
+pub struct OverlordClient {
+    transport: AdminTransport,
+}
+
+impl OverlordClient {
+    pub(crate) fn new(transport: AdminTransport) -> Self {
+        OverlordClient { transport }
+    }
+
+    /// Submits a task, e.g. an [`IndexParallelTask`](crate::ingestion::IndexParallelTask), and
+    /// returns its id.
+    pub async fn submit_task<T: Serialize>(&self, task: &T) -> ClientResult<String> {
+        let submitted: TaskSubmitted = self.transport.post(TASK_PATH, task).await?;
+        Ok(submitted.task)
+    }
+
+    pub async fn task_status(&self, task_id: &str) -> ClientResult<TaskStatus> {
+        let path = format!("{}/{}/status", TASK_PATH, encode_segment(task_id));
+        let response: TaskStatusResponse = self.transport.get(&path, &[]).await?;
+        Ok(response.status)
+    }
+
+    /// Reports of a completed task, with the row counts and parse errors of ingestion tasks.
+    pub async fn task_report(&self, task_id: &str) -> ClientResult<TaskReport> {
+        let path = format!("{}/{}/reports", TASK_PATH, encode_segment(task_id));
+        self.transport.get(&path, &[]).await
+    }
+
+    /// Log of the task starting at byte `offset`, a negative offset reads the last bytes.
+    pub async fn task_log(&self, task_id: &str, offset: i64) -> ClientResult<String> {
+        let path = format!("{}/{}/log", TASK_PATH, encode_segment(task_id));
+        self.transport
+            .send(
+                Method::GET,
+                &path,
+                &[("offset", offset.to_string())],
+                &[],
+                None,
+            )
+            .await
+    }
+
+    pub async fn shutdown_task(&self, task_id: &str) -> ClientResult<()> {
+        let path = format!("{}/{}/shutdown", TASK_PATH, encode_segment(task_id));
+        self.transport
+            .send(Method::POST, &path, &[], &[], None)
+            .await
+            .map(|_| ())
+    }
+
+    /// Tasks known to the overlord, optionally only those in `state` or of `data_source`.
+    pub async fn list_tasks(
+        &self,
+        state: Option<TaskState>,
+        data_source: Option<&str>,
+    ) -> ClientResult<Vec<TaskStatus>> {
+        let mut query = vec![];
+        if let Some(state) = state {
+            query.push(("state", state.as_str().to_string()));
+        }
+        if let Some(data_source) = data_source {
+            query.push(("datasource", data_source.to_string()));
+        }
+        self.transport.get(TASKS_PATH, &query).await
+    }
+
+    /// Polls the task every `poll_interval` until it completes. A failed task is returned as
+    /// [`DruidClientError::TaskFailed`], one still running after `timeout` as
+    /// [`DruidClientError::TaskTimeout`] (the task keeps running).
+    pub async fn wait_for_task(
+        &self,
+        task_id: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> ClientResult<TaskStatus> {
+        let started = Instant::now();
+        loop {
+            let status = self.task_status(task_id).await?;
+            match status.code() {
+                Some(TaskStatusCode::Success) => return Ok(status),
+                Some(TaskStatusCode::Failed) => {
+                    return Err(DruidClientError::TaskFailed {
+                        task_id: task_id.to_string(),
+                        error_msg: status.error_msg.clone(),
+                        status: Box::new(status),
+                    })
+                }
+                _ => {}
+            }
+            if started.elapsed() + poll_interval > timeout {
+                return Err(DruidClientError::TaskTimeout {
+                    task_id: task_id.to_string(),
+                    timeout,
+                });
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Ingests in-memory rows through `inline` input sources and waits for the tasks to
+    /// succeed. Rows are split over several tasks according to
+    /// [`InlineIngestion::max_bytes_per_task`], each submitted once the previous one succeeded,
+    /// and the statuses of the tasks are returned in order. When a task fails after others
+    /// were committed, the error is a [`DruidClientError::PartialIngestion`] holding their
+    /// statuses. Ingestions with perfect rollup must fit in one task, see
+    /// [`InlineIngestion::max_bytes_per_task`].
+    pub async fn ingest_rows<T, I>(
+        &self,
+        rows: I,
+        ingestion: &InlineIngestion,
+    ) -> ClientResult<Vec<TaskStatus>>
+    where
+        T: Serialize,
+        I: IntoIterator<Item = T>,
+    {
+        let template = ingestion
+            .task_template()
+            .map_err(|source| DruidClientError::ParsingError { source })?;
+        let mut chunks = JsonLinesChunks::new(rows.into_iter(), ingestion.max_bytes_per_task);
+        // rows that must go in one task are read up to a second chunk before submitting it
+        let head: Vec<_> = if ingestion.guarantees_rollup() {
+            chunks.by_ref().take(2).collect()
+        } else {
+            vec![]
+        };
+        if head.len() > 1 {
+            return Err(DruidClientError::IngestionTooLarge {
+                max_bytes_per_task: ingestion.max_bytes_per_task,
+            });
+        }
+        let chunks = head.into_iter().chain(chunks);
+
+        let mut statuses = vec![];
+        for chunk in chunks {
+            let ingested = async {
+                let data = chunk.map_err(|source| DruidClientError::ParsingError { source })?;
+                // later chunks must not replace what the previous tasks ingested
+                let append_to_existing = ingestion.append_to_existing || !statuses.is_empty();
+                let task = InlineIngestion::task(&template, data, append_to_existing);
+                let task_id = self.submit_task(&task).await?;
+                self.wait_for_task(&task_id, ingestion.poll_interval, ingestion.timeout)
+                    .await
+            };
+            match ingested.await {
+                Ok(status) => statuses.push(status),
+                Err(err) if statuses.is_empty() => return Err(err),
+                Err(err) => {
+                    return Err(DruidClientError::PartialIngestion {
+                        completed: statuses,
+                        source: Box::new(err),
+                    })
+                }
+            }
+        }
+        Ok(statuses)
+    }
+}
@@ -0,0 +1,196 @@
+use super::{is_connect_error, ClientResult, DruidClientError};
+use rand::Rng;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A failed attempt of a request, as reported to [`RetryPolicy::on_attempt`].
+#[derive(Debug)]
+pub struct RetryAttempt<'a> {
+    /// 1 for the first attempt.
+    pub attempt: u32,
+    pub error: &'a DruidClientError,
+    /// Delay before the next attempt, `None` when the error is returned to the caller.
+    pub retry_in: Option<Duration>,
+}
+
+type AttemptHook = Arc<dyn Fn(&RetryAttempt) + Send + Sync>;
+
+/// How failed queries are retried. Only failures worth another try are retried: connection
+/// errors, `502`/`503`/`504` responses and exceeded query capacity, see
+/// [`DruidClientError::is_retryable`]. Requests that aren't idempotent, like SQL `INSERT` or
+/// `REPLACE`, are only retried when the broker couldn't be connected to, as a `504` doesn't
+/// mean the statement didn't run.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    deadline: Option<Duration>,
+    on_attempt: Option<AttemptHook>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new(3)
+    }
+}
+
+impl RetryPolicy {
+    /// Policy making at most `max_attempts` attempts (the first one included), waiting 100ms
+    /// then doubling up to 10s between them.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            deadline: None,
+            on_attempt: None,
+        }
+    }
+
+    /// Backoff before the first retry and the cap it doubles up to. A random jitter of up to
+    /// half the backoff is taken off each delay so clients don't retry in lockstep.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Total time after which no new attempt is started, counted from the first attempt. An
+    /// attempt in flight isn't interrupted, the query timeout bounds it.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Called after every failed attempt, e.g. for logging or metrics.
+    pub fn on_attempt<F: Fn(&RetryAttempt) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.on_attempt = Some(Arc::new(hook));
+        self
+    }
+
+    fn backoff_for(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        let jitter = rand::thread_rng().gen_range(0.0..0.5);
+        backoff.mul_f64(1.0 - jitter)
+    }
+
+    pub(crate) async fn run<T, F, Fut>(&self, idempotent: bool, mut request: F) -> ClientResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let started = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let error = match request().await {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+            let backoff = self.backoff_for(attempt - 1);
+            let within_deadline = match self.deadline {
+                Some(deadline) => started.elapsed() + backoff < deadline,
+                None => true,
+            };
+            let retryable = if idempotent {
+                error.is_retryable()
+            } else {
+                matches!(&error, DruidClientError::HttpConnection { source } if is_connect_error(source))
+            };
+            let retry_in =
+                (attempt < self.max_attempts && within_deadline && retryable).then_some(backoff);
+            if let Some(hook) = &self.on_attempt {
+                hook(&RetryAttempt {
+                    attempt,
+                    error: &error,
+                    retry_in,
+                });
+            }
+            match retry_in {
+                Some(backoff) => tokio::time::sleep(backoff).await,
+                None => return Err(error),
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::query::response::DruidError;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn capacity_exceeded() -> DruidClientError {
+        DruidClientError::from_response(
+            429,
+            r#"{"error":"Query capacity exceeded","errorMessage":"Too many concurrent queries"}"#
+                .into(),
+        )
+    }
+
+    #[test]
+    fn test_retry_classification() {
+        assert!(capacity_exceeded().is_retryable());
+        assert!(DruidClientError::from_response(503, "Service Unavailable".into()).is_retryable());
+        assert!(!DruidClientError::from_response(500, "oops".into()).is_retryable());
+        assert!(!DruidClientError::ResourceLimitExceeded {
+            status: 400,
+            error: Box::new(DruidError::default()),
+        }
+        .is_retryable());
+        let parse_error =
+            DruidClientError::from_response(400, r#"{"error":"SQL parse failed"}"#.into());
+        assert!(!parse_error.is_retryable());
+    }
+
+    #[test]
+    fn test_retries_until_max_attempts() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let hook_attempts = attempts.clone();
+        let policy = RetryPolicy::new(3)
+            .backoff(Duration::from_millis(1), Duration::from_millis(2))
+            .on_attempt(move |attempt| {
+                hook_attempts.fetch_add(1, Ordering::Relaxed);
+                assert_eq!(attempt.retry_in.is_some(), attempt.attempt < 3);
+            });
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let result: ClientResult<()> =
+            runtime.block_on(policy.run(true, || async { Err(capacity_exceeded()) }));
+        assert!(matches!(
+            result,
+            Err(DruidClientError::QueryCapacityExceeded { .. })
+        ));
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_no_retry_when_not_idempotent() {
+        let attempts = AtomicU32::new(0);
+        let policy =
+            RetryPolicy::new(3).backoff(Duration::from_millis(1), Duration::from_millis(2));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let result: ClientResult<()> = runtime.block_on(policy.run(false, || async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            Err(DruidClientError::from_response(
+                504,
+                "Gateway Timeout".into(),
+            ))
+        }));
+        assert!(matches!(
+            result,
+            Err(DruidClientError::ServerError { status: 504, .. })
+        ));
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+}
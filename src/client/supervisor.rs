@@ -0,0 +1,88 @@
+use super::admin::{encode_segment, AdminTransport};
+use super::ClientResult;
+use crate::ingestion::response::{SupervisorStatus, SupervisorSummary};
+use crate::ingestion::supervisor::SupervisorSpec;
+use reqwest::Method;
+use serde::Deserialize;
+
+const SUPERVISOR_PATH: &str = "/druid/indexer/v1/supervisor";
+
+#[derive(Deserialize, Debug)]
+struct SupervisorCreated {
+    id: String,
+}
+
+/// Client of the overlord's supervisor API, which manages streaming ingestion. Built with
+/// [`AdminClientBuilder::supervisors`](super::AdminClientBuilder::supervisors).
+#[derive(Clone)]
+pub struct SupervisorClient {
+    transport: AdminTransport,
+}
+
+impl SupervisorClient {
+    pub(crate) fn new(transport: AdminTransport) -> Self {
+        SupervisorClient { transport }
+    }
+
+    /// Creates the supervisor of the spec's datasource, or replaces its spec, and returns its id.
+    /// Running tasks of a replaced spec publish their segments before new ones start.
+    pub async fn create_or_update(&self, spec: &SupervisorSpec) -> ClientResult<String> {
+        let created: SupervisorCreated = self.transport.post(SUPERVISOR_PATH, spec).await?;
+        Ok(created.id)
+    }
+
+    pub async fn status(&self, supervisor_id: &str) -> ClientResult<SupervisorStatus> {
+        self.transport
+            .get(&self.path(supervisor_id, "status"), &[])
+            .await
+    }
+
+    /// Supervisors with their state, including suspended ones.
+    pub async fn list(&self) -> ClientResult<Vec<SupervisorSummary>> {
+        self.transport
+            .get(SUPERVISOR_PATH, &[("state", "true".to_string())])
+            .await
+    }
+
+    /// Stops the tasks, which publish their segments, until the supervisor is resumed.
+    pub async fn suspend(&self, supervisor_id: &str) -> ClientResult<()> {
+        self.post_action(supervisor_id, "suspend").await
+    }
+
+    pub async fn resume(&self, supervisor_id: &str) -> ClientResult<()> {
+        self.post_action(supervisor_id, "resume").await
+    }
+
+    /// Forgets the stored offsets: reading restarts from the earliest or latest offsets
+    /// depending on the spec, which can skip or duplicate data.
+    pub async fn reset(&self, supervisor_id: &str) -> ClientResult<()> {
+        self.post_action(supervisor_id, "reset").await
+    }
+
+    /// Stops the supervisor and its tasks for good, the ingested data is kept.
+    pub async fn terminate(&self, supervisor_id: &str) -> ClientResult<()> {
+        self.post_action(supervisor_id, "terminate").await
+    }
+
+    fn path(&self, supervisor_id: &str, action: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            SUPERVISOR_PATH,
+            encode_segment(supervisor_id),
+            action
+        )
+    }
+
+    async fn post_action(&self, supervisor_id: &str, action: &str) -> ClientResult<()> {
+        self.transport
+            .send(
+                Method::POST,
+                &self.path(supervisor_id, action),
+                &[],
+                &[],
+                None,
+            )
+            .await
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,122 @@
+use crate::ingestion::{DimensionsSpec, TuningConfig};
+use crate::query::definitions::{Aggregation, Granularity};
+use crate::serialization::default_for_null;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// [Automatic compaction](https://druid.apache.org/docs/latest/data-management/automatic-compaction.html)
+/// of a datasource, run by the coordinator.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataSourceCompactionConfig {
+    pub data_source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_priority: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_segment_size_bytes: Option<u64>,
+    /// ISO 8601 period of recent data left alone, `P1D` by default, so that compaction
+    /// doesn't race with ingestion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_offset_from_latest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tuning_config: Option<TuningConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub granularity_spec: Option<CompactionGranularitySpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimensions_spec: Option<DimensionsSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_spec: Option<Vec<Aggregation>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io_config: Option<CompactionIoConfig>,
+    #[serde(
+        default,
+        deserialize_with = "default_for_null",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub task_context: HashMap<String, Value>,
+}
+
+impl DataSourceCompactionConfig {
+    pub fn new(data_source: &str) -> Self {
+        DataSourceCompactionConfig {
+            data_source: data_source.to_string(),
+            task_priority: None,
+            input_segment_size_bytes: None,
+            skip_offset_from_latest: None,
+            tuning_config: None,
+            granularity_spec: None,
+            dimensions_spec: None,
+            metrics_spec: None,
+            io_config: None,
+            task_context: HashMap::new(),
+        }
+    }
+}
+
+/// Granularities the compacted segments are rewritten with, those of the input segments when
+/// absent.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactionGranularitySpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segment_granularity: Option<Granularity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_granularity: Option<Granularity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollup: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactionIoConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drop_existing: Option<bool>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ingestion::PartitionsSpec;
+
+    #[test]
+    fn test_compaction_config() {
+        let mut config = DataSourceCompactionConfig::new("wikipedia");
+        config.skip_offset_from_latest = Some("PT1H".into());
+        config.tuning_config = Some(TuningConfig {
+            partitions_spec: Some(PartitionsSpec::Dynamic {
+                max_rows_per_segment: Some(5_000_000),
+                max_total_rows: None,
+            }),
+            ..Default::default()
+        });
+        config.granularity_spec = Some(CompactionGranularitySpec {
+            segment_granularity: Some(Granularity::day()),
+            ..Default::default()
+        });
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            serde_json::json!({
+                "dataSource": "wikipedia",
+                "skipOffsetFromLatest": "PT1H",
+                "tuningConfig": {
+                    "type": "index_parallel",
+                    "partitionsSpec": {"type": "dynamic", "maxRowsPerSegment": 5000000}
+                },
+                "granularitySpec": {"segmentGranularity": "day"}
+            })
+        );
+
+        let config: DataSourceCompactionConfig = serde_json::from_str(
+            r#"{"dataSource":"wikipedia","taskPriority":25,"inputSegmentSizeBytes":100000000000000,"maxRowsPerSegment":null,"skipOffsetFromLatest":"P1D","tuningConfig":null,"granularitySpec":{"segmentGranularity":{"type":"period","period":"P1D","timeZone":"UTC","origin":null},"queryGranularity":null,"rollup":null},"dimensionsSpec":null,"metricsSpec":null,"transformSpec":null,"ioConfig":null,"taskContext":null}"#,
+        )
+        .unwrap();
+        assert_eq!(config.task_priority, Some(25));
+        assert!(config.task_context.is_empty());
+        assert!(config
+            .granularity_spec
+            .unwrap()
+            .segment_granularity
+            .is_some());
+    }
+}
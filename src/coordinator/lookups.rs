@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A [lookup](https://druid.apache.org/docs/latest/querying/lookups.html) as registered for a
+/// tier. Nodes reload it when `version` changes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LookupSpec {
+    pub version: String,
+    pub lookup_extractor_factory: LookupExtractorFactory,
+}
+
+impl LookupSpec {
+    pub fn new(version: &str, lookup_extractor_factory: LookupExtractorFactory) -> Self {
+        LookupSpec {
+            version: version.to_string(),
+            lookup_extractor_factory,
+        }
+    }
+}
+
+/// Where the key/value pairs of a lookup come from. `injective` declares that no two keys map
+/// to the same value, which lets druid optimize group by queries on the lookup.
+#[rustfmt::skip]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum LookupExtractorFactory {
+    /// Pairs held in the spec itself.
+    Map { map: HashMap<String, String>, #[serde(default, skip_serializing_if = "Option::is_none")] injective: Option<bool> },
+    /// Pairs polled from a file or a database table (`druid-lookups-cached-global` extension).
+    #[serde(rename_all = "camelCase")]
+    CachedNamespace {
+        extraction_namespace: ExtractionNamespace,
+        /// Milliseconds to wait for the first load, 0 to not wait.
+        #[serde(default, skip_serializing_if = "Option::is_none")] first_cache_timeout: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] injective: Option<bool>,
+    },
+    /// Pairs read from a compacted kafka topic (`druid-kafka-extraction-namespace` extension).
+    #[serde(rename_all = "camelCase")]
+    Kafka {
+        kafka_topic: String,
+        kafka_properties: HashMap<String, String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] connect_timeout: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] injective: Option<bool>,
+    },
+}
+
+impl LookupExtractorFactory {
+    pub fn map(map: HashMap<String, String>) -> Self {
+        LookupExtractorFactory::Map {
+            map,
+            injective: None,
+        }
+    }
+    pub fn cached_namespace(extraction_namespace: ExtractionNamespace) -> Self {
+        LookupExtractorFactory::CachedNamespace {
+            extraction_namespace,
+            first_cache_timeout: None,
+            injective: None,
+        }
+    }
+    pub fn kafka(kafka_topic: &str, bootstrap_servers: &str) -> Self {
+        LookupExtractorFactory::Kafka {
+            kafka_topic: kafka_topic.to_string(),
+            kafka_properties: HashMap::from([(
+                "bootstrap.servers".to_string(),
+                bootstrap_servers.to_string(),
+            )]),
+            connect_timeout: None,
+            injective: None,
+        }
+    }
+}
+
+/// Source of a `cachedNamespace` lookup. `poll_period` is an ISO 8601 period (`PT10M`), the
+/// source is loaded once when absent.
+#[rustfmt::skip]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum ExtractionNamespace {
+    /// A file, either `uri` or the most recent file under `uri_prefix` matching `file_regex`.
+    #[serde(rename_all = "camelCase")]
+    Uri {
+        #[serde(default, skip_serializing_if = "Option::is_none")] uri: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] uri_prefix: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] file_regex: Option<String>,
+        namespace_parse_spec: NamespaceParseSpec,
+        #[serde(default, skip_serializing_if = "Option::is_none")] poll_period: Option<String>,
+    },
+    /// Two columns of a database table.
+    #[serde(rename_all = "camelCase")]
+    Jdbc {
+        connector_config: JdbcConnectorConfig,
+        table: String,
+        key_column: String,
+        value_column: String,
+        /// SQL condition selecting the rows.
+        #[serde(default, skip_serializing_if = "Option::is_none")] filter: Option<String>,
+        /// Column of the rows' update time, only rows updated since the last poll are read.
+        #[serde(default, skip_serializing_if = "Option::is_none")] ts_column: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] poll_period: Option<String>,
+    },
+}
+
+impl ExtractionNamespace {
+    pub fn uri(uri: &str, namespace_parse_spec: NamespaceParseSpec) -> Self {
+        ExtractionNamespace::Uri {
+            uri: Some(uri.to_string()),
+            uri_prefix: None,
+            file_regex: None,
+            namespace_parse_spec,
+            poll_period: None,
+        }
+    }
+    pub fn jdbc(
+        connector_config: JdbcConnectorConfig,
+        table: &str,
+        key_column: &str,
+        value_column: &str,
+    ) -> Self {
+        ExtractionNamespace::Jdbc {
+            connector_config,
+            table: table.to_string(),
+            key_column: key_column.to_string(),
+            value_column: value_column.to_string(),
+            filter: None,
+            ts_column: None,
+            poll_period: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JdbcConnectorConfig {
+    #[serde(rename = "connectURI")]
+    pub connect_uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// A plain string or a password provider, e.g. `{"type": "environment", "variable": "DB_PASSWORD"}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<Value>,
+}
+
+impl JdbcConnectorConfig {
+    pub fn new(connect_uri: &str) -> Self {
+        JdbcConnectorConfig {
+            connect_uri: connect_uri.to_string(),
+            user: None,
+            password: None,
+        }
+    }
+}
+
+/// Format of a `uri` lookup file.
+#[rustfmt::skip]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "format")]
+#[serde(rename_all = "camelCase")]
+pub enum NamespaceParseSpec {
+    #[serde(rename_all = "camelCase")]
+    Csv {
+        columns: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] key_column: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] value_column: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] skip_header_rows: Option<usize>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Tsv {
+        columns: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] key_column: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] value_column: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] delimiter: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] skip_header_rows: Option<usize>,
+    },
+    /// One `{"key": "value"}` object per line.
+    SimpleJson,
+    #[serde(rename_all = "camelCase")]
+    CustomJson { key_field_name: String, value_field_name: String },
+}
+
+/// Whether a lookup is loaded on all the nodes of its tier, `pending_nodes` is only filled in
+/// by [`LookupsClient::status`](crate::client::LookupsClient::status).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LookupStatus {
+    pub loaded: bool,
+    #[serde(default)]
+    pub pending_nodes: Vec<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lookup_specs() {
+        let mut connector = JdbcConnectorConfig::new("jdbc:postgresql://localhost:5432/app");
+        connector.user = Some("druid".into());
+        let spec = LookupSpec::new(
+            "v1",
+            LookupExtractorFactory::cached_namespace(ExtractionNamespace::jdbc(
+                connector,
+                "countries",
+                "code",
+                "name",
+            )),
+        );
+        assert_eq!(
+            serde_json::to_value(&spec).unwrap(),
+            serde_json::json!({
+                "version": "v1",
+                "lookupExtractorFactory": {
+                    "type": "cachedNamespace",
+                    "extractionNamespace": {
+                        "type": "jdbc",
+                        "connectorConfig": {"connectURI": "jdbc:postgresql://localhost:5432/app", "user": "druid"},
+                        "table": "countries",
+                        "keyColumn": "code",
+                        "valueColumn": "name"
+                    }
+                }
+            })
+        );
+
+        let specs: HashMap<String, LookupSpec> = serde_json::from_str(
+            r#"{
+                "country_names": {"version": "v2", "lookupExtractorFactory": {"type": "map", "map": {"FR": "France"}, "injective": true}},
+                "pages": {"version": "v1", "lookupExtractorFactory": {"type": "cachedNamespace", "extractionNamespace": {"type": "uri", "uri": "s3://bucket/pages.json", "namespaceParseSpec": {"format": "customJson", "keyFieldName": "id", "valueFieldName": "title"}, "pollPeriod": "PT10M"}, "firstCacheTimeout": 0}},
+                "users": {"version": "v1", "lookupExtractorFactory": {"type": "kafka", "kafkaTopic": "users", "kafkaProperties": {"bootstrap.servers": "kafka:9092"}}}
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            &specs["country_names"].lookup_extractor_factory,
+            LookupExtractorFactory::Map { map, injective: Some(true) } if map["FR"] == "France"
+        ));
+        assert!(matches!(
+            &specs["pages"].lookup_extractor_factory,
+            LookupExtractorFactory::CachedNamespace {
+                extraction_namespace: ExtractionNamespace::Uri {
+                    namespace_parse_spec: NamespaceParseSpec::CustomJson { .. },
+                    ..
+                },
+                ..
+            }
+        ));
+        assert!(matches!(
+            &specs["users"].lookup_extractor_factory,
+            LookupExtractorFactory::Kafka { kafka_topic, .. } if kafka_topic == "users"
+        ));
+    }
+}
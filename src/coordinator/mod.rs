@@ -0,0 +1,202 @@
+//! Types of the coordinator's API (`/druid/coordinator/v1`), used by
+//! [`CoordinatorClient`](crate::client::CoordinatorClient).
+
+use crate::query::definitions::Interval;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub mod compaction;
+pub mod lookups;
+pub mod rules;
+
+pub use compaction::{CompactionGranularitySpec, CompactionIoConfig, DataSourceCompactionConfig};
+pub use lookups::{
+    ExtractionNamespace, JdbcConnectorConfig, LookupExtractorFactory, LookupSpec, LookupStatus,
+    NamespaceParseSpec,
+};
+pub use rules::Rule;
+
+/// Author and comment recorded in the coordinator's audit log for a configuration change.
+#[derive(Debug, Clone, Default)]
+pub struct AuditInfo {
+    pub author: String,
+    pub comment: String,
+}
+
+impl AuditInfo {
+    pub fn new(author: &str, comment: &str) -> Self {
+        AuditInfo {
+            author: author.to_string(),
+            comment: comment.to_string(),
+        }
+    }
+
+    pub(crate) fn headers(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("X-Druid-Author", self.author.clone()),
+            ("X-Druid-Comment", self.comment.clone()),
+        ]
+    }
+}
+
+/// Segment counts and sizes of a datasource, from `GET /datasources?simple`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DataSourceSummary {
+    pub name: String,
+    pub properties: DataSourceProperties,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DataSourceProperties {
+    /// Segments loaded on the historicals of each tier.
+    #[serde(default)]
+    pub tiers: HashMap<String, TierSegments>,
+    pub segments: SegmentsSummary,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TierSegments {
+    #[serde(default)]
+    pub segment_count: u64,
+    /// Size in bytes of one copy of the segments.
+    #[serde(default)]
+    pub size: u64,
+    /// Size in bytes of all the replicas.
+    #[serde(default)]
+    pub replicated_size: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentsSummary {
+    #[serde(default)]
+    pub count: u64,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub replicated_size: u64,
+    #[serde(default)]
+    pub min_time: Option<String>,
+    #[serde(default)]
+    pub max_time: Option<String>,
+}
+
+/// Metadata of a published segment.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DataSegment {
+    pub data_source: String,
+    pub interval: String,
+    pub version: String,
+    /// Location of the segment in deep storage, its fields depend on the storage type.
+    #[serde(default)]
+    pub load_spec: HashMap<String, Value>,
+    #[serde(default, with = "crate::serialization::comma_separated")]
+    pub dimensions: Vec<String>,
+    #[serde(default, with = "crate::serialization::comma_separated")]
+    pub metrics: Vec<String>,
+    pub shard_spec: ShardSpec,
+    #[serde(default)]
+    pub binary_version: Option<u32>,
+    /// Size in bytes.
+    pub size: u64,
+    pub identifier: String,
+}
+
+/// How the segments of a time chunk are partitioned. Fields specific to the partitioning type
+/// (`hashed`, `range`...) are kept in `other`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardSpec {
+    #[serde(rename = "type")]
+    pub shard_type: String,
+    #[serde(default)]
+    pub partition_num: Option<u32>,
+    #[serde(default)]
+    pub partitions: Option<u32>,
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+
+/// Segments of a datasource to mark used or unused.
+#[rustfmt::skip]
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum SegmentSelection {
+    Interval { interval: Interval },
+    #[serde(rename_all = "camelCase")]
+    SegmentIds { segment_ids: Vec<String> },
+}
+
+impl SegmentSelection {
+    pub fn interval(interval: Interval) -> Self {
+        SegmentSelection::Interval { interval }
+    }
+    pub fn segment_ids(segment_ids: Vec<String>) -> Self {
+        SegmentSelection::SegmentIds { segment_ids }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SegmentsChanged {
+    pub(crate) num_changed_segments: u64,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_deserialize_segment() {
+        let segment: DataSegment = serde_json::from_str(
+            r#"{"dataSource":"wikipedia","interval":"2015-09-12T00:00:00.000Z/2015-09-13T00:00:00.000Z","version":"2021-01-01T00:00:00.000Z","loadSpec":{"type":"local","path":"/opt/druid/var/druid/segments/wikipedia/index.zip"},"dimensions":"channel,page,user","metrics":"count,added","shardSpec":{"type":"numbered","partitionNum":0,"partitions":1},"binaryVersion":9,"size":4820000,"identifier":"wikipedia_2015-09-12T00:00:00.000Z_2015-09-13T00:00:00.000Z_2021-01-01T00:00:00.000Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(segment.dimensions, vec!["channel", "page", "user"]);
+        assert_eq!(segment.metrics, vec!["count", "added"]);
+        assert_eq!(segment.shard_spec.partition_num, Some(0));
+        assert_eq!(
+            serde_json::to_value(&segment).unwrap()["dimensions"],
+            "channel,page,user"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_data_source_summary() {
+        let summaries: Vec<DataSourceSummary> = serde_json::from_str(
+            r#"[{"name":"wikipedia","properties":{"tiers":{"_default_tier":{"size":4820000,"segmentCount":1,"replicatedSize":9640000}},"segments":{"maxTime":"2015-09-13T00:00:00.000Z","size":4820000,"minTime":"2015-09-12T00:00:00.000Z","count":1,"replicatedSize":9640000}}}]"#,
+        )
+        .unwrap();
+        let properties = &summaries[0].properties;
+        assert_eq!(properties.segments.count, 1);
+        assert_eq!(properties.tiers["_default_tier"].replicated_size, 9640000);
+    }
+
+    #[test]
+    fn test_serialize_segment_selection() {
+        let selection = SegmentSelection::interval(Interval {
+            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            to: NaiveDate::from_ymd_opt(2015, 9, 13)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        });
+        assert_eq!(
+            serde_json::to_value(&selection).unwrap(),
+            serde_json::json!({"interval": "2015-09-12T00:00:00/2015-09-13T00:00:00"})
+        );
+        assert_eq!(
+            serde_json::to_value(SegmentSelection::segment_ids(vec!["a".into()])).unwrap(),
+            serde_json::json!({"segmentIds": ["a"]})
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// [Retention rule](https://druid.apache.org/docs/latest/operations/rule-configuration.html)
+/// of a datasource. Rules are evaluated in order, the first one matching a segment decides
+/// whether it is loaded, on how many historicals of each tier, or dropped.
+///
+/// `tiered_replicants` maps historical tiers to their number of replicas, `period`s are ISO
+/// 8601 periods (`P1M`) and `interval`s ISO 8601 intervals.
+#[rustfmt::skip]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum Rule {
+    #[serde(rename_all = "camelCase")]
+    LoadForever { #[serde(default)] tiered_replicants: HashMap<String, usize> },
+    #[serde(rename_all = "camelCase")]
+    LoadByInterval { interval: String, #[serde(default)] tiered_replicants: HashMap<String, usize> },
+    #[serde(rename_all = "camelCase")]
+    LoadByPeriod {
+        period: String,
+        #[serde(skip_serializing_if = "Option::is_none")] include_future: Option<bool>,
+        #[serde(default)] tiered_replicants: HashMap<String, usize>,
+    },
+    DropForever,
+    DropByInterval { interval: String },
+    #[serde(rename_all = "camelCase")]
+    DropByPeriod { period: String, #[serde(skip_serializing_if = "Option::is_none")] include_future: Option<bool> },
+    /// Drops segments older than the period.
+    DropBeforeByPeriod { period: String },
+    BroadcastForever,
+    BroadcastByInterval { interval: String },
+    #[serde(rename_all = "camelCase")]
+    BroadcastByPeriod { period: String, #[serde(skip_serializing_if = "Option::is_none")] include_future: Option<bool> },
+}
+
+impl Rule {
+    pub fn load_forever(tiered_replicants: HashMap<String, usize>) -> Self {
+        Rule::LoadForever { tiered_replicants }
+    }
+    pub fn load_by_interval(interval: &str, tiered_replicants: HashMap<String, usize>) -> Self {
+        Rule::LoadByInterval {
+            interval: interval.to_string(),
+            tiered_replicants,
+        }
+    }
+    pub fn load_by_period(period: &str, tiered_replicants: HashMap<String, usize>) -> Self {
+        Rule::LoadByPeriod {
+            period: period.to_string(),
+            include_future: None,
+            tiered_replicants,
+        }
+    }
+    pub fn drop_forever() -> Self {
+        Rule::DropForever
+    }
+    pub fn drop_by_interval(interval: &str) -> Self {
+        Rule::DropByInterval {
+            interval: interval.to_string(),
+        }
+    }
+    pub fn drop_by_period(period: &str) -> Self {
+        Rule::DropByPeriod {
+            period: period.to_string(),
+            include_future: None,
+        }
+    }
+    pub fn drop_before_by_period(period: &str) -> Self {
+        Rule::DropBeforeByPeriod {
+            period: period.to_string(),
+        }
+    }
+    pub fn broadcast_forever() -> Self {
+        Rule::BroadcastForever
+    }
+    pub fn broadcast_by_interval(interval: &str) -> Self {
+        Rule::BroadcastByInterval {
+            interval: interval.to_string(),
+        }
+    }
+    pub fn broadcast_by_period(period: &str) -> Self {
+        Rule::BroadcastByPeriod {
+            period: period.to_string(),
+            include_future: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let rules = vec![
+            Rule::load_by_period(
+                "P1M",
+                HashMap::from([("hot".to_string(), 2), ("_default_tier".to_string(), 1)]),
+            ),
+            Rule::drop_before_by_period("P1M"),
+            Rule::drop_forever(),
+        ];
+        let json = serde_json::to_value(&rules).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"type": "loadByPeriod", "period": "P1M", "tieredReplicants": {"hot": 2, "_default_tier": 1}},
+                {"type": "dropBeforeByPeriod", "period": "P1M"},
+                {"type": "dropForever"}
+            ])
+        );
+
+        let rules: Vec<Rule> = serde_json::from_str(
+            r#"[{"tieredReplicants":{"_default_tier":2},"useDefaultTierForNull":true,"type":"loadForever"},{"interval":"2012-01-01T00:00:00.000Z/2013-01-01T00:00:00.000Z","type":"broadcastByInterval"},{"period":"P1D","includeFuture":true,"type":"dropByPeriod"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                Rule::load_forever(HashMap::from([("_default_tier".to_string(), 2)])),
+                Rule::broadcast_by_interval("2012-01-01T00:00:00.000Z/2013-01-01T00:00:00.000Z"),
+                Rule::DropByPeriod {
+                    period: "P1D".into(),
+                    include_future: Some(true)
+                },
+            ]
+        );
+    }
+}
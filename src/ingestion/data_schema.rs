@@ -0,0 +1,154 @@
+use crate::query::definitions::{Aggregation, Granularity, Interval};
+use serde::{Deserialize, Serialize};
+
+/// What ingested rows look like and how they are stored, shared by batch and streaming ingestion.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataSchema {
+    pub data_source: String,
+    pub timestamp_spec: TimestampSpec,
+    pub dimensions_spec: DimensionsSpec,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metrics_spec: Vec<Aggregation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub granularity_spec: Option<GranularitySpec>,
+}
+
+impl DataSchema {
+    pub fn new(
+        data_source: &str,
+        timestamp_spec: TimestampSpec,
+        dimensions_spec: DimensionsSpec,
+    ) -> Self {
+        DataSchema {
+            data_source: data_source.to_string(),
+            timestamp_spec,
+            dimensions_spec,
+            metrics_spec: vec![],
+            granularity_spec: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TimestampSpec {
+    pub column: String,
+    /// `iso`, `posix`, `millis`, `micro`, `nano`, `auto` or a Joda format string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Timestamp used for rows without one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub missing_value: Option<String>,
+}
+
+impl TimestampSpec {
+    pub fn new(column: &str, format: &str) -> Self {
+        TimestampSpec {
+            column: column.to_string(),
+            format: Some(format.to_string()),
+            missing_value: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DimensionsSpec {
+    #[serde(default)]
+    pub dimensions: Vec<DimensionSchema>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dimension_exclusions: Vec<String>,
+    /// Ingest every input field not listed as a dimension or excluded, without type detection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_all_dimensions: Option<bool>,
+    /// Ingest every input field and detect its type (druid 26+).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_schema_discovery: Option<bool>,
+}
+
+impl DimensionsSpec {
+    pub fn new(dimensions: Vec<DimensionSchema>) -> Self {
+        DimensionsSpec {
+            dimensions,
+            ..Default::default()
+        }
+    }
+}
+
+#[rustfmt::skip]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum DimensionSchema {
+    #[serde(rename_all = "camelCase")]
+    String { name: String, #[serde(skip_serializing_if = "Option::is_none")] multi_value_handling: Option<MultiValueHandling>, #[serde(skip_serializing_if = "Option::is_none")] create_bitmap_index: Option<bool> },
+    Long { name: String },
+    Float { name: String },
+    Double { name: String },
+    Json { name: String },
+}
+
+impl DimensionSchema {
+    pub fn string(name: &str) -> Self {
+        DimensionSchema::String {
+            name: name.to_string(),
+            multi_value_handling: None,
+            create_bitmap_index: None,
+        }
+    }
+    pub fn long(name: &str) -> Self {
+        DimensionSchema::Long {
+            name: name.to_string(),
+        }
+    }
+    pub fn float(name: &str) -> Self {
+        DimensionSchema::Float {
+            name: name.to_string(),
+        }
+    }
+    pub fn double(name: &str) -> Self {
+        DimensionSchema::Double {
+            name: name.to_string(),
+        }
+    }
+    pub fn json(name: &str) -> Self {
+        DimensionSchema::Json {
+            name: name.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MultiValueHandling {
+    SortedArray,
+    SortedSet,
+    Array,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type", rename = "uniform")]
+pub struct GranularitySpec {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment_granularity: Option<Granularity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_granularity: Option<Granularity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollup: Option<bool>,
+    /// Time chunks to ingest, rows outside of them are dropped. Required to replace data.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub intervals: Vec<Interval>,
+}
+
+impl GranularitySpec {
+    pub fn new(segment_granularity: Granularity, query_granularity: Granularity) -> Self {
+        GranularitySpec {
+            segment_granularity: Some(segment_granularity),
+            query_granularity: Some(query_granularity),
+            rollup: None,
+            intervals: vec![],
+        }
+    }
+}
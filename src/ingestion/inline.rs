@@ -0,0 +1,192 @@
+use super::{DataSchema, InputFormat, InputSource, IoConfig, PartitionsSpec, TuningConfig};
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+const DEFAULT_MAX_BYTES_PER_TASK: usize = 1024 * 1024;
+
+/// Ingestion of rows held in memory, see
+/// [`OverlordClient::ingest_rows`](crate::client::OverlordClient::ingest_rows). Rows are sent as
+/// json lines inside `index_parallel` task specs, which druid stores in its metadata database,
+/// so inputs over `max_bytes_per_task` are split into several tasks run one after the other.
+///
+/// Each task commits its rows on its own: when a task fails, the ones before it stay ingested
+/// and are reported by
+/// [`DruidClientError::PartialIngestion`](crate::client::DruidClientError::PartialIngestion).
+pub struct InlineIngestion {
+    pub(crate) data_schema: DataSchema,
+    pub(crate) tuning_config: Option<TuningConfig>,
+    pub(crate) append_to_existing: bool,
+    pub(crate) max_bytes_per_task: usize,
+    pub(crate) poll_interval: Duration,
+    pub(crate) timeout: Duration,
+}
+
+impl InlineIngestion {
+    pub fn new(data_schema: DataSchema) -> Self {
+        InlineIngestion {
+            data_schema,
+            tuning_config: None,
+            append_to_existing: false,
+            max_bytes_per_task: DEFAULT_MAX_BYTES_PER_TASK,
+            poll_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(3600),
+        }
+    }
+    pub fn tuning_config(mut self, tuning_config: TuningConfig) -> Self {
+        self.tuning_config = Some(tuning_config);
+        self
+    }
+    /// Keep the existing data of the ingested time chunks. When the rows are split, every task
+    /// after the first one appends regardless.
+    pub fn append_to_existing(mut self, append_to_existing: bool) -> Self {
+        self.append_to_existing = append_to_existing;
+        self
+    }
+    /// Size of the json lines sent in one task, 1MiB by default. A row larger than that is
+    /// sent in a task of its own.
+    ///
+    /// Tasks after the first one append to the data of the previous ones, which druid refuses
+    /// with perfect rollup (`hashed` and `range` partitions specs): such ingestions fail with
+    /// [`DruidClientError::IngestionTooLarge`](crate::client::DruidClientError::IngestionTooLarge)
+    /// before any task is submitted when the rows don't fit in one task.
+    pub fn max_bytes_per_task(mut self, max_bytes_per_task: usize) -> Self {
+        self.max_bytes_per_task = max_bytes_per_task;
+        self
+    }
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+    /// How long to wait for each task.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Whether the tuning config asks for perfect rollup, which can't append to existing data.
+    pub(crate) fn guarantees_rollup(&self) -> bool {
+        self.tuning_config.as_ref().is_some_and(|tuning_config| {
+            tuning_config.force_guaranteed_rollup == Some(true)
+                || matches!(
+                    tuning_config.partitions_spec,
+                    Some(PartitionsSpec::Hashed { .. } | PartitionsSpec::Range { .. })
+                )
+        })
+    }
+
+    /// `index_parallel` task json with empty inline data, filled in for every chunk of rows.
+    pub(crate) fn task_template(&self) -> Result<Value, serde_json::Error> {
+        let io_config = IoConfig::new(InputSource::inline(""), InputFormat::json());
+        let mut spec = serde_json::Map::new();
+        spec.insert(
+            "dataSchema".into(),
+            serde_json::to_value(&self.data_schema)?,
+        );
+        spec.insert("ioConfig".into(), serde_json::to_value(&io_config)?);
+        if let Some(tuning_config) = &self.tuning_config {
+            spec.insert("tuningConfig".into(), serde_json::to_value(tuning_config)?);
+        }
+        Ok(serde_json::json!({"type": "index_parallel", "spec": spec}))
+    }
+
+    pub(crate) fn task(template: &Value, data: String, append_to_existing: bool) -> Value {
+        let mut task = template.clone();
+        let io_config = &mut task["spec"]["ioConfig"];
+        io_config["inputSource"]["data"] = Value::String(data);
+        io_config["appendToExisting"] = Value::Bool(append_to_existing);
+        task
+    }
+}
+
+/// Serializes the rows as json lines, cut in chunks of at most `max_bytes` as the rows are
+/// pulled from the input.
+pub(crate) struct JsonLinesChunks<I> {
+    rows: I,
+    max_bytes: usize,
+    /// line read past the end of the previous chunk
+    pending: Option<String>,
+}
+
+impl<I> JsonLinesChunks<I> {
+    pub(crate) fn new(rows: I, max_bytes: usize) -> Self {
+        JsonLinesChunks {
+            rows,
+            max_bytes,
+            pending: None,
+        }
+    }
+}
+
+impl<T: Serialize, I: Iterator<Item = T>> Iterator for JsonLinesChunks<I> {
+    type Item = Result<String, serde_json::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = self.pending.take().unwrap_or_default();
+        for row in self.rows.by_ref() {
+            let mut line = match serde_json::to_string(&row) {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+            line.push('\n');
+            if !chunk.is_empty() && chunk.len() + line.len() > self.max_bytes {
+                self.pending = Some(line);
+                return Some(Ok(chunk));
+            }
+            chunk.push_str(&line);
+        }
+        (!chunk.is_empty()).then_some(Ok(chunk))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ingestion::{DimensionSchema, DimensionsSpec, TimestampSpec};
+
+    #[derive(Serialize)]
+    struct Edit {
+        time: &'static str,
+        page: &'static str,
+    }
+
+    #[test]
+    fn test_chunks() {
+        let rows = (0..5).map(|_| Edit {
+            time: "2015-09-12T00:00:00Z",
+            page: "Main",
+        });
+        // each line is 46 bytes with its line break
+        let chunks = JsonLinesChunks::new(rows, 100)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| chunk.lines().count())
+                .collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 100));
+    }
+
+    #[test]
+    fn test_task() {
+        let ingestion = InlineIngestion::new(DataSchema::new(
+            "wikipedia",
+            TimestampSpec::new("time", "iso"),
+            DimensionsSpec::new(vec![DimensionSchema::string("page")]),
+        ));
+        let template = ingestion.task_template().unwrap();
+        let task = InlineIngestion::task(&template, "{\"page\":\"Main\"}\n".into(), true);
+        assert_eq!(
+            task["spec"]["ioConfig"],
+            serde_json::json!({
+                "type": "index_parallel",
+                "inputSource": {"type": "inline", "data": "{\"page\":\"Main\"}\n"},
+                "inputFormat": {"type": "json"},
+                "appendToExisting": true
+            })
+        );
+    }
+}
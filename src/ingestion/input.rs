@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+/// Where a batch task reads its input from.
+#[rustfmt::skip]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum InputSource {
+    /// Data embedded in the spec, for small amounts of data.
+    Inline { data: String },
+    /// Files on the disks of the druid servers running the task.
+    #[serde(rename_all = "camelCase")]
+    Local {
+        #[serde(skip_serializing_if = "Option::is_none")] base_dir: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")] filter: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")] files: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Http {
+        uris: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")] http_authentication_username: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")] http_authentication_password: Option<String>,
+    },
+}
+
+impl InputSource {
+    pub fn inline(data: &str) -> Self {
+        InputSource::Inline {
+            data: data.to_string(),
+        }
+    }
+    /// Files of `base_dir` matching the `filter` wildcard, e.g. `*.json`.
+    pub fn local(base_dir: &str, filter: &str) -> Self {
+        InputSource::Local {
+            base_dir: Some(base_dir.to_string()),
+            filter: Some(filter.to_string()),
+            files: vec![],
+        }
+    }
+    pub fn http(uris: Vec<String>) -> Self {
+        InputSource::Http {
+            uris,
+            http_authentication_username: None,
+            http_authentication_password: None,
+        }
+    }
+}
+
+/// How the input is parsed into rows.
+#[rustfmt::skip]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum InputFormat {
+    /// One json object per line.
+    #[serde(rename_all = "camelCase")]
+    Json {
+        #[serde(skip_serializing_if = "Option::is_none")] keep_null_columns: Option<bool>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Csv {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")] columns: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")] find_columns_from_header: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")] skip_header_rows: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")] list_delimiter: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Tsv {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")] columns: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")] delimiter: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")] find_columns_from_header: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")] skip_header_rows: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")] list_delimiter: Option<String>,
+    },
+    /// Needs the `druid-parquet-extensions` extension loaded on the cluster.
+    #[serde(rename_all = "camelCase")]
+    Parquet {
+        #[serde(skip_serializing_if = "Option::is_none")] binary_as_string: Option<bool>,
+    },
+}
+
+impl InputFormat {
+    pub fn json() -> Self {
+        InputFormat::Json {
+            keep_null_columns: None,
+        }
+    }
+    /// Csv whose first line names the columns.
+    pub fn csv_with_header() -> Self {
+        InputFormat::Csv {
+            columns: vec![],
+            find_columns_from_header: Some(true),
+            skip_header_rows: None,
+            list_delimiter: None,
+        }
+    }
+    pub fn csv(columns: Vec<String>) -> Self {
+        InputFormat::Csv {
+            columns,
+            find_columns_from_header: None,
+            skip_header_rows: None,
+            list_delimiter: None,
+        }
+    }
+    pub fn tsv(columns: Vec<String>) -> Self {
+        InputFormat::Tsv {
+            columns,
+            delimiter: None,
+            find_columns_from_header: None,
+            skip_header_rows: None,
+            list_delimiter: None,
+        }
+    }
+    pub fn parquet() -> Self {
+        InputFormat::Parquet {
+            binary_as_string: None,
+        }
+    }
+}
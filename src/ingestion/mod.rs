@@ -0,0 +1,206 @@
+//! Specs of [native batch ingestion](https://druid.apache.org/docs/latest/ingestion/native-batch.html)
+//! tasks, submitted with [`OverlordClient::submit_task`](crate::client::OverlordClient::submit_task).
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub mod data_schema;
+pub mod inline;
+pub mod input;
+pub mod msq;
+pub mod response;
+pub mod supervisor;
+
+pub use data_schema::{
+    DataSchema, DimensionSchema, DimensionsSpec, GranularitySpec, MultiValueHandling, TimestampSpec,
+};
+pub use inline::InlineIngestion;
+pub use input::{InputFormat, InputSource};
+pub use supervisor::{
+    KafkaIoConfig, KafkaTuningConfig, KinesisIoConfig, KinesisTuningConfig, SupervisorSpec,
+};
+
+/// Parallel batch ingestion task (`index_parallel`).
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type", rename = "index_parallel")]
+pub struct IndexParallelTask {
+    /// Task id, druid generates one when absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub spec: IngestionSpec,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub context: HashMap<String, Value>,
+}
+
+impl IndexParallelTask {
+    pub fn new(data_schema: DataSchema, io_config: IoConfig) -> Self {
+        IndexParallelTask {
+            id: None,
+            spec: IngestionSpec {
+                data_schema,
+                io_config,
+                tuning_config: None,
+            },
+            context: HashMap::new(),
+        }
+    }
+
+    pub fn tuning_config(mut self, tuning_config: TuningConfig) -> Self {
+        self.spec.tuning_config = Some(tuning_config);
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestionSpec {
+    pub data_schema: DataSchema,
+    pub io_config: IoConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tuning_config: Option<TuningConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type", rename = "index_parallel")]
+pub struct IoConfig {
+    pub input_source: InputSource,
+    pub input_format: InputFormat,
+    /// Add segments to the existing data instead of replacing it.
+    #[serde(default)]
+    pub append_to_existing: bool,
+    /// Drop existing segments of the ingested intervals even where no new data is written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drop_existing: Option<bool>,
+}
+
+impl IoConfig {
+    pub fn new(input_source: InputSource, input_format: InputFormat) -> Self {
+        IoConfig {
+            input_source,
+            input_format,
+            append_to_existing: false,
+            drop_existing: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type", rename = "index_parallel")]
+pub struct TuningConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_rows_in_memory: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes_in_memory: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partitions_spec: Option<PartitionsSpec>,
+    /// Required by the `hashed` and `range` partitions specs, enables perfect rollup.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force_guaranteed_rollup: Option<bool>,
+    /// Sub tasks run at the same time, 1 runs the whole ingestion in the supervisor task.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_num_concurrent_sub_tasks: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_retry: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_parse_exceptions: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_parse_exceptions: Option<u64>,
+}
+
+#[rustfmt::skip]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum PartitionsSpec {
+    #[serde(rename_all = "camelCase")]
+    Dynamic {
+        #[serde(skip_serializing_if = "Option::is_none")] max_rows_per_segment: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")] max_total_rows: Option<u64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Hashed {
+        #[serde(skip_serializing_if = "Option::is_none")] num_shards: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")] target_rows_per_segment: Option<u64>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")] partition_dimensions: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Range {
+        partition_dimensions: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")] target_rows_per_segment: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")] max_rows_per_segment: Option<u64>,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::query::definitions::{Aggregation, Granularity};
+
+    #[test]
+    fn test_serialize_index_parallel() {
+        let mut data_schema = DataSchema::new(
+            "wikipedia",
+            TimestampSpec::new("time", "iso"),
+            DimensionsSpec::new(vec![
+                DimensionSchema::string("page"),
+                DimensionSchema::long("added"),
+            ]),
+        );
+        data_schema.metrics_spec = vec![Aggregation::count("count")];
+        data_schema.granularity_spec = Some(GranularitySpec::new(
+            Granularity::day(),
+            Granularity::hour(),
+        ));
+        let task = IndexParallelTask::new(
+            data_schema,
+            IoConfig::new(
+                InputSource::http(vec!["https://example.com/wikiticker.json.gz".into()]),
+                InputFormat::json(),
+            ),
+        )
+        .tuning_config(TuningConfig {
+            partitions_spec: Some(PartitionsSpec::Dynamic {
+                max_rows_per_segment: Some(5_000_000),
+                max_total_rows: None,
+            }),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            serde_json::to_value(&task).unwrap(),
+            serde_json::json!({
+                "type": "index_parallel",
+                "spec": {
+                    "dataSchema": {
+                        "dataSource": "wikipedia",
+                        "timestampSpec": {"column": "time", "format": "iso"},
+                        "dimensionsSpec": {"dimensions": [
+                            {"type": "string", "name": "page"},
+                            {"type": "long", "name": "added"}
+                        ]},
+                        "metricsSpec": [{"type": "count", "name": "count"}],
+                        "granularitySpec": {
+                            "type": "uniform",
+                            "segmentGranularity": "day",
+                            "queryGranularity": "hour"
+                        }
+                    },
+                    "ioConfig": {
+                        "type": "index_parallel",
+                        "inputSource": {"type": "http", "uris": ["https://example.com/wikiticker.json.gz"]},
+                        "inputFormat": {"type": "json"},
+                        "appendToExisting": false
+                    },
+                    "tuningConfig": {
+                        "type": "index_parallel",
+                        "partitionsSpec": {"type": "dynamic", "maxRowsPerSegment": 5000000}
+                    }
+                }
+            })
+        );
+    }
+}
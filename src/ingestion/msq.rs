@@ -0,0 +1,350 @@
+//! Types of the [multi-stage query](https://druid.apache.org/docs/latest/multi-stage-query/index.html)
+//! task engine, used by [`MsqClient`](crate::client::MsqClient): reports of SQL-based
+//! ingestion tasks and statuses of asynchronous SQL statements.
+
+use super::response::TaskStatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SqlTaskSubmitted {
+    pub(crate) task_id: String,
+}
+
+/// Report of a multi-stage query controller task, `multiStageQuery` in the task reports.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MsqTaskReport {
+    #[serde(default)]
+    pub task_id: Option<String>,
+    pub payload: MsqReportPayload,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MsqReportPayload {
+    pub status: MsqStatus,
+    #[serde(default)]
+    pub stages: Vec<MsqStage>,
+    /// Counters by stage number, then worker number, then counter name (`input0`, `output`,
+    /// `shuffle`, `sortProgress`, `warnings`...).
+    #[serde(default)]
+    pub counters: HashMap<String, HashMap<String, HashMap<String, MsqCounter>>>,
+    /// Rows of a SELECT whose results are written to the report.
+    #[serde(default)]
+    pub results: Option<MsqResults>,
+}
+
+impl MsqReportPayload {
+    /// Rows a stage wrote, summed over its workers and partitions.
+    pub fn output_rows(&self, stage_number: u32) -> u64 {
+        self.counters
+            .get(&stage_number.to_string())
+            .into_iter()
+            .flat_map(|workers| workers.values())
+            .filter_map(|counters| match counters.get("output") {
+                Some(MsqCounter::Channel(channel)) => Some(channel.rows.iter().sum::<u64>()),
+                _ => None,
+            })
+            .sum()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MsqStatus {
+    pub status: TaskStatusCode,
+    #[serde(default)]
+    pub error_report: Option<MsqErrorReport>,
+    #[serde(default)]
+    pub warnings: Vec<MsqErrorReport>,
+    #[serde(default)]
+    pub start_time: Option<String>,
+    #[serde(default)]
+    pub duration_ms: Option<i64>,
+    #[serde(default)]
+    pub pending_tasks: Option<u32>,
+    #[serde(default)]
+    pub running_tasks: Option<u32>,
+}
+
+/// An error or warning with the worker task and stage it was raised in.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MsqErrorReport {
+    #[serde(default)]
+    pub task_id: Option<String>,
+    #[serde(default)]
+    pub host: Option<String>,
+    #[serde(default)]
+    pub stage_number: Option<u32>,
+    pub error: MsqError,
+    #[serde(default)]
+    pub exception_stack_trace: Option<String>,
+}
+
+/// A multi-stage query fault, e.g. `TooManyBuckets` or `CannotParseExternalData`. Fields
+/// specific to the error code (`maxBuckets`...) are kept in `details`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MsqError {
+    pub error_code: String,
+    #[serde(default)]
+    pub error_message: Option<String>,
+    #[serde(flatten)]
+    pub details: HashMap<String, Value>,
+}
+
+impl fmt::Display for MsqError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error_message {
+            Some(message) => write!(f, "{}: {}", self.error_code, message),
+            None => f.write_str(&self.error_code),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MsqStage {
+    pub stage_number: u32,
+    pub definition: StageDefinition,
+    #[serde(default)]
+    pub phase: Option<StagePhase>,
+    #[serde(default)]
+    pub worker_count: Option<u32>,
+    #[serde(default)]
+    pub partition_count: Option<u32>,
+    #[serde(default)]
+    pub start_time: Option<String>,
+    /// Run time in milliseconds.
+    #[serde(default)]
+    pub duration: Option<i64>,
+    #[serde(default)]
+    pub sort: Option<bool>,
+}
+
+/// What a stage does, its `input`, `processor`, `signature`... are kept as json in `other`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StageDefinition {
+    pub id: String,
+    #[serde(default)]
+    pub max_worker_count: Option<u32>,
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StagePhase {
+    New,
+    ReadingInput,
+    PostReading,
+    ResultsReady,
+    Finished,
+    Failed,
+    #[serde(other)]
+    Unknown,
+}
+
+#[rustfmt::skip]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum MsqCounter {
+    /// Rows, bytes, frames and files read or written, one entry per partition.
+    Channel(ChannelCounter),
+    SortProgress(SortProgressCounter),
+    /// Number of warnings by error code.
+    Warnings(HashMap<String, u64>),
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelCounter {
+    #[serde(default)]
+    pub rows: Vec<u64>,
+    #[serde(default)]
+    pub bytes: Vec<u64>,
+    #[serde(default)]
+    pub frames: Vec<u64>,
+    #[serde(default)]
+    pub files: Vec<u64>,
+    #[serde(default)]
+    pub total_files: Vec<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SortProgressCounter {
+    #[serde(default)]
+    pub total_merging_levels: Option<i64>,
+    /// Completion of the sort, between 0 and 1.
+    #[serde(default)]
+    pub progress_digest: Option<f64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MsqResults {
+    #[serde(default)]
+    pub signature: Vec<MsqColumn>,
+    #[serde(default)]
+    pub sql_type_names: Vec<String>,
+    /// Rows in the order of `signature`.
+    #[serde(default)]
+    pub results: Vec<Vec<Value>>,
+    #[serde(default)]
+    pub results_truncated: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MsqColumn {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub column_type: Option<String>,
+}
+
+/// Status of a statement of the asynchronous SQL API (`/druid/v2/sql/statements`).
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlStatement {
+    pub query_id: String,
+    pub state: SqlStatementState,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub schema: Vec<StatementColumn>,
+    #[serde(default)]
+    pub duration_ms: Option<i64>,
+    #[serde(default)]
+    pub result: Option<StatementResult>,
+    #[serde(default)]
+    pub error_details: Option<MsqError>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SqlStatementState {
+    Accepted,
+    Running,
+    Success,
+    Failed,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementColumn {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub column_type: Option<String>,
+    #[serde(default)]
+    pub native_type: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementResult {
+    #[serde(default)]
+    pub num_total_rows: Option<u64>,
+    #[serde(default)]
+    pub total_size_in_bytes: Option<u64>,
+    #[serde(default)]
+    pub result_format: Option<String>,
+    #[serde(default)]
+    pub pages: Vec<ResultPage>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultPage {
+    pub id: u32,
+    #[serde(default)]
+    pub num_rows: Option<u64>,
+    #[serde(default)]
+    pub size_in_bytes: Option<u64>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_msq_report() {
+        let report: MsqTaskReport = serde_json::from_str(
+            r#"{
+                "type": "multiStageQuery",
+                "taskId": "query-abc",
+                "payload": {
+                    "status": {
+                        "status": "FAILED",
+                        "errorReport": {"taskId": "query-abc-worker0_0", "host": "localhost:8101", "stageNumber": 0, "error": {"errorCode": "TooManyBuckets", "maxBuckets": 5000, "errorMessage": "Too many partition buckets (max = 5,000)"}},
+                        "warnings": [{"taskId": "query-abc-worker0_0", "stageNumber": 0, "error": {"errorCode": "CannotParseExternalData", "errorMessage": "Unable to parse row"}}],
+                        "startTime": "2023-01-01T00:00:00.000Z",
+                        "durationMs": 3204,
+                        "pendingTasks": 0,
+                        "runningTasks": 2
+                    },
+                    "stages": [
+                        {"stageNumber": 0, "definition": {"id": "abc_0", "input": [{"type": "external"}], "processor": {"type": "scan"}, "maxWorkerCount": 1}, "phase": "FAILED", "workerCount": 1, "partitionCount": 1, "startTime": "2023-01-01T00:00:01.000Z", "duration": 2100, "sort": true}
+                    ],
+                    "counters": {"0": {"0": {
+                        "input0": {"type": "channel", "rows": [24433], "bytes": [6525055], "files": [1], "totalFiles": [1]},
+                        "output": {"type": "channel", "rows": [24433, 10], "bytes": [5000000, 100], "frames": [10, 1]},
+                        "sortProgress": {"type": "sortProgress", "totalMergingLevels": 3, "progressDigest": 1.0},
+                        "warnings": {"type": "warnings", "CannotParseExternalData": 3},
+                        "segmentGenerationProgress": {"type": "segmentGenerationProgress", "rowsProcessed": 10}
+                    }}}
+                }
+            }"#,
+        )
+        .unwrap();
+        let payload = report.payload;
+        assert_eq!(payload.status.status, TaskStatusCode::Failed);
+        let error = &payload.status.error_report.as_ref().unwrap().error;
+        assert_eq!(error.error_code, "TooManyBuckets");
+        assert_eq!(error.details["maxBuckets"], 5000);
+        assert_eq!(
+            payload.status.warnings[0].error.error_code,
+            "CannotParseExternalData"
+        );
+        assert_eq!(payload.stages[0].phase, Some(StagePhase::Failed));
+        assert_eq!(payload.stages[0].definition.max_worker_count, Some(1));
+        assert_eq!(payload.output_rows(0), 24443);
+        let counters = &payload.counters["0"]["0"];
+        assert!(
+            matches!(&counters["warnings"], MsqCounter::Warnings(counts) if counts["CannotParseExternalData"] == 3)
+        );
+        assert!(matches!(
+            counters["segmentGenerationProgress"],
+            MsqCounter::Unknown
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_statement() {
+        let statement: SqlStatement = serde_json::from_str(
+            r#"{"queryId":"query-abc","state":"SUCCESS","createdAt":"2023-01-01T00:00:00.000Z","schema":[{"name":"__time","type":"TIMESTAMP","nativeType":"LONG"},{"name":"page","type":"VARCHAR","nativeType":"STRING"}],"durationMs":1200,"result":{"numTotalRows":2,"totalSizeInBytes":150,"resultFormat":"object","dataSource":"__query_select","pages":[{"id":0,"numRows":2,"sizeInBytes":150}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(statement.state, SqlStatementState::Success);
+        assert_eq!(statement.result.unwrap().pages[0].num_rows, Some(2));
+
+        let statement: SqlStatement = serde_json::from_str(
+            r#"{"queryId":"query-def","state":"FAILED","createdAt":"2023-01-01T00:00:00.000Z","durationMs":10,"errorDetails":{"error":"druidException","errorCode":"invalidInput","persona":"USER","category":"INVALID_INPUT","errorMessage":"Table [missing] not found","context":{}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            statement.error_details.unwrap().to_string(),
+            "invalidInput: Table [missing] not found"
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use super::msq::MsqTaskReport;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Status of a task, as returned by the overlord's task status and task list APIs.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskStatus {
+    pub id: String,
+    #[serde(default)]
+    pub group_id: Option<String>,
+    #[serde(rename = "type", default)]
+    pub task_type: Option<String>,
+    #[serde(default)]
+    pub created_time: Option<String>,
+    #[serde(default)]
+    pub queue_insertion_time: Option<String>,
+    #[serde(default)]
+    pub status_code: Option<TaskStatusCode>,
+    /// Same as `status_code`, older overlords only send this one.
+    #[serde(default)]
+    pub status: Option<TaskStatusCode>,
+    #[serde(default)]
+    pub runner_status_code: Option<RunnerStatusCode>,
+    /// Run time in milliseconds, -1 while running.
+    #[serde(default)]
+    pub duration: Option<i64>,
+    #[serde(default)]
+    pub location: Option<TaskLocation>,
+    #[serde(default)]
+    pub data_source: Option<String>,
+    #[serde(default)]
+    pub error_msg: Option<String>,
+}
+
+impl TaskStatus {
+    pub fn code(&self) -> Option<TaskStatusCode> {
+        self.status_code.or(self.status)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TaskStatusCode {
+    Running,
+    Success,
+    Failed,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RunnerStatusCode {
+    Waiting,
+    Pending,
+    Running,
+    None,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskLocation {
+    pub host: Option<String>,
+    pub port: i32,
+    pub tls_port: i32,
+}
+
+/// Filter of the task list.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskState {
+    Running,
+    Complete,
+    Waiting,
+    Pending,
+}
+
+impl TaskState {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            TaskState::Running => "running",
+            TaskState::Complete => "complete",
+            TaskState::Waiting => "waiting",
+            TaskState::Pending => "pending",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct TaskStatusResponse {
+    pub(crate) status: TaskStatus,
+}
+
+/// Reports of a completed task. Ingestion tasks report their row counts and parse errors in
+/// `ingestion_stats_and_errors`, multi-stage query tasks their stages in `multi_stage_query`,
+/// other reports are kept as json.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskReport {
+    #[serde(default)]
+    pub ingestion_stats_and_errors: Option<IngestionStatsAndErrorsReport>,
+    #[serde(default)]
+    pub multi_stage_query: Option<MsqTaskReport>,
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestionStatsAndErrorsReport {
+    #[serde(default)]
+    pub task_id: Option<String>,
+    pub payload: IngestionStatsAndErrors,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestionStatsAndErrors {
+    #[serde(default)]
+    pub ingestion_state: Option<IngestionState>,
+    /// Parse errors by ingestion phase (`determinePartitions`, `buildSegments`).
+    #[serde(default)]
+    pub unparseable_events: HashMap<String, Vec<UnparseableEvent>>,
+    /// Row counts by ingestion phase (`determinePartitions`, `buildSegments`).
+    #[serde(default)]
+    pub row_stats: HashMap<String, RowStats>,
+    #[serde(default)]
+    pub error_msg: Option<String>,
+    #[serde(default)]
+    pub segment_availability_confirmed: Option<bool>,
+}
+
+impl IngestionStatsAndErrors {
+    /// Row counts of the phase writing segments, the one that matters for the ingested data.
+    pub fn build_segments(&self) -> RowStats {
+        self.row_stats
+            .get("buildSegments")
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IngestionState {
+    NotStarted,
+    DeterminePartitions,
+    BuildSegments,
+    SegmentAvailabilityWait,
+    Completed,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RowStats {
+    #[serde(default)]
+    pub processed: u64,
+    #[serde(default)]
+    pub processed_bytes: u64,
+    #[serde(default)]
+    pub processed_with_error: u64,
+    #[serde(default)]
+    pub thrown_away: u64,
+    #[serde(default)]
+    pub unparseable: u64,
+}
+
+/// A row druid couldn't parse: a structured report on recent versions, a message on older ones.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum UnparseableEvent {
+    Report(ParseExceptionReport),
+    Message(String),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseExceptionReport {
+    #[serde(default)]
+    pub input: Option<Value>,
+    #[serde(default)]
+    pub error_type: Option<String>,
+    #[serde(default)]
+    pub details: Vec<String>,
+    #[serde(default)]
+    pub time_of_exception_millis: Option<i64>,
+}
+
+/// Status of a supervisor, from `GET /druid/indexer/v1/supervisor/{id}/status`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SupervisorStatus {
+    pub id: String,
+    #[serde(default)]
+    pub generation_time: Option<String>,
+    pub payload: SupervisorStatusPayload,
+}
+
+/// Lags are given per partition (kafka) or shard (kinesis): in messages for kafka, in
+/// milliseconds for kinesis.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SupervisorStatusPayload {
+    pub data_source: String,
+    pub stream: String,
+    #[serde(default)]
+    pub partitions: Option<u32>,
+    #[serde(default)]
+    pub replicas: Option<u32>,
+    #[serde(default)]
+    pub duration_seconds: Option<u64>,
+    #[serde(default)]
+    pub active_tasks: Vec<SupervisorTaskReport>,
+    #[serde(default)]
+    pub publishing_tasks: Vec<SupervisorTaskReport>,
+    #[serde(default)]
+    pub latest_offsets: Option<HashMap<String, StreamOffset>>,
+    #[serde(default)]
+    pub minimum_lag: Option<HashMap<String, i64>>,
+    #[serde(default)]
+    pub aggregate_lag: Option<i64>,
+    #[serde(default)]
+    pub minimum_lag_millis: Option<HashMap<String, i64>>,
+    #[serde(default)]
+    pub aggregate_lag_millis: Option<i64>,
+    #[serde(default)]
+    pub offsets_last_updated: Option<String>,
+    #[serde(default)]
+    pub suspended: bool,
+    #[serde(default)]
+    pub healthy: bool,
+    pub state: SupervisorState,
+    /// Finer grained `state`, e.g. `CONNECTING_TO_STREAM` or `DISCOVERING_INITIAL_TASKS`.
+    #[serde(default)]
+    pub detailed_state: Option<String>,
+    #[serde(default)]
+    pub recent_errors: Vec<SupervisorError>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SupervisorState {
+    Pending,
+    Running,
+    Idle,
+    Suspended,
+    Stopping,
+    UnhealthySupervisor,
+    UnhealthyTasks,
+    LostContactWithStream,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Offset of a kafka partition or sequence number of a kinesis shard.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum StreamOffset {
+    Offset(i64),
+    SequenceNumber(String),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SupervisorTaskReport {
+    pub id: String,
+    #[serde(default)]
+    pub starting_offsets: HashMap<String, StreamOffset>,
+    #[serde(default)]
+    pub current_offsets: HashMap<String, StreamOffset>,
+    #[serde(default)]
+    pub start_time: Option<String>,
+    #[serde(default)]
+    pub remaining_seconds: Option<i64>,
+    #[serde(default)]
+    pub lag: Option<HashMap<String, i64>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SupervisorError {
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    #[serde(default)]
+    pub exception_class: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub stream_exception: bool,
+}
+
+/// A supervisor in the list of `GET /druid/indexer/v1/supervisor?state=true`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SupervisorSummary {
+    pub id: String,
+    pub state: SupervisorState,
+    #[serde(default)]
+    pub detailed_state: Option<String>,
+    #[serde(default)]
+    pub healthy: bool,
+    #[serde(default)]
+    pub suspended: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_task_report() {
+        let report: TaskReport = serde_json::from_str(
+            r#"{
+                "ingestionStatsAndErrors": {
+                    "type": "ingestionStatsAndErrors",
+                    "taskId": "index_parallel_wikipedia_abc",
+                    "payload": {
+                        "ingestionState": "COMPLETED",
+                        "unparseableEvents": {"buildSegments": [
+                            {"timeOfExceptionMillis": 1600000000000, "details": ["Unparseable timestamp found!"], "input": {"time": "x"}, "errorType": "processedWithError"},
+                            "Unparseable timestamp found!"
+                        ]},
+                        "rowStats": {"buildSegments": {"processed": 39244, "processedBytes": 0, "processedWithError": 0, "thrownAway": 0, "unparseable": 2}},
+                        "errorMsg": null,
+                        "segmentAvailabilityConfirmed": false
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let stats = report.ingestion_stats_and_errors.unwrap().payload;
+        assert_eq!(stats.ingestion_state, Some(IngestionState::Completed));
+        assert_eq!(stats.build_segments().processed, 39244);
+        assert_eq!(stats.build_segments().unparseable, 2);
+        assert!(matches!(
+            &stats.unparseable_events["buildSegments"][0],
+            UnparseableEvent::Report(report) if report.details.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_task_status() {
+        let status: TaskStatusResponse = serde_json::from_str(
+            r#"{"task":"index_parallel_wikipedia_abc","status":{"id":"index_parallel_wikipedia_abc","type":"index_parallel","createdTime":"2021-01-01T00:00:00.000Z","queueInsertionTime":"1970-01-01T00:00:00.000Z","statusCode":"FAILED","status":"FAILED","runnerStatusCode":"NONE","duration":1200,"location":{"host":null,"port":-1,"tlsPort":-1},"dataSource":"wikipedia","errorMsg":"Unparseable rows exceeded maxParseExceptions"}}"#,
+        )
+        .unwrap();
+        assert_eq!(status.status.code(), Some(TaskStatusCode::Failed));
+        assert_eq!(
+            status.status.runner_status_code,
+            Some(RunnerStatusCode::None)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_supervisor_status() {
+        let status: SupervisorStatus = serde_json::from_str(
+            r#"{"id":"wikipedia","generationTime":"2021-01-01T00:00:00.000Z","payload":{"dataSource":"wikipedia","stream":"wikipedia","partitions":2,"replicas":1,"durationSeconds":3600,"activeTasks":[{"id":"index_kafka_wikipedia_abc","startingOffsets":{"0":10,"1":20},"startTime":"2021-01-01T00:00:00.000Z","remainingSeconds":3000,"type":"ACTIVE","currentOffsets":{"0":90,"1":95},"lag":{"0":10,"1":5}}],"publishingTasks":[],"latestOffsets":{"0":100,"1":100},"minimumLag":{"0":10,"1":5},"aggregateLag":15,"offsetsLastUpdated":"2021-01-01T00:10:00.000Z","suspended":false,"healthy":true,"state":"RUNNING","detailedState":"RUNNING","recentErrors":[]}}"#,
+        )
+        .unwrap();
+        let payload = status.payload;
+        assert_eq!(payload.state, SupervisorState::Running);
+        assert_eq!(payload.aggregate_lag, Some(15));
+        assert_eq!(payload.minimum_lag.unwrap()["1"], 5);
+        assert_eq!(
+            payload.active_tasks[0].current_offsets["0"],
+            StreamOffset::Offset(90)
+        );
+
+        let status: SupervisorStatus = serde_json::from_str(
+            r#"{"id":"events","payload":{"dataSource":"events","stream":"events","partitions":1,"activeTasks":[],"publishingTasks":[],"latestOffsets":{"shardId-000000000000":"49590338271490256608559692538361571095921575989136588898"},"minimumLagMillis":{"shardId-000000000000":1200},"aggregateLagMillis":1200,"suspended":false,"healthy":false,"state":"UNHEALTHY_SUPERVISOR","detailedState":"UNABLE_TO_CONNECT_TO_STREAM","recentErrors":[{"timestamp":"2021-01-01T00:00:00.000Z","exceptionClass":"org.apache.druid.java.util.common.StreamException","message":"Unable to connect","streamException":true}]}}"#,
+        )
+        .unwrap();
+        let payload = status.payload;
+        assert_eq!(payload.state, SupervisorState::UnhealthySupervisor);
+        assert_eq!(payload.aggregate_lag_millis, Some(1200));
+        assert!(matches!(
+            &payload.latest_offsets.unwrap()["shardId-000000000000"],
+            StreamOffset::SequenceNumber(_)
+        ));
+        assert!(payload.recent_errors[0].stream_exception);
+    }
+}
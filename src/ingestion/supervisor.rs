@@ -0,0 +1,255 @@
+//! Specs of [streaming ingestion](https://druid.apache.org/docs/latest/ingestion/streaming.html)
+//! supervisors, managed with [`SupervisorClient`](crate::client::SupervisorClient).
+
+use super::{DataSchema, InputFormat};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Spec of a supervisor, submitted with
+/// [`SupervisorClient::create_or_update`](crate::client::SupervisorClient::create_or_update).
+#[rustfmt::skip]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum SupervisorSpec {
+    Kafka {
+        spec: KafkaIngestionSpec,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")] suspended: bool,
+    },
+    Kinesis {
+        spec: KinesisIngestionSpec,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")] suspended: bool,
+    },
+}
+
+impl SupervisorSpec {
+    pub fn kafka(data_schema: DataSchema, io_config: KafkaIoConfig) -> Self {
+        SupervisorSpec::Kafka {
+            spec: KafkaIngestionSpec {
+                data_schema,
+                io_config,
+                tuning_config: None,
+            },
+            suspended: false,
+        }
+    }
+
+    pub fn kinesis(data_schema: DataSchema, io_config: KinesisIoConfig) -> Self {
+        SupervisorSpec::Kinesis {
+            spec: KinesisIngestionSpec {
+                data_schema,
+                io_config,
+                tuning_config: None,
+            },
+            suspended: false,
+        }
+    }
+
+    pub fn data_source(&self) -> &str {
+        match self {
+            SupervisorSpec::Kafka { spec, .. } => &spec.data_schema.data_source,
+            SupervisorSpec::Kinesis { spec, .. } => &spec.data_schema.data_source,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KafkaIngestionSpec {
+    pub data_schema: DataSchema,
+    pub io_config: KafkaIoConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tuning_config: Option<KafkaTuningConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KinesisIngestionSpec {
+    pub data_schema: DataSchema,
+    pub io_config: KinesisIoConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tuning_config: Option<KinesisTuningConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type", rename = "kafka")]
+pub struct KafkaIoConfig {
+    pub topic: String,
+    pub input_format: InputFormat,
+    /// Kafka consumer properties, `bootstrap.servers` at least.
+    pub consumer_properties: HashMap<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replicas: Option<usize>,
+    /// ISO 8601 period after which tasks stop reading and publish their segments, e.g. `PT1H`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_duration: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_earliest_offset: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completion_timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub late_message_rejection_period: Option<String>,
+}
+
+impl KafkaIoConfig {
+    pub fn new(topic: &str, bootstrap_servers: &str, input_format: InputFormat) -> Self {
+        KafkaIoConfig {
+            topic: topic.to_string(),
+            input_format,
+            consumer_properties: HashMap::from([(
+                "bootstrap.servers".to_string(),
+                Value::from(bootstrap_servers),
+            )]),
+            task_count: None,
+            replicas: None,
+            task_duration: None,
+            use_earliest_offset: None,
+            completion_timeout: None,
+            late_message_rejection_period: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type", rename = "kinesis")]
+pub struct KinesisIoConfig {
+    pub stream: String,
+    pub input_format: InputFormat,
+    /// Kinesis endpoint, e.g. `kinesis.us-east-1.amazonaws.com`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replicas: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_duration: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_earliest_sequence_number: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aws_assumed_role_arn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aws_external_id: Option<String>,
+}
+
+impl KinesisIoConfig {
+    pub fn new(stream: &str, endpoint: &str, input_format: InputFormat) -> Self {
+        KinesisIoConfig {
+            stream: stream.to_string(),
+            input_format,
+            endpoint: Some(endpoint.to_string()),
+            task_count: None,
+            replicas: None,
+            task_duration: None,
+            use_earliest_sequence_number: None,
+            aws_assumed_role_arn: None,
+            aws_external_id: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type", rename = "kafka")]
+pub struct KafkaTuningConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_rows_in_memory: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes_in_memory: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_rows_per_segment: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_rows: Option<u64>,
+    /// ISO 8601 period between persists of the rows held in memory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intermediate_persist_period: Option<String>,
+    /// Skip to the latest available offset when the stored one is no longer available,
+    /// losing data, instead of failing the tasks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_offset_automatically: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_parse_exceptions: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_parse_exceptions: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "type", rename = "kinesis")]
+pub struct KinesisTuningConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_rows_in_memory: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes_in_memory: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_rows_per_segment: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_rows: Option<u64>,
+    /// ISO 8601 period between persists of the rows held in memory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intermediate_persist_period: Option<String>,
+    /// Skip to the latest available offset when the stored one is no longer available,
+    /// losing data, instead of failing the tasks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset_offset_automatically: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_parse_exceptions: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_parse_exceptions: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_buffer_size: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch_threads: Option<usize>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ingestion::{DimensionSchema, DimensionsSpec, TimestampSpec};
+
+    #[test]
+    fn test_serialize_kafka_supervisor() {
+        let mut io_config = KafkaIoConfig::new("wikipedia", "localhost:9092", InputFormat::json());
+        io_config.task_count = Some(2);
+        let mut spec = SupervisorSpec::kafka(
+            DataSchema::new(
+                "wikipedia",
+                TimestampSpec::new("time", "auto"),
+                DimensionsSpec::new(vec![DimensionSchema::string("page")]),
+            ),
+            io_config,
+        );
+        if let SupervisorSpec::Kafka { spec, .. } = &mut spec {
+            spec.tuning_config = Some(KafkaTuningConfig {
+                reset_offset_automatically: Some(true),
+                ..Default::default()
+            });
+        }
+        assert_eq!(
+            serde_json::to_value(&spec).unwrap(),
+            serde_json::json!({
+                "type": "kafka",
+                "spec": {
+                    "dataSchema": {
+                        "dataSource": "wikipedia",
+                        "timestampSpec": {"column": "time", "format": "auto"},
+                        "dimensionsSpec": {"dimensions": [{"type": "string", "name": "page"}]}
+                    },
+                    "ioConfig": {
+                        "type": "kafka",
+                        "topic": "wikipedia",
+                        "inputFormat": {"type": "json"},
+                        "consumerProperties": {"bootstrap.servers": "localhost:9092"},
+                        "taskCount": 2
+                    },
+                    "tuningConfig": {"type": "kafka", "resetOffsetAutomatically": true}
+                }
+            })
+        );
+    }
+}
@@ -23,6 +23,8 @@
 //! * SegmentMetadata
 //! * DataSourceMetadata
 //!
+//! [Druid SQL](https://druid.apache.org/docs/latest/querying/sql-api.html) queries are supported as well.
+//!
 //! ## Usage
 //!
 //! ### Client
@@ -35,6 +37,19 @@
 //! let druid_client = DruidClientBuilder::new("http://localhost:8082").build();
 //! ```
 //!
+//! Queries are spread over several brokers with a pluggable selection strategy. Brokers that refuse
+//! connections are skipped for a backoff period and the query is retried on another one:
+//!
+//! ```rust
+//! use druid_io::client::{DruidClientBuilder, LeastOutstanding};
+//! use std::time::Duration;
+//!
+//! let druid_client = DruidClientBuilder::with_brokers(&["http://broker1:8082", "http://broker2:8082"])
+//!     .selection_strategy(LeastOutstanding)
+//!     .unhealthy_backoff(Duration::from_secs(10))
+//!     .build();
+//! ```
+//!
 //! ### Querying
 //!
 //! #### Timeseries
@@ -105,9 +120,17 @@
 
 extern crate serde_json;
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
 pub mod client;
+pub mod coordinator;
+pub mod ingestion;
+#[cfg(feature = "polars")]
+pub mod polars;
 pub mod query;
 pub mod serialization;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,253 @@
+//! Conversion of query results to polars [`DataFrame`]s (feature `polars`).
+//!
+//! ```ignore
+//! use druid_io::polars::ToDataFrame;
+//!
+//! let responses = druid_client.top_n::<Value>(&query).await?;
+//! let df = responses.to_data_frame()?;
+//! ```
+//!
+//! Aggregated results (GroupBy, Timeseries, TopN) get their timestamp in a `timestamp` column,
+//! GroupBy results one row per `event` and TopN results one row per entry of each result list.
+//! `compactedList` scan events are zipped with the response's `columns`.
+//!
+//! Columns keep the order of the results, the feature turns on `serde_json/preserve_order` for
+//! that. Cargo unifies features, so json maps keep their insertion order in every crate of the
+//! build.
+
+use crate::serialization::table::{ColumnKind, JsonTable, ToJsonTable};
+use crate::serialization::values::{as_f64, as_i64, as_text, as_timestamp_millis};
+use polars::prelude::{
+    polars_err, Column, DataFrame, Int64Chunked, IntoSeries, NamedFrom, PolarsResult, Series,
+    TimeUnit, TimeZone,
+};
+use serde_json::Value;
+
+/// Results convertible to a [`DataFrame`]: slices of `ScanResponse`, `GroupByResponse`,
+/// `TimeseriesResponse`, `TopNResponse`, `TimeBoundaryResponse`, `SegmentMetadataResponse`,
+/// `MetadataResponse` (DataSourceMetadata) and of the [`Row`](crate::query::row::Row)s of
+/// `query_rows`. Columns come in the order of the results, scan `columns` for scans and the
+/// query's output names for rows.
+pub trait ToDataFrame {
+    /// Converts the results to a data frame with column types guessed from the values: json
+    /// integers become `Int64`, other numbers `Float64`, lists `List<String>`, ISO 8601 texts and
+    /// `__time` `Datetime(ms, UTC)` and anything else `String`.
+    fn to_data_frame(&self) -> PolarsResult<DataFrame>;
+}
+
+impl<R: ToJsonTable + ?Sized> ToDataFrame for R {
+    fn to_data_frame(&self) -> PolarsResult<DataFrame> {
+        let table = self
+            .to_json_table()
+            .map_err(|err| polars_err!(ComputeError: "{}", err))?;
+        data_frame(&table)
+    }
+}
+
+fn data_frame(table: &JsonTable) -> PolarsResult<DataFrame> {
+    let columns = table
+        .columns
+        .iter()
+        .map(|name| column(table, name))
+        .collect::<PolarsResult<Vec<_>>>()?;
+    DataFrame::new(columns)
+}
+
+fn column(table: &JsonTable, name: &str) -> PolarsResult<Column> {
+    let values: Vec<&Value> = table.values(name).collect();
+    let series = match table.infer_kind(name) {
+        ColumnKind::Timestamp => {
+            Int64Chunked::new(name.into(), convert(name, &values, as_timestamp_millis)?)
+                .into_datetime(TimeUnit::Milliseconds, Some(TimeZone::UTC))
+                .into_series()
+        }
+        ColumnKind::Boolean => Series::new(name.into(), convert(name, &values, Value::as_bool)?),
+        ColumnKind::Long => Series::new(name.into(), convert(name, &values, as_i64)?),
+        ColumnKind::Double => Series::new(name.into(), convert(name, &values, as_f64)?),
+        ColumnKind::String => Series::new(
+            name.into(),
+            convert(name, &values, |value| Some(as_text(value)))?,
+        ),
+        ColumnKind::StringList => {
+            let lists: Vec<Option<Series>> = values
+                .iter()
+                .map(|value| match value {
+                    Value::Null => None,
+                    // druid sends single-valued rows of multi-value columns as plain strings
+                    Value::Array(items) => Some(Series::new(
+                        "".into(),
+                        items
+                            .iter()
+                            .map(|item| (!item.is_null()).then(|| as_text(item)))
+                            .collect::<Vec<_>>(),
+                    )),
+                    value => Some(Series::new("".into(), [as_text(value)])),
+                })
+                .collect();
+            Series::new(name.into(), lists)
+        }
+    };
+    Ok(series.into())
+}
+
+fn convert<T>(
+    name: &str,
+    values: &[&Value],
+    convert: impl Fn(&Value) -> Option<T>,
+) -> PolarsResult<Vec<Option<T>>> {
+    values
+        .iter()
+        .map(|value| match value {
+            Value::Null => Ok(None),
+            value => convert(value).map(Some).ok_or_else(
+                || polars_err!(ComputeError: "can't convert {} for column `{}`", value, name),
+            ),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::query::definitions::{Aggregation, Granularity};
+    use crate::query::response::{
+        GroupByResponse, MetadataResponse, ScanResponse, SegmentMetadataResponse,
+        TimeBoundaryResponse, TopNResponse,
+    };
+    use crate::query::timeseries::Timeseries;
+    use crate::query::{DataSource, Query};
+    use polars::prelude::DataType;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_group_by_data_frame() {
+        let responses: Vec<GroupByResponse<Value>> = serde_json::from_str(
+            r#"[
+                {"timestamp": "2015-09-12T00:00:00.000Z", "event": {"page": "Main", "count": 3, "added": 12.5}},
+                {"timestamp": "2015-09-12T01:00:00.000Z", "event": {"page": null, "count": 1, "added": 1}}
+            ]"#,
+        )
+        .unwrap();
+        let df = responses.to_data_frame().unwrap();
+        assert_eq!(df.height(), 2);
+        assert_eq!(
+            df.get_column_names_str(),
+            vec!["timestamp", "page", "count", "added"]
+        );
+        assert_eq!(
+            df.column("timestamp").unwrap().dtype(),
+            &DataType::Datetime(TimeUnit::Milliseconds, Some(TimeZone::UTC))
+        );
+        let timestamps = df.column("timestamp").unwrap().datetime().unwrap();
+        assert_eq!(timestamps.phys.get(1), Some(1442019600000));
+        assert_eq!(df.column("added").unwrap().f64().unwrap().get(1), Some(1.0));
+        assert_eq!(df.column("count").unwrap().i64().unwrap().get(0), Some(3));
+        let pages = df.column("page").unwrap().str().unwrap();
+        assert_eq!(pages.get(0), Some("Main"));
+        assert_eq!(pages.get(1), None);
+    }
+
+    #[test]
+    fn test_top_n_data_frame() {
+        let responses: Vec<TopNResponse<Value>> = serde_json::from_str(
+            r#"[
+                {"timestamp": "2015-09-12T00:00:00.000Z", "result": [{"page": "Main", "count": 3}, {"page": "Talk", "count": 1}]},
+                {"timestamp": "2015-09-13T00:00:00.000Z", "result": [{"page": "Main", "count": 2}]}
+            ]"#,
+        )
+        .unwrap();
+        let df = responses.to_data_frame().unwrap();
+        assert_eq!(df.height(), 3);
+        assert_eq!(
+            df.column("page").unwrap().str().unwrap().get(1),
+            Some("Talk")
+        );
+    }
+
+    #[test]
+    fn test_rows_data_frame() {
+        let query = Query::Timeseries(Timeseries {
+            data_source: DataSource::table("wikipedia"),
+            limit: None,
+            descending: false,
+            granularity: Granularity::hour(),
+            filter: None,
+            aggregations: vec![
+                Aggregation::long_sum("zebras", "zebras"),
+                Aggregation::count("count"),
+            ],
+            post_aggregations: vec![],
+            virtual_columns: vec![],
+            intervals: vec![],
+            context: Default::default(),
+        });
+        // the result lists `count` first, the rows keep the query's order
+        let rows = crate::query::row::rows(
+            &query,
+            vec![serde_json::json!({"timestamp": "2015-09-12T00:00:00.000Z", "result": {"count": 3, "zebras": 1}})],
+        )
+        .unwrap();
+        let df = rows.to_data_frame().unwrap();
+        assert_eq!(
+            df.get_column_names_str(),
+            vec!["timestamp", "zebras", "count"]
+        );
+    }
+
+    #[test]
+    fn test_metadata_data_frames() {
+        let boundaries: Vec<TimeBoundaryResponse> = serde_json::from_str(
+            r#"[{"timestamp": "2015-09-12T00:00:00.000Z", "result": {"minTime": "2015-09-12T00:00:00.000Z", "maxTime": "2015-09-12T23:59:59.000Z"}}]"#,
+        )
+        .unwrap();
+        let df = boundaries.to_data_frame().unwrap();
+        assert_eq!(
+            df.get_column_names_str(),
+            vec!["timestamp", "maxTime", "minTime"]
+        );
+        assert!(matches!(
+            df.column("minTime").unwrap().dtype(),
+            DataType::Datetime(TimeUnit::Milliseconds, _)
+        ));
+
+        let metadata: Vec<MetadataResponse<HashMap<String, String>>> = serde_json::from_str(
+            r#"[{"timestamp": "2015-09-12T00:00:00.000Z", "result": {"maxIngestedEventTime": "2015-09-12T23:59:59.000Z"}}]"#,
+        )
+        .unwrap();
+        let df = metadata.to_data_frame().unwrap();
+        assert_eq!(
+            df.get_column_names_str(),
+            vec!["timestamp", "maxIngestedEventTime"]
+        );
+
+        let segments: Vec<SegmentMetadataResponse> = serde_json::from_str(
+            r#"[{"id": "merged", "intervals": ["2015-09-12T00:00:00.000Z/2015-09-13T00:00:00.000Z"], "columns": {}, "queryGranularity": null, "rollup": null, "size": 4096, "numRows": 39244, "timestampSpec": {"column": "time", "format": "auto", "missingValue": null}, "aggregators": null}]"#,
+        )
+        .unwrap();
+        let df = segments.to_data_frame().unwrap();
+        assert_eq!(df.height(), 1);
+        assert_eq!(
+            df.column("numRows").unwrap().i64().unwrap().get(0),
+            Some(39244)
+        );
+    }
+
+    #[test]
+    fn test_compacted_scan_data_frame() {
+        let responses: Vec<ScanResponse<Value>> = serde_json::from_str(
+            r#"[{"segmentId": "s1", "columns": ["__time", "added", "tags"], "events": [[1442016000000, 5, ["a", "b"]], [1442016001000, 7, "c"]]}]"#,
+        )
+        .unwrap();
+        let df = responses.to_data_frame().unwrap();
+        assert!(matches!(
+            df.column("__time").unwrap().dtype(),
+            DataType::Datetime(TimeUnit::Milliseconds, _)
+        ));
+        let tags = df.column("tags").unwrap().list().unwrap();
+        assert_eq!(tags.get_as_series(0).unwrap().len(), 2);
+        assert_eq!(
+            tags.get_as_series(1).unwrap().str().unwrap().get(0),
+            Some("c")
+        );
+    }
+}
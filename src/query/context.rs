@@ -0,0 +1,196 @@
+use crate::serialization::lenient;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Declares the typed fields of [`QueryContext`] with their druid name, which is used both for
+/// serde and for setting and reading single keys.
+macro_rules! query_context {
+    ($(#[$meta:meta])* pub struct $name:ident {
+        $($(#[$field_meta:meta])* $key:literal => $field:ident: $type:ty,)*
+    }) => {
+        $(#[$meta])*
+        pub struct $name {
+            $(
+                $(#[$field_meta])*
+                #[serde(
+                    rename = $key,
+                    default,
+                    deserialize_with = "lenient",
+                    skip_serializing_if = "Option::is_none"
+                )]
+                pub $field: Option<$type>,
+            )*
+            #[serde(flatten)]
+            pub extra: HashMap<String, Value>,
+        }
+
+        impl $name {
+            /// Sets the typed field of `key` from `value`, clearing it when the value doesn't
+            /// parse. `None` for keys without a typed field.
+            fn set_typed(&mut self, key: &str, value: &Value) -> Option<bool> {
+                match key {
+                    $($key => Some(match lenient(value) {
+                        Ok(parsed) => {
+                            self.$field = parsed;
+                            true
+                        }
+                        Err(_) => {
+                            self.$field = None;
+                            false
+                        }
+                    }),)*
+                    _ => None,
+                }
+            }
+
+            fn get_typed(&self, key: &str) -> Option<Value> {
+                match key {
+                    $($key => self
+                        .$field
+                        .as_ref()
+                        .and_then(|value| serde_json::to_value(value).ok()),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+query_context! {
+    /// [Query context](https://druid.apache.org/docs/latest/querying/query-context.html)
+    /// parameters. Well known keys are typed so they reach druid as json numbers and booleans,
+    /// anything else goes to `extra`.
+    #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+    pub struct QueryContext {
+        "queryId" => query_id: String,
+        "sqlQueryId" => sql_query_id: String,
+        /// Query timeout in milliseconds.
+        "timeout" => timeout: u64,
+        "priority" => priority: i64,
+        "lane" => lane: String,
+        "useCache" => use_cache: bool,
+        "populateCache" => populate_cache: bool,
+        "useResultLevelCache" => use_result_level_cache: bool,
+        "populateResultLevelCache" => populate_result_level_cache: bool,
+        "bySegment" => by_segment: bool,
+        "finalize" => finalize: bool,
+        "maxScatterGatherBytes" => max_scatter_gather_bytes: u64,
+        "maxQueuedBytes" => max_queued_bytes: u64,
+        "serializeDateTimeAsLong" => serialize_date_time_as_long: bool,
+        "vectorize" => vectorize: Vectorize,
+        "vectorizeVirtualColumns" => vectorize_virtual_columns: Vectorize,
+        "vectorSize" => vector_size: u64,
+        "sqlTimeZone" => sql_time_zone: String,
+        /// GroupBy rows as positional arrays, decoded into the same row types by
+        /// [`DruidClient::group_by`](crate::client::DruidClient::group_by). Druid only reads it
+        /// for groupBy, timeseries and topN results stay json objects.
+        "resultAsArray" => result_as_array: bool,
+        "grandTotal" => grand_total: bool,
+        "skipEmptyBuckets" => skip_empty_buckets: bool,
+        /// Tasks a multi-stage query may use, the controller included.
+        "maxNumTasks" => max_num_tasks: u64,
+        /// Store finalized aggregation results rather than intermediate ones in segments written
+        /// by a multi-stage query, `false` keeps them re-aggregatable (rollup).
+        "finalizeAggregations" => finalize_aggregations: bool,
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Vectorize {
+    False,
+    True,
+    Force,
+}
+
+impl FromStr for Vectorize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "false" => Ok(Vectorize::False),
+            "true" => Ok(Vectorize::True),
+            "force" => Ok(Vectorize::Force),
+            _ => Err(format!("unknown vectorize mode `{s}`")),
+        }
+    }
+}
+
+impl QueryContext {
+    /// Sets a context key by its druid name. Known keys end up in their typed field (string
+    /// values such as `"true"` or `"1000"` are parsed), unknown ones in `extra`. A value that
+    /// doesn't parse clears the typed field and is sent as is, for druid to report it.
+    pub fn insert<V: Into<Value>>(&mut self, key: &str, value: V) {
+        let value = value.into();
+        if self.set_typed(key, &value) == Some(true) {
+            self.extra.remove(key);
+        } else {
+            self.extra.insert(key.to_string(), value);
+        }
+    }
+
+    /// Value of a context key by its druid name, typed fields included.
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.get_typed(key).or_else(|| self.extra.get(key).cloned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_serialize_typed_keys() {
+        let mut context = QueryContext {
+            timeout: Some(1000),
+            vectorize: Some(Vectorize::Force),
+            ..Default::default()
+        };
+        context.insert("useCache", "false");
+        context.insert("priority", 10);
+        context.insert("groupByStrategy", "v2");
+
+        assert_eq!(context.use_cache, Some(false));
+        assert_eq!(context.priority, Some(10));
+        assert_eq!(
+            serde_json::to_value(&context).unwrap(),
+            serde_json::json!({
+                "timeout": 1000,
+                "priority": 10,
+                "useCache": false,
+                "vectorize": "force",
+                "groupByStrategy": "v2"
+            })
+        );
+        assert_eq!(
+            serde_json::to_string(&QueryContext::default()).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_invalid_typed_value() {
+        let mut context = QueryContext {
+            timeout: Some(1000),
+            ..Default::default()
+        };
+        context.insert("timeout", "abc");
+        assert_eq!(context.timeout, None);
+        assert_eq!(context.get("timeout"), Some(Value::from("abc")));
+        assert_eq!(
+            serde_json::to_string(&context).unwrap(),
+            r#"{"timeout":"abc"}"#
+        );
+
+        context.insert("timeout", 500);
+        assert_eq!(context.timeout, Some(500));
+        assert_eq!(context.get("timeout"), Some(Value::from(500)));
+        assert_eq!(
+            serde_json::to_string(&context).unwrap(),
+            r#"{"timeout":500}"#
+        );
+        assert_eq!(context.get("vectorize"), None);
+    }
+}
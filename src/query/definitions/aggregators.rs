@@ -73,6 +73,40 @@ pub enum Aggregation {
 }
 
 impl Aggregation {
+    /// Name of the column the aggregation is returned in.
+    pub fn name(&self) -> &str {
+        match self {
+            Aggregation::Count { name, .. }
+            | Aggregation::LongSum { name, .. }
+            | Aggregation::DoubleSum { name, .. }
+            | Aggregation::FloatSum { name, .. }
+            | Aggregation::LongMax { name, .. }
+            | Aggregation::DoubleMax { name, .. }
+            | Aggregation::FloatMax { name, .. }
+            | Aggregation::LongMin { name, .. }
+            | Aggregation::FloatMin { name, .. }
+            | Aggregation::DoubleMin { name, .. }
+            | Aggregation::LongFirst { name, .. }
+            | Aggregation::FloatFirst { name, .. }
+            | Aggregation::DoubleFirst { name, .. }
+            | Aggregation::LongLast { name, .. }
+            | Aggregation::FloatLast { name, .. }
+            | Aggregation::DoubleLast { name, .. }
+            | Aggregation::StringFirst { name, .. }
+            | Aggregation::StringLast { name, .. }
+            | Aggregation::DoubleAny { name, .. }
+            | Aggregation::FloatAny { name, .. }
+            | Aggregation::LongAny { name, .. }
+            | Aggregation::StringAny { name, .. }
+            | Aggregation::Javascript { name, .. }
+            | Aggregation::ThetaSketch { name, .. }
+            | Aggregation::HLLSketchBuild { name, .. }
+            | Aggregation::Cardinality { name, .. }
+            | Aggregation::HyperUnique { name, .. } => name,
+            Aggregation::Filtered { aggregator, .. } => aggregator.name(),
+        }
+    }
+
     pub fn count(name: &str) -> Aggregation {
         Aggregation::Count {
             name: name.to_string(),
@@ -57,6 +57,19 @@ pub enum Dimension {
 
 // todo: macro
 impl Dimension {
+    /// Name of the column the dimension is returned in.
+    pub fn output_name(&self) -> &str {
+        match self {
+            Dimension::Default { output_name, .. }
+            | Dimension::Extraction { output_name, .. }
+            | Dimension::LookupMap { output_name, .. }
+            | Dimension::Lookup { output_name, .. } => output_name,
+            Dimension::ListFiltered { delegate, .. }
+            | Dimension::RegexFiltered { delegate, .. }
+            | Dimension::PrefixFiltered { delegate, .. } => delegate.output_name(),
+        }
+    }
+
     pub fn default(dimension: &str) -> Dimension {
         Dimension::Default {
             dimension: dimension.into(),
@@ -39,6 +39,18 @@ pub enum PostAggregation {
 }
 
 impl PostAggregation {
+    /// Name of the column the post aggregation is returned in.
+    pub fn name(&self) -> &str {
+        match self {
+            PostAggregation::Arithmetic { name, .. }
+            | PostAggregation::DoubleGreatest { name, .. }
+            | PostAggregation::LongGreatest { name, .. }
+            | PostAggregation::LongLeast { name, .. }
+            | PostAggregation::DoubleLeast { name, .. }
+            | PostAggregation::Javascript { name, .. } => name,
+        }
+    }
+
     pub fn arithmetic(
         name: &str,
         function: &str,
@@ -1,9 +1,10 @@
 use super::definitions::Dimension;
 use super::definitions::Filter;
-use super::definitions::Granularity;
 use super::definitions::Interval;
 use super::definitions::VirtualColumn;
+use super::definitions::{Granularity, GranularityBase};
 use super::DataSource;
+use crate::query::context::QueryContext;
 use crate::query::definitions::Aggregation;
 use crate::query::definitions::Having;
 use crate::query::definitions::Limit;
@@ -25,7 +26,40 @@ pub struct GroupBy {
     pub virtual_columns: Vec<VirtualColumn>,
     pub intervals: Vec<Interval>,
     pub subtotal_spec: Vec<Vec<String>>,
-    pub context: std::collections::HashMap<String, String>,
+    pub context: QueryContext,
+}
+
+impl GroupBy {
+    /// Whether druid returns the rows as arrays, see the `resultAsArray` context key.
+    pub(crate) fn result_as_array(&self) -> bool {
+        self.context.result_as_array.unwrap_or(false)
+    }
+
+    /// Names of the values of `resultAsArray` rows after their timestamp: dimensions,
+    /// aggregations and post aggregations.
+    pub(crate) fn array_columns(&self) -> Vec<String> {
+        self.dimensions
+            .iter()
+            .map(Dimension::output_name)
+            .chain(self.aggregations.iter().map(Aggregation::name))
+            .chain(self.post_aggregations.iter().map(PostAggregation::name))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Timestamp of the rows of the `all` granularity, which `resultAsArray` rows come without:
+    /// the start of the first interval, as druid puts in object rows.
+    pub(crate) fn all_granularity_timestamp(&self) -> Option<String> {
+        match self.granularity {
+            Granularity::Base(GranularityBase::All) => Some(
+                self.intervals
+                    .first()
+                    .map(|interval| interval.from.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+                    .unwrap_or_default(),
+            ),
+            _ => None,
+        }
+    }
 }
 
 pub struct GroupByBuilder {
@@ -40,7 +74,7 @@ pub struct GroupByBuilder {
     virtual_columns: Vec<VirtualColumn>,
     intervals: Vec<Interval>,
     subtotal_spec: Vec<Vec<String>>,
-    context: std::collections::HashMap<String, String>,
+    context: QueryContext,
 }
 
 impl GroupByBuilder {
@@ -57,7 +91,7 @@ impl GroupByBuilder {
             virtual_columns: vec![],
             intervals: vec![],
             subtotal_spec: vec![],
-            context: std::collections::HashMap::new(),
+            context: QueryContext::default(),
         }
     }
     pub fn dimensions(mut self, dimensions: Vec<Dimension>) -> Self {
@@ -100,15 +134,21 @@ impl GroupByBuilder {
         self.subtotal_spec = subtotals;
         self
     }
-    pub fn context(mut self, context: std::collections::HashMap<String, String>) -> Self {
+    pub fn context(mut self, context: QueryContext) -> Self {
         self.context = context;
         self
     }
 
-    pub fn add_context(mut self, key: &str, value: &str) -> Self {
-        self.context.insert(key.to_string(), value.to_string());
+    pub fn add_context<V: Into<serde_json::Value>>(mut self, key: &str, value: V) -> Self {
+        self.context.insert(key, value);
         self
     }
+    /// Sets `context.queryId`, the id [`DruidClient::cancel`](crate::client::DruidClient::cancel) expects.
+    pub fn query_id(mut self, query_id: &str) -> Self {
+        self.context.query_id = Some(query_id.to_string());
+        self
+    }
+
     pub fn build(self) -> GroupBy {
         GroupBy {
             data_source: self.data_source,
@@ -1,4 +1,5 @@
 use crate::query::search::Search;
+use context::QueryContext;
 use group_by::GroupBy;
 use scan::Scan;
 use segment_metadata::SegmentMetadata;
@@ -7,12 +8,15 @@ use time_boundary::TimeBoundary;
 use timeseries::Timeseries;
 use top_n::TopN;
 
+pub mod context;
 pub mod definitions;
 pub mod group_by;
 pub mod response;
+pub mod row;
 pub mod scan;
 pub mod search;
 pub mod segment_metadata;
+pub mod sql;
 pub mod time_boundary;
 pub mod timeseries;
 pub mod top_n;
@@ -175,7 +179,7 @@ pub enum JoinType {
 #[serde(tag = "queryType", rename = "dataSourceMetadata")]
 pub struct DataSourceMetadata {
     pub data_source: DataSource,
-    pub context: std::collections::HashMap<String, String>,
+    pub context: QueryContext,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -197,9 +201,14 @@ impl From<isize> for JsonNumber {
     }
 }
 
+/// Any json scalar. Numbers are deserialized as `Long` or `Double`, untagged variants being
+/// tried in order.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
+#[non_exhaustive]
 pub enum JsonAny {
+    Long(i64),
+    Double(f64),
     Float(f32),
     Integer(isize),
     STRING(String),
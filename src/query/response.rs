@@ -1,9 +1,12 @@
 use super::{definitions::Granularity, JsonAny};
+use crate::serialization::compact::{EventSeed, EventsSeed};
 use crate::serialization::default_for_null;
 use crate::serialization::tagged_or_untagged;
-use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct DruidListResponse<T: DeserializeOwned> {
@@ -37,15 +40,70 @@ pub struct DimValue {
 
 pub type SearchResponse = DruidListResponse<DimValue>;
 
-#[derive(Deserialize, Serialize, Debug)]
+/// Scan results of a segment. `compactedList` events are positional arrays of the `columns`
+/// values, zipped with the column names when `T` is a struct or a map.
+#[derive(Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ScanResponse<T: DeserializeOwned> {
     pub segment_id: String,
     pub columns: Vec<String>,
-    #[serde(bound(deserialize = ""))]
     pub events: Vec<T>,
 }
 
+impl<'de, T: DeserializeOwned> Deserialize<'de> for ScanResponse<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(ScanResponseVisitor(PhantomData))
+    }
+}
+
+struct ScanResponseVisitor<T>(PhantomData<T>);
+
+impl<'de, T: DeserializeOwned> Visitor<'de> for ScanResponseVisitor<T> {
+    type Value = ScanResponse<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a scan response")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut segment_id = None;
+        let mut columns: Option<Vec<String>> = None;
+        let mut events = None;
+        // events sent before the columns are buffered until the columns are known
+        let mut buffered: Option<Vec<serde_json::Value>> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "segmentId" => segment_id = Some(map.next_value()?),
+                "columns" => columns = Some(map.next_value()?),
+                "events" => match &columns {
+                    Some(columns) => {
+                        events = Some(map.next_value_seed(EventsSeed(columns, PhantomData))?)
+                    }
+                    None => buffered = Some(map.next_value()?),
+                },
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        let columns = columns.unwrap_or_default();
+        if let Some(buffered) = buffered {
+            events = Some(
+                buffered
+                    .into_iter()
+                    .map(|event| EventSeed::new(&columns).deserialize(event))
+                    .collect::<Result<_, _>>()
+                    .map_err(de::Error::custom)?,
+            );
+        }
+        Ok(ScanResponse {
+            segment_id: segment_id.ok_or_else(|| de::Error::missing_field("segmentId"))?,
+            columns,
+            events: events.ok_or_else(|| de::Error::missing_field("events"))?,
+        })
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct MinMaxTime {
@@ -55,61 +113,182 @@ pub struct MinMaxTime {
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct TimeBoundaryResponse {
-    timestamp: String,
-    result: MinMaxTime,
+    pub timestamp: String,
+    pub result: MinMaxTime,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ColumnDefinition {
     #[serde(rename(deserialize = "type"))]
-    column_type: String,
-    has_multiple_values: bool,
-    size: usize,
-    cardinality: Option<f32>,
-    min_value: Option<JsonAny>,
-    max_value: Option<JsonAny>,
-    error_message: Option<String>,
+    pub column_type: String,
+    pub has_multiple_values: bool,
+    pub size: usize,
+    pub cardinality: Option<f32>,
+    pub min_value: Option<JsonAny>,
+    pub max_value: Option<JsonAny>,
+    pub error_message: Option<String>,
 }
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct AggregatorDefinition {
     #[serde(rename(deserialize = "type"))]
-    aggr_type: String,
-    name: String,
-    field_name: String,
-    expression: Option<String>,
+    pub aggr_type: String,
+    pub name: String,
+    pub field_name: String,
+    pub expression: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct TimestampSpec {
-    column: String,
-    format: String,
-    missing_value: Option<String>,
+    pub column: String,
+    pub format: String,
+    pub missing_value: Option<String>,
 }
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct SegmentMetadataResponse {
-    id: String,
+    pub id: String,
     #[serde(default, deserialize_with = "default_for_null")]
-    intervals: Vec<String>,
-    columns: HashMap<String, ColumnDefinition>,
+    pub intervals: Vec<String>,
+    pub columns: HashMap<String, ColumnDefinition>,
 
     #[serde(deserialize_with = "tagged_or_untagged")]
-    query_granularity: Granularity,
-    rollup: Option<bool>,
-    size: Option<usize>,
-    num_rows: Option<usize>,
-    timestamp_spec: TimestampSpec,
+    pub query_granularity: Granularity,
+    pub rollup: Option<bool>,
+    pub size: Option<usize>,
+    pub num_rows: Option<usize>,
+    pub timestamp_spec: TimestampSpec,
     #[serde(default, deserialize_with = "default_for_null")]
-    aggregators: HashMap<String, AggregatorDefinition>,
+    pub aggregators: HashMap<String, AggregatorDefinition>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct TimeseriesResponse<T: DeserializeOwned> {
-    timestamp: Option<String>,
+    pub timestamp: Option<String>,
     #[serde(bound(deserialize = ""))]
-    result: T,
+    pub result: T,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlColumn {
+    pub name: String,
+    pub column_type: Option<String>,
+    pub sql_type: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SqlResultSet {
+    pub columns: Vec<SqlColumn>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// Error body returned by druid when a query fails. Older brokers only fill `error`,
+/// `errorMessage`, `errorClass` and `host`, newer ones report a `druidException` with
+/// `errorCode`, `persona`, `category` and `context`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DruidError {
+    pub error: Option<String>,
+    pub error_message: Option<String>,
+    pub error_class: Option<String>,
+    pub host: Option<String>,
+    pub error_code: Option<String>,
+    pub persona: Option<ErrorPersona>,
+    pub category: Option<ErrorCategory>,
+    #[serde(default, deserialize_with = "default_for_null")]
+    pub context: HashMap<String, serde_json::Value>,
+}
+
+impl std::fmt::Display for DruidError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = self.error_code.as_ref().or(self.error.as_ref());
+        match (code, &self.error_message) {
+            (Some(code), Some(message)) => write!(f, "{code}: {message}"),
+            (Some(code), None) => write!(f, "{code}"),
+            (None, Some(message)) => write!(f, "{message}"),
+            (None, None) => write!(f, "unknown error"),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorPersona {
+    User,
+    Admin,
+    Operator,
+    Developer,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCategory {
+    Defensive,
+    InvalidInput,
+    Unauthorized,
+    Forbidden,
+    CapacityExceeded,
+    Canceled,
+    RuntimeFailure,
+    Timeout,
+    Unsupported,
+    Uncategorized,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Druid's response context, sent back in the `X-Druid-Response-Context` header.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseContext {
+    /// Intervals of the query no segment was available for, reported when the query context
+    /// sets `uncoveredIntervalsLimit`.
+    #[serde(default, deserialize_with = "default_for_null")]
+    pub uncovered_intervals: Vec<String>,
+    #[serde(default)]
+    pub uncovered_intervals_overflowed: bool,
+    #[serde(default, deserialize_with = "default_for_null")]
+    pub missing_segments: Vec<SegmentDescriptor>,
+    #[serde(rename = "ETag")]
+    pub etag: Option<String>,
+    /// Set when the broker had to drop keys because the context exceeded the header size limit.
+    #[serde(default)]
+    pub truncated: bool,
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SegmentDescriptor {
+    pub itvl: String,
+    pub ver: String,
+    pub part: usize,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ResponseMetadata {
+    pub query_id: Option<String>,
+    pub context: ResponseContext,
+}
+
+impl ResponseMetadata {
+    /// Whether some of the data the query asked for wasn't queried, because segments were
+    /// missing or intervals weren't covered by any segment.
+    pub fn is_partial(&self) -> bool {
+        !self.context.missing_segments.is_empty()
+            || !self.context.uncovered_intervals.is_empty()
+            || self.context.uncovered_intervals_overflowed
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WithMetadata<T> {
+    pub rows: Vec<T>,
+    pub metadata: ResponseMetadata,
 }
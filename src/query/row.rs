@@ -0,0 +1,293 @@
+//! Results of any native query without a compile-time row type, see
+//! [`DruidClient::query_rows`](crate::client::DruidClient::query_rows).
+
+use super::definitions::{Aggregation, Dimension, PostAggregation};
+use super::response::{DruidListResponse, GroupByResponse, ScanResponse, TimeseriesResponse};
+use super::Query;
+use crate::serialization::compact::GroupByRowSeed;
+use crate::serialization::values::{as_f64, as_i64, as_timestamp_millis};
+use chrono::{DateTime, Utc};
+use serde::de::{DeserializeSeed, Error as _};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+/// Name of the column holding the timestamp of aggregated results.
+const TIMESTAMP: &str = "timestamp";
+
+/// One result row. Columns are in the order of the scan response's `columns` or of the query's
+/// output names: timestamp, dimensions, aggregations and post aggregations. GroupBy events and
+/// TopN and Search result lists are flattened to one row each.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    columns: Arc<[String]>,
+    values: Vec<Value>,
+}
+
+impl Row {
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Value of a column, `None` if the row has no such column.
+    pub fn get(&self, column: &str) -> Option<&Value> {
+        let index = self.columns.iter().position(|name| name == column)?;
+        self.values.get(index)
+    }
+
+    pub fn get_str(&self, column: &str) -> Option<&str> {
+        self.get(column)?.as_str()
+    }
+
+    /// Integer value of a column, `None` for doubles that aren't whole numbers.
+    pub fn get_i64(&self, column: &str) -> Option<i64> {
+        as_i64(self.get(column)?)
+    }
+
+    /// Numeric value of a column, including the `NaN` and `Infinity` strings druid sends.
+    pub fn get_f64(&self, column: &str) -> Option<f64> {
+        as_f64(self.get(column)?)
+    }
+
+    /// Timestamp of a column holding epoch millis, like `__time`, or an ISO 8601 date.
+    pub fn get_timestamp(&self, column: &str) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_millis(as_timestamp_millis(self.get(column)?)?)
+    }
+
+    /// Column names and values in column order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.columns
+            .iter()
+            .map(String::as_str)
+            .zip(self.values.iter())
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// Serialized as a json object with the columns in order.
+impl Serialize for Row {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (column, value) in self.iter() {
+            map.serialize_entry(column, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Output names of a query, in the order druid documents them. Columns of the results missing
+/// from them are added after.
+fn output_names(query: &Query) -> Vec<String> {
+    let names: Vec<&str> = match query {
+        Query::GroupBy(query) => std::iter::once(TIMESTAMP)
+            .chain(query.dimensions.iter().map(Dimension::output_name))
+            .chain(query.aggregations.iter().map(Aggregation::name))
+            .chain(query.post_aggregations.iter().map(PostAggregation::name))
+            .collect(),
+        Query::TopN(query) => [TIMESTAMP, query.dimension.output_name()]
+            .into_iter()
+            .chain(query.aggregations.iter().map(Aggregation::name))
+            .collect(),
+        Query::Timeseries(query) => std::iter::once(TIMESTAMP)
+            .chain(query.aggregations.iter().map(Aggregation::name))
+            .chain(query.post_aggregations.iter().map(PostAggregation::name))
+            .collect(),
+        Query::Search(_) => vec![TIMESTAMP, "dimension", "value", "count"],
+        Query::TimeBoundary(_) => vec![TIMESTAMP],
+        Query::Scan(query) => query.columns.iter().map(String::as_str).collect(),
+        Query::SegmentMetadata(_) => vec![],
+    };
+    names.into_iter().map(String::from).collect()
+}
+
+fn object(value: Value) -> Result<Map<String, Value>, serde_json::Error> {
+    match value {
+        Value::Object(fields) => Ok(fields),
+        other => Err(serde_json::Error::custom(format!(
+            "expected a json object, got {other}"
+        ))),
+    }
+}
+
+fn timestamped(timestamp: Option<String>, fields: Map<String, Value>) -> Map<String, Value> {
+    let mut record = Map::new();
+    record.insert(
+        TIMESTAMP.into(),
+        timestamp.map_or(Value::Null, Value::String),
+    );
+    record.extend(fields);
+    record
+}
+
+/// Flattens the json results of `query` to rows.
+pub(crate) fn rows(query: &Query, results: Vec<Value>) -> Result<Vec<Row>, serde_json::Error> {
+    let mut columns = output_names(query);
+    let mut records = vec![];
+    for result in results {
+        match query {
+            Query::Scan(_) => {
+                let response: ScanResponse<Value> = serde_json::from_value(result)?;
+                for column in &response.columns {
+                    if !columns.contains(column) {
+                        columns.push(column.clone());
+                    }
+                }
+                for event in response.events {
+                    records.push(match event {
+                        // compactedList events are positional
+                        Value::Array(values) => {
+                            response.columns.iter().cloned().zip(values).collect()
+                        }
+                        event => object(event)?,
+                    });
+                }
+            }
+            Query::GroupBy(query) => {
+                let response: GroupByResponse<Map<String, Value>> = if result.is_array() {
+                    let columns = query.array_columns();
+                    let timestamp = query.all_granularity_timestamp();
+                    GroupByRowSeed::new(&columns, timestamp.as_deref()).deserialize(result)?
+                } else {
+                    serde_json::from_value(result)?
+                };
+                records.push(timestamped(Some(response.timestamp), response.event));
+            }
+            Query::TopN(_) | Query::Search(_) => {
+                let response: DruidListResponse<Map<String, Value>> =
+                    serde_json::from_value(result)?;
+                for fields in response.result {
+                    records.push(timestamped(Some(response.timestamp.clone()), fields));
+                }
+            }
+            Query::Timeseries(_) | Query::TimeBoundary(_) => {
+                let response: TimeseriesResponse<Map<String, Value>> =
+                    serde_json::from_value(result)?;
+                records.push(timestamped(response.timestamp, response.result));
+            }
+            Query::SegmentMetadata(_) => records.push(object(result)?),
+        }
+    }
+    for record in &records {
+        for column in record.keys() {
+            if !columns.contains(column) {
+                columns.push(column.clone());
+            }
+        }
+    }
+    let columns: Arc<[String]> = columns.into();
+    Ok(records
+        .into_iter()
+        .map(|mut record| Row {
+            values: columns
+                .iter()
+                .map(|column| record.remove(column).unwrap_or(Value::Null))
+                .collect(),
+            columns: columns.clone(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::query::definitions::{Granularity, Interval};
+    use crate::query::scan::ScanBuilder;
+    use crate::query::top_n::TopN;
+    use crate::query::DataSource;
+    use chrono::NaiveDate;
+
+    fn interval() -> Interval {
+        Interval {
+            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            to: NaiveDate::from_ymd_opt(2015, 9, 13)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_top_n_rows() {
+        let query = Query::TopN(TopN {
+            data_source: DataSource::table("wikipedia"),
+            dimension: Dimension::default("page"),
+            threshold: 2,
+            metric: "count".into(),
+            aggregations: vec![
+                Aggregation::count("count"),
+                Aggregation::double_sum("added", "added"),
+            ],
+            virtual_columns: vec![],
+            granularity: Granularity::all(),
+            intervals: vec![interval()],
+            context: Default::default(),
+        });
+        // post aggregations missing from the query are kept after its output names
+        let results: Vec<Value> = serde_json::from_str(
+            r#"[{"timestamp": "2015-09-12T00:00:00.000Z", "result": [
+                {"page": "Main", "count": 3, "added": 12.0, "ratio": 4.0},
+                {"page": "Talk", "count": 1, "added": "NaN", "ratio": "NaN"}
+            ]}]"#,
+        )
+        .unwrap();
+        let rows = rows(&query, results).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0].columns(),
+            &["timestamp", "page", "count", "added", "ratio"]
+        );
+        assert_eq!(rows[1].get_str("page"), Some("Talk"));
+        assert_eq!(rows[0].get_i64("count"), Some(3));
+        assert_eq!(rows[0].get_f64("ratio"), Some(4.0));
+        assert!(rows[1].get_f64("added").unwrap().is_nan());
+        assert_eq!(
+            rows[0]
+                .get_timestamp("timestamp")
+                .unwrap()
+                .timestamp_millis(),
+            1442016000000
+        );
+        assert_eq!(rows[0].get("missing"), None);
+        assert_eq!(
+            serde_json::to_string(&rows[0]).unwrap(),
+            r#"{"timestamp":"2015-09-12T00:00:00.000Z","page":"Main","count":3,"added":12.0,"ratio":4.0}"#
+        );
+    }
+
+    #[test]
+    fn test_compacted_scan_rows() {
+        let query = Query::Scan(
+            ScanBuilder::new(DataSource::table("wikipedia"))
+                .columns(vec!["__time".into(), "page".into(), "added".into()])
+                .build(),
+        );
+        let results: Vec<Value> = serde_json::from_str(
+            r#"[{"segmentId": "s1", "columns": ["__time", "page", "added"], "events": [[1442016000000, "Main", 5], [1442016001000, null, 7]]}]"#,
+        )
+        .unwrap();
+        let rows = rows(&query, results).unwrap();
+        assert_eq!(rows[1].columns(), &["__time", "page", "added"]);
+        assert_eq!(
+            rows[1].get_timestamp("__time").unwrap().timestamp_millis(),
+            1442016001000
+        );
+        assert_eq!(rows[1].get("page"), Some(&Value::Null));
+        assert_eq!(rows[1].get_str("page"), None);
+        assert_eq!(rows[1].get_i64("added"), Some(7));
+    }
+}
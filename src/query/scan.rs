@@ -3,6 +3,7 @@ use super::definitions::Interval;
 use super::definitions::Ordering;
 use super::definitions::VirtualColumn;
 use super::DataSource;
+use crate::query::context::QueryContext;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -18,7 +19,7 @@ pub struct Scan {
     pub batch_size: usize,
     pub limit: Option<usize>,
     pub order: Ordering,
-    pub context: std::collections::HashMap<String, String>,
+    pub context: QueryContext,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -39,7 +40,7 @@ pub struct ScanBuilder {
     batch_size: usize,
     limit: Option<usize>,
     order: Ordering,
-    context: std::collections::HashMap<String, String>,
+    context: QueryContext,
 }
 
 impl ScanBuilder {
@@ -54,7 +55,7 @@ impl ScanBuilder {
             batch_size: 20480,
             limit: None,
             order: Ordering::None,
-            context: std::collections::HashMap::new(),
+            context: QueryContext::default(),
         }
     }
 
@@ -98,13 +99,19 @@ impl ScanBuilder {
         self
     }
 
-    pub fn context(mut self, context: std::collections::HashMap<String, String>) -> Self {
+    pub fn context(mut self, context: QueryContext) -> Self {
         self.context = context;
         self
     }
 
-    pub fn add_context(mut self, key: &str, value: &str) -> Self {
-        self.context.insert(key.to_string(), value.to_string());
+    pub fn add_context<V: Into<serde_json::Value>>(mut self, key: &str, value: V) -> Self {
+        self.context.insert(key, value);
+        self
+    }
+
+    /// Sets `context.queryId`, the id [`DruidClient::cancel`](crate::client::DruidClient::cancel) expects.
+    pub fn query_id(mut self, query_id: &str) -> Self {
+        self.context.query_id = Some(query_id.to_string());
         self
     }
 
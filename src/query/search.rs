@@ -3,6 +3,7 @@ use super::definitions::Granularity;
 use super::definitions::Interval;
 use super::definitions::SortingOrder;
 use super::DataSource;
+use crate::query::context::QueryContext;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -17,7 +18,7 @@ pub struct Search {
     pub search_dimensions: Vec<String>,
     pub query: SearchQuerySpec,
     pub sort: Option<SortingOrder>,
-    pub context: std::collections::HashMap<String, String>,
+    pub context: QueryContext,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -0,0 +1,450 @@
+use super::JsonAny;
+use crate::query::context::QueryContext;
+use crate::query::response::{SqlColumn, SqlResultSet};
+use crate::serialization::csv;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlQuery {
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<SqlParameter>,
+    pub result_format: SqlResultFormat,
+    pub header: bool,
+    pub types_header: bool,
+    pub sql_types_header: bool,
+    pub context: QueryContext,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SqlResultFormat {
+    Object,
+    Array,
+    ObjectLines,
+    ArrayLines,
+    Csv,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SqlParameter {
+    #[serde(rename = "type")]
+    pub parameter_type: SqlType,
+    pub value: JsonAny,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SqlType {
+    Char,
+    Varchar,
+    Boolean,
+    Tinyint,
+    Smallint,
+    Integer,
+    Bigint,
+    Float,
+    Real,
+    Double,
+    Decimal,
+    Timestamp,
+    Date,
+    Other,
+}
+
+impl SqlParameter {
+    pub fn new(parameter_type: SqlType, value: JsonAny) -> Self {
+        SqlParameter {
+            parameter_type,
+            value,
+        }
+    }
+    pub fn varchar(value: &str) -> Self {
+        SqlParameter::new(SqlType::Varchar, value.into())
+    }
+    pub fn bigint(value: i64) -> Self {
+        SqlParameter::new(SqlType::Bigint, JsonAny::Long(value))
+    }
+    pub fn double(value: f64) -> Self {
+        SqlParameter::new(SqlType::Double, JsonAny::Double(value))
+    }
+    pub fn boolean(value: bool) -> Self {
+        SqlParameter::new(SqlType::Boolean, value.into())
+    }
+    pub fn timestamp(value: &str) -> Self {
+        SqlParameter::new(SqlType::Timestamp, value.into())
+    }
+}
+
+pub struct SqlQueryBuilder {
+    query: String,
+    parameters: Vec<SqlParameter>,
+    result_format: SqlResultFormat,
+    header: bool,
+    types_header: bool,
+    sql_types_header: bool,
+    context: QueryContext,
+}
+
+impl SqlQueryBuilder {
+    pub fn new(query: &str) -> Self {
+        SqlQueryBuilder {
+            query: query.to_string(),
+            parameters: vec![],
+            result_format: SqlResultFormat::Object,
+            header: false,
+            types_header: false,
+            sql_types_header: false,
+            context: QueryContext::default(),
+        }
+    }
+    pub fn parameters(mut self, parameters: Vec<SqlParameter>) -> Self {
+        self.parameters = parameters;
+        self
+    }
+    pub fn add_parameter(mut self, parameter: SqlParameter) -> Self {
+        self.parameters.push(parameter);
+        self
+    }
+    pub fn result_format(mut self, result_format: SqlResultFormat) -> Self {
+        self.result_format = result_format;
+        self
+    }
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+    pub fn types_header(mut self, types_header: bool) -> Self {
+        self.types_header = types_header;
+        self
+    }
+    pub fn sql_types_header(mut self, sql_types_header: bool) -> Self {
+        self.sql_types_header = sql_types_header;
+        self
+    }
+    pub fn context(mut self, context: QueryContext) -> Self {
+        self.context = context;
+        self
+    }
+    pub fn add_context<V: Into<serde_json::Value>>(mut self, key: &str, value: V) -> Self {
+        self.context.insert(key, value);
+        self
+    }
+    pub fn build(self) -> SqlQuery {
+        SqlQuery {
+            query: self.query,
+            parameters: self.parameters,
+            result_format: self.result_format,
+            header: self.header,
+            types_header: self.types_header,
+            sql_types_header: self.sql_types_header,
+            context: self.context,
+        }
+    }
+}
+
+/// The query without its `--` and `/* */` comments, quoted literals and identifiers are
+/// emptied so that the `;` in them don't split statements.
+fn strip_comments(query: &str) -> String {
+    let mut stripped = String::with_capacity(query.len());
+    let mut rest = query;
+    while let Some(start) = rest.find(['\'', '"', '-', '/']) {
+        stripped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = if let Some(comment) = rest.strip_prefix("--") {
+            stripped.push('\n');
+            comment.find('\n').map_or(rest.len(), |end| end + 3)
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            stripped.push(' ');
+            comment.find("*/").map_or(rest.len(), |end| end + 4)
+        } else {
+            let quote = &rest[..1];
+            match quote {
+                "'" | "\"" => {
+                    stripped.push_str(quote);
+                    stripped.push_str(quote);
+                    rest[1..].find(quote).map_or(rest.len(), |end| end + 2)
+                }
+                _ => {
+                    stripped.push_str(quote);
+                    1
+                }
+            }
+        };
+        rest = &rest[end..];
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+#[derive(Debug)]
+pub(crate) enum SqlParseError {
+    Json(serde_json::Error),
+    Truncated,
+}
+
+impl From<serde_json::Error> for SqlParseError {
+    fn from(err: serde_json::Error) -> Self {
+        SqlParseError::Json(err)
+    }
+}
+
+impl SqlQuery {
+    /// Whether sending the query twice is harmless, i.e. it isn't an `INSERT` or `REPLACE`.
+    /// Comments and `SET` statements before the query are skipped.
+    pub(crate) fn is_idempotent(&self) -> bool {
+        let query = strip_comments(&self.query);
+        let statement = query
+            .split(';')
+            .map(str::trim)
+            .rfind(|statement| !statement.is_empty())
+            .unwrap_or_default();
+        let keyword = statement
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .next()
+            .unwrap_or_default();
+        !["INSERT", "REPLACE"]
+            .iter()
+            .any(|dml| keyword.eq_ignore_ascii_case(dml))
+    }
+
+    // number of leading rows druid spends on headers in the array-like formats
+    fn header_rows(&self) -> usize {
+        if !self.header {
+            return 0;
+        }
+        1 + self.types_header as usize + self.sql_types_header as usize
+    }
+
+    pub(crate) fn parse_response<T: DeserializeOwned>(
+        &self,
+        body: &str,
+    ) -> Result<Vec<T>, SqlParseError> {
+        match self.result_format {
+            SqlResultFormat::Object => {
+                let rows = serde_json::from_str::<Vec<Value>>(body)?;
+                self.decode_json_rows(rows, self.header as usize)
+            }
+            SqlResultFormat::Array => {
+                let rows = serde_json::from_str::<Vec<Value>>(body)?;
+                self.decode_json_rows(rows, self.header_rows())
+            }
+            SqlResultFormat::ObjectLines => {
+                let rows = json_lines(body)?;
+                self.decode_json_rows(rows, self.header as usize)
+            }
+            SqlResultFormat::ArrayLines => {
+                let rows = json_lines(body)?;
+                self.decode_json_rows(rows, self.header_rows())
+            }
+            SqlResultFormat::Csv => {
+                let mut records = csv::parse_records(body);
+                // a complete csv response is terminated by an empty line
+                match records.pop() {
+                    Some(last) if last.len() == 1 && last[0].is_empty() => {}
+                    _ => return Err(SqlParseError::Truncated),
+                }
+                let names = if self.header && !records.is_empty() {
+                    Some(records[0].clone())
+                } else {
+                    None
+                };
+                records
+                    .iter()
+                    .skip(self.header_rows())
+                    .map(|record| {
+                        T::deserialize(csv::RecordDeserializer::new(record, names.as_deref()))
+                            .map_err(SqlParseError::Json)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn decode_json_rows<T: DeserializeOwned>(
+        &self,
+        rows: Vec<Value>,
+        skip: usize,
+    ) -> Result<Vec<T>, SqlParseError> {
+        rows.into_iter()
+            .skip(skip)
+            .map(|row| serde_json::from_value(row).map_err(SqlParseError::Json))
+            .collect()
+    }
+
+    /// Copy of the query that asks druid for array rows preceded by all header rows.
+    pub(crate) fn with_full_header(&self) -> SqlQuery {
+        SqlQuery {
+            result_format: SqlResultFormat::Array,
+            header: true,
+            types_header: true,
+            sql_types_header: true,
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn parse_result_set(body: &str) -> Result<SqlResultSet, SqlParseError> {
+        let mut rows = serde_json::from_str::<Vec<Vec<Value>>>(body)?.into_iter();
+        let names = rows.next().unwrap_or_default();
+        let types = rows.next().unwrap_or_default();
+        let sql_types = rows.next().unwrap_or_default();
+
+        let columns = names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| SqlColumn {
+                name: header_string(Some(name)).unwrap_or_default(),
+                column_type: header_string(types.get(i).cloned()),
+                sql_type: header_string(sql_types.get(i).cloned()),
+            })
+            .collect();
+
+        Ok(SqlResultSet {
+            columns,
+            rows: rows.collect(),
+        })
+    }
+}
+
+fn header_string(value: Option<Value>) -> Option<String> {
+    match value {
+        Some(Value::String(str)) => Some(str),
+        _ => None,
+    }
+}
+
+// objectLines and arrayLines responses are terminated by an empty line, its absence means
+// the broker stopped writing in the middle of the response
+fn json_lines(body: &str) -> Result<Vec<Value>, SqlParseError> {
+    if !body.ends_with("\n\n") && body != "\n" {
+        return Err(SqlParseError::Truncated);
+    }
+    body.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(SqlParseError::Json))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Page {
+        page: String,
+        count: usize,
+    }
+
+    fn query(result_format: SqlResultFormat, header: bool) -> SqlQuery {
+        SqlQueryBuilder::new("SELECT page, COUNT(*) AS \"count\" FROM wikipedia GROUP BY page")
+            .result_format(result_format)
+            .header(header)
+            .build()
+    }
+
+    fn expected() -> Vec<Page> {
+        vec![
+            Page {
+                page: "Main".into(),
+                count: 3,
+            },
+            Page {
+                page: "Foo, \"bar\"".into(),
+                count: 1,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_serialize_query() {
+        let query = SqlQueryBuilder::new("SELECT * FROM wikipedia WHERE user = ?")
+            .add_parameter(SqlParameter::varchar("Taffe316"))
+            .result_format(SqlResultFormat::ArrayLines)
+            .build();
+        assert_eq!(
+            serde_json::to_string(&query).unwrap(),
+            r#"{"query":"SELECT * FROM wikipedia WHERE user = ?","parameters":[{"type":"VARCHAR","value":"Taffe316"}],"resultFormat":"arrayLines","header":false,"typesHeader":false,"sqlTypesHeader":false,"context":{}}"#
+        );
+
+        let parameters = [SqlParameter::double(0.1), SqlParameter::bigint(1 << 53)];
+        assert_eq!(
+            serde_json::to_string(&parameters).unwrap(),
+            r#"[{"type":"DOUBLE","value":0.1},{"type":"BIGINT","value":9007199254740992}]"#
+        );
+        let parameters: Vec<SqlParameter> = serde_json::from_str(
+            r#"[{"type":"DOUBLE","value":0.1},{"type":"BIGINT","value":9007199254740993}]"#,
+        )
+        .unwrap();
+        assert!(matches!(parameters[0].value, JsonAny::Double(value) if value == 0.1));
+        assert!(matches!(
+            parameters[1].value,
+            JsonAny::Long(9007199254740993)
+        ));
+    }
+
+    #[test]
+    fn test_is_idempotent() {
+        let query = |sql: &str| SqlQueryBuilder::new(sql).build();
+        assert!(query("SELECT * FROM wikipedia").is_idempotent());
+        assert!(query("SET useCache = false; select 1;").is_idempotent());
+        assert!(!query("insert INTO w SELECT * FROM wikipedia PARTITIONED BY DAY").is_idempotent());
+        assert!(!query("SET a = 1;\nREPLACE INTO w OVERWRITE ALL SELECT 1").is_idempotent());
+        assert!(!query("-- load\nINSERT INTO w SELECT 1 PARTITIONED BY DAY").is_idempotent());
+        assert!(!query("/* x; y */ REPLACE INTO w OVERWRITE ALL SELECT 1").is_idempotent());
+        assert!(!query("INSERT INTO w SELECT '--;' PARTITIONED BY DAY; -- done").is_idempotent());
+        assert!(query("/* INSERT */ SELECT 1 - 2 -- REPLACE").is_idempotent());
+    }
+
+    #[test]
+    fn test_parse_object() {
+        let body = r#"[{"page":null},{"page":"Main","count":3},{"page":"Foo, \"bar\"","count":1}]"#;
+        let rows = query(SqlResultFormat::Object, true).parse_response::<Page>(body);
+        assert_eq!(rows.unwrap(), expected());
+    }
+
+    #[test]
+    fn test_parse_array() {
+        let body = r#"[["Main",3],["Foo, \"bar\"",1]]"#;
+        let rows = query(SqlResultFormat::Array, false).parse_response::<Page>(body);
+        assert_eq!(rows.unwrap(), expected());
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let body =
+            "{\"page\":\"Main\",\"count\":3}\n{\"page\":\"Foo, \\\"bar\\\"\",\"count\":1}\n\n";
+        let rows = query(SqlResultFormat::ObjectLines, false).parse_response::<Page>(body);
+        assert_eq!(rows.unwrap(), expected());
+
+        let body = "[\"page\",\"count\"]\n[\"Main\",3]\n[\"Foo, \\\"bar\\\"\",1]\n";
+        let rows = query(SqlResultFormat::ArrayLines, true).parse_response::<Page>(body);
+        assert!(matches!(rows, Err(SqlParseError::Truncated)));
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let body = "page,count\nMain,3\n\"Foo, \"\"bar\"\"\",1\n\n";
+        let rows = query(SqlResultFormat::Csv, true).parse_response::<Page>(body);
+        assert_eq!(rows.unwrap(), expected());
+
+        let body = "Main,3\n\"Foo, \"\"bar\"\"\",1\n\n";
+        let rows = query(SqlResultFormat::Csv, false).parse_response::<(String, Option<u8>)>(body);
+        assert_eq!(
+            rows.unwrap(),
+            vec![("Main".into(), Some(3)), ("Foo, \"bar\"".into(), Some(1))]
+        );
+    }
+
+    #[test]
+    fn test_parse_result_set() {
+        let body = r#"[["page","count"],["STRING","LONG"],["VARCHAR","BIGINT"],["Main",3]]"#;
+        let result = SqlQuery::parse_result_set(body).unwrap();
+        assert_eq!(result.columns[1].name, "count");
+        assert_eq!(result.columns[1].column_type.as_deref(), Some("LONG"));
+        assert_eq!(result.columns[1].sql_type.as_deref(), Some("BIGINT"));
+        assert_eq!(result.rows, vec![vec![Value::from("Main"), Value::from(3)]]);
+    }
+}
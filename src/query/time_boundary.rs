@@ -1,5 +1,6 @@
 use super::definitions::Filter;
 use super::DataSource;
+use crate::query::context::QueryContext;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -10,7 +11,7 @@ pub struct TimeBoundary {
     #[serde(skip_serializing_if = "TimeBoundType::is_both")]
     pub bound: TimeBoundType,
     pub filter: Option<Filter>,
-    pub context: std::collections::HashMap<String, String>,
+    pub context: QueryContext,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
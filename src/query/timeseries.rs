@@ -1,9 +1,10 @@
 use super::definitions::Filter;
 use super::definitions::Granularity;
 use super::definitions::Interval;
-use super::definitions::VirtualColumn;
 use super::definitions::PostAggregation;
+use super::definitions::VirtualColumn;
 use super::DataSource;
+use crate::query::context::QueryContext;
 use crate::query::definitions::Aggregation;
 use serde::{Deserialize, Serialize};
 
@@ -20,5 +21,5 @@ pub struct Timeseries {
     pub post_aggregations: Vec<PostAggregation>,
     pub virtual_columns: Vec<VirtualColumn>,
     pub limit: Option<usize>,
-    pub context: std::collections::HashMap<String, String>,
+    pub context: QueryContext,
 }
@@ -4,6 +4,7 @@ use super::definitions::Granularity;
 use super::definitions::Interval;
 use super::definitions::VirtualColumn;
 use super::DataSource;
+use crate::query::context::QueryContext;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -19,5 +20,5 @@ pub struct TopN {
     pub virtual_columns: Vec<VirtualColumn>,
     pub intervals: Vec<Interval>,
     pub granularity: Granularity,
-    pub context: std::collections::HashMap<String, String>,
+    pub context: QueryContext,
 }
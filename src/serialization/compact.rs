@@ -0,0 +1,348 @@
+//! Decoding of the positional result formats, scan `compactedList` and groupBy
+//! `resultAsArray`, into the types their object counterparts deserialize to.
+
+use crate::query::response::GroupByResponse;
+use serde::de::value::{MapAccessDeserializer, StrDeserializer};
+use serde::de::DeserializeOwned;
+use serde::de::{
+    self, DeserializeSeed, Deserializer, IgnoredAny, IntoDeserializer, MapAccess, SeqAccess,
+    Visitor,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// One event, a json object or an array of the values of `columns`. Arrays are zipped with
+/// the column names for types deserialized from a map or a struct, types deserialized from a
+/// sequence (`Vec<Value>`, tuples, `Value`) get the array as is.
+pub(crate) struct EventSeed<'a, T> {
+    columns: &'a [String],
+    marker: PhantomData<T>,
+}
+
+impl<'a, T> EventSeed<'a, T> {
+    pub(crate) fn new(columns: &'a [String]) -> Self {
+        EventSeed {
+            columns,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> DeserializeSeed<'de> for EventSeed<'_, T> {
+    type Value = T;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for EventSeed<'_, T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a json object or array")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<T, A::Error> {
+        T::deserialize(MapAccessDeserializer::new(map))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<T, A::Error> {
+        zipped(self.columns, &mut seq)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<T, E> {
+        T::deserialize(v.into_deserializer())
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<T, E> {
+        T::deserialize(v.into_deserializer())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+        T::deserialize(v.into_deserializer())
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<T, E> {
+        T::deserialize(v.into_deserializer())
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        T::deserialize(v.to_string().into_deserializer())
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<T, E> {
+        T::deserialize(().into_deserializer())
+    }
+}
+
+/// Deserializes `T` from the remaining values of `seq` named by `columns`, then skips the values
+/// left over.
+fn zipped<'de, T: Deserialize<'de>, A: SeqAccess<'de>>(
+    columns: &[String],
+    seq: &mut A,
+) -> Result<T, A::Error> {
+    let value = T::deserialize(PositionalRow {
+        columns,
+        seq: &mut *seq,
+    })?;
+    while seq.next_element::<IgnoredAny>()?.is_some() {}
+    Ok(value)
+}
+
+/// Values of a positional row, seen as a map by maps and structs and as a sequence otherwise.
+struct PositionalRow<'a, 's, A> {
+    columns: &'a [String],
+    seq: &'s mut A,
+}
+
+impl<'de, A: SeqAccess<'de>> Deserializer<'de> for PositionalRow<'_, '_, A> {
+    type Error = A::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, A::Error> {
+        visitor.visit_seq(self.seq)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, A::Error> {
+        visitor.visit_map(ZippedRow {
+            columns: self.columns.iter(),
+            seq: self.seq,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct enum identifier ignored_any
+    }
+}
+
+struct ZippedRow<'a, 's, A> {
+    columns: std::slice::Iter<'a, String>,
+    seq: &'s mut A,
+}
+
+impl<'de, A: SeqAccess<'de>> MapAccess<'de> for ZippedRow<'_, '_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        match self.columns.next() {
+            Some(column) => seed
+                .deserialize(StrDeserializer::<A::Error>::new(column))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, A::Error> {
+        self.seq
+            .next_element_seed(seed)?
+            .ok_or_else(|| de::Error::custom("positional row shorter than its columns"))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.columns.len())
+    }
+}
+
+/// A json array of events, see [`EventSeed`].
+pub(crate) struct EventsSeed<'a, T>(pub(crate) &'a [String], pub(crate) PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> DeserializeSeed<'de> for EventsSeed<'_, T> {
+    type Value = Vec<T>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Vec<T>, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for EventsSeed<'_, T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a json array of events")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+        let mut rows = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(row) = seq.next_element_seed(EventSeed::new(self.0))? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
+
+/// A groupBy `resultAsArray` row: the timestamp in epoch millis, left out with the `all`
+/// granularity, followed by the dimensions, aggregations and post aggregations.
+pub(crate) struct GroupByRowSeed<'a, T> {
+    columns: &'a [String],
+    /// Timestamp of every row when they come without one.
+    timestamp: Option<&'a str>,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T> GroupByRowSeed<'a, T> {
+    pub(crate) fn new(columns: &'a [String], timestamp: Option<&'a str>) -> Self {
+        GroupByRowSeed {
+            columns,
+            timestamp,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, T: DeserializeOwned> DeserializeSeed<'de> for GroupByRowSeed<'_, T> {
+    type Value = GroupByResponse<T>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T: DeserializeOwned> Visitor<'de> for GroupByRowSeed<'_, T> {
+    type Value = GroupByResponse<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a groupBy result array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let timestamp = match self.timestamp {
+            Some(timestamp) => timestamp.to_string(),
+            None => match seq.next_element::<Value>()? {
+                Some(Value::Number(millis)) => millis
+                    .as_i64()
+                    .and_then(chrono::DateTime::from_timestamp_millis)
+                    .map(|time| time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+                    .ok_or_else(|| de::Error::custom(format!("invalid timestamp {millis}")))?,
+                Some(Value::String(timestamp)) => timestamp,
+                other => {
+                    return Err(de::Error::custom(format!(
+                        "expected a row timestamp, got {other:?}"
+                    )))
+                }
+            },
+        };
+        Ok(GroupByResponse {
+            timestamp,
+            event: zipped(self.columns, &mut seq)?,
+        })
+    }
+}
+
+/// A json array of groupBy `resultAsArray` rows, see [`GroupByRowSeed`].
+pub(crate) struct GroupByRowsSeed<'a, T>(
+    pub(crate) &'a [String],
+    pub(crate) Option<&'a str>,
+    pub(crate) PhantomData<T>,
+);
+
+impl<'de, T: DeserializeOwned> DeserializeSeed<'de> for GroupByRowsSeed<'_, T> {
+    type Value = Vec<GroupByResponse<T>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, T: DeserializeOwned> Visitor<'de> for GroupByRowsSeed<'_, T> {
+    type Value = Vec<GroupByResponse<T>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a json array of groupBy result arrays")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut rows = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(row) = seq.next_element_seed(GroupByRowSeed::new(self.0, self.1))? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::query::response::ScanResponse;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Edit {
+        #[serde(rename = "__time")]
+        time: i64,
+        page: Option<String>,
+    }
+
+    #[test]
+    fn test_compacted_list_into_struct() {
+        let responses: Vec<ScanResponse<Edit>> = serde_json::from_str(
+            r#"[{"segmentId": "s1", "columns": ["__time", "page", "added"], "events": [[1442016000000, "Main", 5], [1442016001000, null, 7]]}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            responses[0].events,
+            vec![
+                Edit {
+                    time: 1442016000000,
+                    page: Some("Main".into())
+                },
+                Edit {
+                    time: 1442016001000,
+                    page: None
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compacted_list_kept_positional() {
+        let json = r#"[{"events": [[1442016000000, "Main"]], "columns": ["__time", "page"], "segmentId": "s1"}]"#;
+        let responses: Vec<ScanResponse<Vec<Value>>> = serde_json::from_str(json).unwrap();
+        assert_eq!(responses[0].events[0][1], "Main");
+        // events sent before the columns still zip
+        let responses: Vec<ScanResponse<Edit>> = serde_json::from_str(json).unwrap();
+        assert_eq!(responses[0].events[0].page.as_deref(), Some("Main"));
+    }
+
+    #[test]
+    fn test_list_into_struct() {
+        let responses: Vec<ScanResponse<Edit>> = serde_json::from_str(
+            r#"[{"segmentId": "s1", "columns": ["__time", "page"], "events": [{"__time": 1442016000000, "page": "Main"}]}]"#,
+        )
+        .unwrap();
+        assert_eq!(responses[0].events[0].time, 1442016000000);
+    }
+
+    #[test]
+    fn test_group_by_arrays_without_timestamp() {
+        #[derive(Deserialize)]
+        struct PageCount {
+            page: String,
+            count: usize,
+        }
+        let columns = vec!["page".to_string(), "count".to_string()];
+        let seed = GroupByRowsSeed(&columns, Some("2015-09-12T00:00:00.000Z"), PhantomData);
+        let rows: Vec<GroupByResponse<PageCount>> = seed
+            .deserialize(&mut serde_json::Deserializer::from_str(
+                r#"[["Main", 3, "ignored"], ["Talk", 1]]"#,
+            ))
+            .unwrap();
+        assert_eq!(rows[0].timestamp, "2015-09-12T00:00:00.000Z");
+        assert_eq!(rows[0].event.count, 3);
+        assert_eq!(rows[1].event.page, "Talk");
+    }
+}
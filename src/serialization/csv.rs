@@ -0,0 +1,230 @@
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::forward_to_deserialize_any;
+
+type Error = serde_json::Error;
+
+/// Splits RFC 4180 formatted text into records. Quoted fields may contain separators,
+/// doubled quotes and line breaks. A trailing empty line is returned as a record with a
+/// single empty field so callers can tell a complete response from a truncated one.
+pub(crate) fn parse_records(body: &str) -> Vec<Vec<String>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Deserializes a single csv record either positionally or, when column names are known,
+/// by name. Fields are parsed on demand so numeric and boolean targets work as well as strings.
+pub(crate) struct RecordDeserializer<'a> {
+    fields: &'a [String],
+    names: Option<&'a [String]>,
+}
+
+impl<'a> RecordDeserializer<'a> {
+    pub(crate) fn new(fields: &'a [String], names: Option<&'a [String]>) -> Self {
+        RecordDeserializer { fields, names }
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for RecordDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.names {
+            Some(names) => visitor.visit_map(RecordMap {
+                names: names.iter(),
+                fields: self.fields.iter(),
+                value: None,
+            }),
+            None => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(RecordSeq {
+            fields: self.fields.iter(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+struct RecordSeq<'a> {
+    fields: std::slice::Iter<'a, String>,
+}
+
+impl<'de, 'a> SeqAccess<'de> for RecordSeq<'a> {
+    type Error = Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Error> {
+        self.fields
+            .next()
+            .map(|field| seed.deserialize(FieldDeserializer(field)))
+            .transpose()
+    }
+}
+
+struct RecordMap<'a> {
+    names: std::slice::Iter<'a, String>,
+    fields: std::slice::Iter<'a, String>,
+    value: Option<&'a String>,
+}
+
+impl<'de, 'a> MapAccess<'de> for RecordMap<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match (self.names.next(), self.fields.next()) {
+            (Some(name), Some(field)) => {
+                self.value = Some(field);
+                seed.deserialize(name.as_str().into_deserializer())
+                    .map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(field) => seed.deserialize(FieldDeserializer(field)),
+            None => Err(de::Error::custom(
+                "csv value requested before its column name",
+            )),
+        }
+    }
+}
+
+struct FieldDeserializer<'a>(&'a str);
+
+macro_rules! parse_field {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.0.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(self.0), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for FieldDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_str(self.0)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.0.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    parse_field! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_records() {
+        let records = parse_records("a,\"b,\"\"c\"\"\nd\",\r\n1,2,3\n\n");
+        assert_eq!(
+            records,
+            vec![
+                vec!["a".to_string(), "b,\"c\"\nd".into(), "".into()],
+                vec!["1".to_string(), "2".into(), "3".into()],
+                vec!["".to_string()],
+            ]
+        );
+    }
+}
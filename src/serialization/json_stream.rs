@@ -0,0 +1,103 @@
+use serde::de::Error;
+
+/// Cuts a top level json array into its elements while the array is still arriving in chunks.
+/// Only the bytes of the element currently being read are buffered.
+#[derive(Default)]
+pub(crate) struct JsonArraySplitter {
+    buffer: Vec<u8>,
+    started: bool,
+    finished: bool,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl JsonArraySplitter {
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Result<Vec<Vec<u8>>, serde_json::Error> {
+        let mut elements = vec![];
+        for &byte in chunk {
+            if !self.started {
+                match byte {
+                    b'[' => self.started = true,
+                    b if b.is_ascii_whitespace() => {}
+                    _ => return Err(serde_json::Error::custom("expected a json array")),
+                }
+                continue;
+            }
+            if self.finished {
+                continue;
+            }
+            if self.depth == 0 {
+                // between elements: separators, whitespace or the end of the array
+                match byte {
+                    b']' => self.finished = true,
+                    b',' => {}
+                    b if b.is_ascii_whitespace() => {}
+                    b'{' | b'[' => {
+                        self.depth = 1;
+                        self.buffer.push(byte);
+                    }
+                    _ => return Err(serde_json::Error::custom("expected an array of objects")),
+                }
+                continue;
+            }
+
+            self.buffer.push(byte);
+            if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => self.depth += 1,
+                b'}' | b']' => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        elements.push(std::mem::take(&mut self.buffer));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(elements)
+    }
+
+    /// Whether the closing bracket of the array was seen.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_chunks() {
+        let body = br#" [{"a":"}]\"","b":[1,{"c":2}]}, {"a":"x"} ]"#;
+        let mut splitter = JsonArraySplitter::default();
+        let mut elements = vec![];
+        for chunk in body.chunks(3) {
+            elements.extend(splitter.feed(chunk).unwrap());
+        }
+        assert!(splitter.is_finished());
+        assert_eq!(
+            elements,
+            vec![
+                br#"{"a":"}]\"","b":[1,{"c":2}]}"#.to_vec(),
+                br#"{"a":"x"}"#.to_vec()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_not_an_array() {
+        let mut splitter = JsonArraySplitter::default();
+        assert!(splitter.feed(br#"{"error":"Unknown exception"}"#).is_err());
+    }
+}
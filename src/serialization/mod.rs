@@ -3,6 +3,16 @@ use serde::de::{self, MapAccess, Visitor};
 use serde::{Deserialize, Deserializer};
 use std::fmt;
 use std::marker::PhantomData;
+use std::str::FromStr;
+
+pub(crate) mod compact;
+pub(crate) mod csv;
+pub(crate) mod json_stream;
+#[cfg(feature = "smile")]
+pub mod smile;
+#[cfg(any(feature = "arrow", feature = "polars"))]
+pub(crate) mod table;
+pub(crate) mod values;
 
 pub(crate) fn default_for_null<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
@@ -12,27 +22,67 @@ where
     Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) struct ParseError {
-    _priv: (),
+/// Deserializes an optional value that may also arrive as a string (`"true"`, `"1000"`) or,
+/// for string-like types, as a json number or boolean.
+pub(crate) fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: fmt::Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Lenient<T> {
+        Value(T),
+        Str(String),
+        Bool(bool),
+        Number(serde_json::Number),
+    }
+
+    let text = match Option::<Lenient<T>>::deserialize(deserializer)? {
+        None => return Ok(None),
+        Some(Lenient::Value(value)) => return Ok(Some(value)),
+        Some(Lenient::Str(str)) => str,
+        Some(Lenient::Bool(boolean)) => boolean.to_string(),
+        Some(Lenient::Number(number)) => number.to_string(),
+    };
+    text.parse().map(Some).map_err(de::Error::custom)
 }
 
-impl fmt::Display for ParseError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        "provided string was not `true` or `false`".fmt(f)
+/// (De)serializes a list of names sent as one comma separated string, e.g. the dimensions of
+/// a segment.
+pub(crate) mod comma_separated {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        names: &[String],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&names.join(","))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        let names = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+        Ok(names
+            .split(',')
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect())
     }
 }
 
 pub(crate) fn tagged_or_untagged<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
     D: Deserializer<'de>,
-    T: Deserialize<'de> + Default, /* + FromStr<Err = ParseError> */
+    T: Deserialize<'de> + Default,
 {
     struct StringOrStruct<T>(PhantomData<fn() -> T>);
 
     impl<'de, T> Visitor<'de> for StringOrStruct<T>
     where
-        T: Deserialize<'de> + Default, /* + FromStr<Err = ParseError> */
+        T: Deserialize<'de> + Default,
     {
         type Value = T;
 
@@ -91,17 +141,13 @@ mod test {
         assert!(test_struct.unwrap().field.is_empty())
     }
 
-    #[derive(Eq, PartialEq, Deserialize, Serialize, Debug)]
+    #[derive(Eq, PartialEq, Deserialize, Serialize, Debug, Default)]
     #[serde(rename_all = "camelCase")]
     enum Tagged {
+        #[default]
         One,
         Two,
     }
-    impl Default for Tagged {
-        fn default() -> Self {
-            Tagged::One
-        }
-    }
     #[derive(Deserialize, Serialize, Debug)]
     struct Wrap {
         #[serde(deserialize_with = "tagged_or_untagged")]
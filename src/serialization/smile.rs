@@ -0,0 +1,794 @@
+//! [Smile](https://github.com/FasterXML/smile-format-specification), the binary json format of
+//! Jackson that druid brokers speak besides json (feature `smile`).
+//!
+//! Documents are decoded straight into serde types, binary values as the base64 strings druid
+//! would have sent in json. Encoding isn't streamed: values are converted with
+//! [`serde_json::to_value`] first, then written without shared names or shared values.
+
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+    Visitor,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Display;
+use thiserror::Error;
+
+pub const CONTENT_TYPE: &str = "application/x-jackson-smile";
+
+const HEADER: &[u8] = b":)\n";
+const SHARED_NAMES: u8 = 0x01;
+const SHARED_VALUES: u8 = 0x02;
+/// Shared name and value tables are cleared once they hold that many strings.
+const MAX_SHARED: usize = 1024;
+
+const EMPTY_STRING: u8 = 0x20;
+const NULL: u8 = 0x21;
+const FALSE: u8 = 0x22;
+const TRUE: u8 = 0x23;
+const INT_32: u8 = 0x24;
+const INT_64: u8 = 0x25;
+const BIG_INTEGER: u8 = 0x26;
+const FLOAT_32: u8 = 0x28;
+const FLOAT_64: u8 = 0x29;
+const BIG_DECIMAL: u8 = 0x2A;
+const LONG_ASCII: u8 = 0xE0;
+const LONG_UNICODE: u8 = 0xE4;
+const BINARY_7BIT: u8 = 0xE8;
+const START_ARRAY: u8 = 0xF8;
+const END_ARRAY: u8 = 0xF9;
+const START_OBJECT: u8 = 0xFA;
+const END_OBJECT: u8 = 0xFB;
+const END_STRING: u8 = 0xFC;
+const BINARY_RAW: u8 = 0xFD;
+const END_CONTENT: u8 = 0xFF;
+const LONG_KEY: u8 = 0x34;
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("invalid smile data at byte {offset}: {message}")]
+    Syntax { offset: usize, message: String },
+    #[error("{0}")]
+    Message(String),
+    #[error("couldn't convert to a json value")]
+    Json { source: serde_json::Error },
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Deserializes a Smile document.
+pub fn from_slice<'de, T: Deserialize<'de>>(input: &'de [u8]) -> Result<T> {
+    from_slice_seed(input, std::marker::PhantomData)
+}
+
+pub(crate) fn from_slice_seed<'de, S: DeserializeSeed<'de>>(
+    input: &'de [u8],
+    seed: S,
+) -> Result<S::Value> {
+    let mut deserializer = Deserializer::new(input)?;
+    let value = seed.deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Serializes `value` to a Smile document with a header, through a [`serde_json::Value`].
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let value = serde_json::to_value(value).map_err(|source| Error::Json { source })?;
+    let mut out = HEADER.to_vec();
+    out.push(0x00);
+    write_value(&mut out, &value);
+    Ok(out)
+}
+
+/// Whether the document is an object, e.g. the error body druid answers instead of results.
+pub(crate) fn is_object(input: &[u8]) -> bool {
+    input.starts_with(HEADER) && input.get(HEADER.len() + 1) == Some(&START_OBJECT)
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => out.push(NULL),
+        Value::Bool(false) => out.push(FALSE),
+        Value::Bool(true) => out.push(TRUE),
+        Value::Number(number) => {
+            if let Some(integer) = number.as_i64() {
+                write_integer(out, integer);
+            } else if let Some(integer) = number.as_u64() {
+                // beyond i64, a BigInteger of the two's complement bytes
+                let mut bytes = vec![0];
+                bytes.extend(integer.to_be_bytes());
+                out.push(BIG_INTEGER);
+                write_7bit_binary(out, &bytes);
+            } else {
+                let mut bits = number.as_f64().unwrap_or(f64::NAN).to_bits();
+                let mut bytes = [0u8; 10];
+                for byte in bytes.iter_mut().rev() {
+                    *byte = (bits & 0x7F) as u8;
+                    bits >>= 7;
+                }
+                out.push(FLOAT_64);
+                out.extend(bytes);
+            }
+        }
+        Value::String(text) => write_string(out, text),
+        Value::Array(values) => {
+            out.push(START_ARRAY);
+            for value in values {
+                write_value(out, value);
+            }
+            out.push(END_ARRAY);
+        }
+        Value::Object(fields) => {
+            out.push(START_OBJECT);
+            for (key, value) in fields {
+                write_key(out, key);
+                write_value(out, value);
+            }
+            out.push(END_OBJECT);
+        }
+    }
+}
+
+fn write_integer(out: &mut Vec<u8>, integer: i64) {
+    match i32::try_from(integer) {
+        Ok(small @ -16..=15) => out.push(0xC0 + ((small << 1) ^ (small >> 31)) as u8),
+        Ok(int) => {
+            out.push(INT_32);
+            write_vint(out, ((int << 1) ^ (int >> 31)) as u32 as u64);
+        }
+        Err(_) => {
+            out.push(INT_64);
+            write_vint(out, ((integer << 1) ^ (integer >> 63)) as u64);
+        }
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, text: &str) {
+    let bytes = text.as_bytes();
+    let len = bytes.len();
+    match (text.is_ascii(), len) {
+        (_, 0) => out.push(EMPTY_STRING),
+        (true, 1..=32) => out.push(0x40 + (len - 1) as u8),
+        (true, 33..=64) => out.push(0x60 + (len - 33) as u8),
+        (false, 2..=33) => out.push(0x80 + (len - 2) as u8),
+        (false, 34..=65) => out.push(0xA0 + (len - 34) as u8),
+        (ascii, _) => {
+            out.push(if ascii { LONG_ASCII } else { LONG_UNICODE });
+            out.extend(bytes);
+            out.push(END_STRING);
+            return;
+        }
+    }
+    out.extend(bytes);
+}
+
+fn write_key(out: &mut Vec<u8>, key: &str) {
+    let bytes = key.as_bytes();
+    let len = bytes.len();
+    match (key.is_ascii(), len) {
+        (_, 0) => out.push(EMPTY_STRING),
+        (true, 1..=64) => out.push(0x80 + (len - 1) as u8),
+        (false, 2..=57) => out.push(0xC0 + (len - 2) as u8),
+        _ => {
+            out.push(LONG_KEY);
+            out.extend(bytes);
+            out.push(END_STRING);
+            return;
+        }
+    }
+    out.extend(bytes);
+}
+
+/// Unsigned variable length integer: 7 bits per byte, the last byte flagged by its high bit and
+/// holding 6 bits.
+fn write_vint(out: &mut Vec<u8>, mut value: u64) {
+    let mut bytes = [0u8; 11];
+    let mut start = bytes.len() - 1;
+    bytes[start] = 0x80 | (value & 0x3F) as u8;
+    value >>= 6;
+    while value > 0 {
+        start -= 1;
+        bytes[start] = (value & 0x7F) as u8;
+        value >>= 7;
+    }
+    out.extend(&bytes[start..]);
+}
+
+/// Length followed by the bytes spread over 7 bits per byte, the last byte holding the bits
+/// left right-aligned.
+fn write_7bit_binary(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_vint(out, bytes.len() as u64);
+    let mut bits: u32 = 0;
+    let mut count = 0;
+    for &byte in bytes {
+        bits = (bits << 8) | byte as u32;
+        count += 8;
+        while count >= 7 {
+            count -= 7;
+            out.push(((bits >> count) & 0x7F) as u8);
+        }
+    }
+    if count > 0 {
+        out.push((bits & ((1 << count) - 1)) as u8);
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Two's complement big endian integer, as an `i64` or `u64` when it fits.
+enum BigInteger {
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+}
+
+impl BigInteger {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let negative = bytes.first().is_some_and(|byte| byte & 0x80 != 0);
+        if bytes.len() <= 16 {
+            let fill = if negative { u128::MAX } else { 0 };
+            let value = bytes
+                .iter()
+                .fold(fill, |value, &byte| (value << 8) | byte as u128)
+                as i128;
+            return match (i64::try_from(value), u64::try_from(value)) {
+                (Ok(value), _) => BigInteger::Signed(value),
+                (_, Ok(value)) => BigInteger::Unsigned(value),
+                _ => BigInteger::Float(value as f64),
+            };
+        }
+        let magnitude = bytes
+            .iter()
+            .fold(0f64, |value, &byte| value * 256.0 + byte as f64);
+        if negative {
+            BigInteger::Float(magnitude - 2f64.powi(8 * bytes.len() as i32))
+        } else {
+            BigInteger::Float(magnitude)
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        match *self {
+            BigInteger::Signed(value) => value as f64,
+            BigInteger::Unsigned(value) => value as f64,
+            BigInteger::Float(value) => value,
+        }
+    }
+}
+
+struct Deserializer<'de> {
+    input: &'de [u8],
+    offset: usize,
+    shared_names: Option<Vec<&'de str>>,
+    shared_values: Option<Vec<&'de str>>,
+}
+
+impl<'de> Deserializer<'de> {
+    fn new(input: &'de [u8]) -> Result<Self> {
+        let mut deserializer = Deserializer {
+            input,
+            offset: 0,
+            shared_names: None,
+            shared_values: None,
+        };
+        if !input.starts_with(HEADER) {
+            return Err(deserializer.error("missing smile header"));
+        }
+        deserializer.offset = HEADER.len();
+        let flags = deserializer.next()?;
+        if flags >> 4 != 0 {
+            return Err(deserializer.error(format!("unsupported smile version {}", flags >> 4)));
+        }
+        if flags & SHARED_NAMES != 0 {
+            deserializer.shared_names = Some(vec![]);
+        }
+        if flags & SHARED_VALUES != 0 {
+            deserializer.shared_values = Some(vec![]);
+        }
+        Ok(deserializer)
+    }
+
+    fn error<M: Display>(&self, message: M) -> Error {
+        Error::Syntax {
+            offset: self.offset,
+            message: message.to_string(),
+        }
+    }
+
+    fn end(&mut self) -> Result<()> {
+        if self.peek() == Some(END_CONTENT) {
+            self.offset += 1;
+        }
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error("trailing data after the document")),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.offset).copied()
+    }
+
+    fn next(&mut self) -> Result<u8> {
+        let byte = self
+            .peek()
+            .ok_or_else(|| self.error("unexpected end of data"))?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'de [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.input.len())
+            .ok_or_else(|| self.error("unexpected end of data"))?;
+        let bytes = &self.input[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn str(&mut self, len: usize) -> Result<&'de str> {
+        let bytes = self.take(len)?;
+        std::str::from_utf8(bytes).map_err(|err| self.error(err))
+    }
+
+    /// String terminated by the end-of-string marker.
+    fn terminated_str(&mut self) -> Result<&'de str> {
+        let len = self.input[self.offset..]
+            .iter()
+            .position(|byte| *byte == END_STRING)
+            .ok_or_else(|| self.error("unterminated string"))?;
+        let text = self.str(len)?;
+        self.offset += 1;
+        Ok(text)
+    }
+
+    fn vint(&mut self) -> Result<u64> {
+        let mut value: u64 = 0;
+        for _ in 0..11 {
+            let byte = self.next()?;
+            if byte & 0x80 != 0 {
+                return Ok((value << 6) | (byte & 0x3F) as u64);
+            }
+            value = (value << 7) | byte as u64;
+        }
+        Err(self.error("variable length integer too long"))
+    }
+
+    fn zigzag_32(&mut self) -> Result<i32> {
+        let value = self.vint()? as u32;
+        Ok(((value >> 1) as i32) ^ -((value & 1) as i32))
+    }
+
+    fn zigzag_64(&mut self) -> Result<i64> {
+        let value = self.vint()?;
+        Ok(((value >> 1) as i64) ^ -((value & 1) as i64))
+    }
+
+    /// Big endian bits spread over 7 bits per byte.
+    fn bits_7(&mut self, len: usize) -> Result<u64> {
+        let mut bits: u64 = 0;
+        for _ in 0..len {
+            bits = (bits << 7) | (self.next()? & 0x7F) as u64;
+        }
+        Ok(bits)
+    }
+
+    fn binary_7bit(&mut self) -> Result<Vec<u8>> {
+        let len = self.vint()? as usize;
+        let mut bytes = Vec::with_capacity(len.min(self.input.len()));
+        while bytes.len() < len {
+            let chunk = (len - bytes.len()).min(7);
+            let mut bits = self.bits_7(chunk)? as u128;
+            if chunk == 7 {
+                bits = (bits << 7) | self.next()? as u128;
+            } else {
+                // the last byte holds the remaining bits right-aligned
+                bits = (bits << chunk) | (self.next()? as u128 & ((1 << chunk) - 1));
+            }
+            for i in (0..chunk).rev() {
+                bytes.push((bits >> (8 * i)) as u8);
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn add_shared(table: &mut Option<Vec<&'de str>>, text: &'de str) {
+        if let Some(table) = table {
+            if table.len() == MAX_SHARED {
+                table.clear();
+            }
+            table.push(text);
+        }
+    }
+
+    fn shared(&self, table: &Option<Vec<&'de str>>, index: usize) -> Result<&'de str> {
+        table
+            .as_ref()
+            .and_then(|table| table.get(index).copied())
+            .ok_or_else(|| self.error(format!("invalid shared string reference {index}")))
+    }
+
+    fn short_value(&mut self, len: usize) -> Result<&'de str> {
+        let text = self.str(len)?;
+        Self::add_shared(&mut self.shared_values, text);
+        Ok(text)
+    }
+
+    /// Reads a key, `None` at the end of the object.
+    fn key(&mut self) -> Result<Option<&'de str>> {
+        let token = self.next()?;
+        let key = match token {
+            END_OBJECT => return Ok(None),
+            EMPTY_STRING => "",
+            0x30..=0x33 => {
+                let index = ((token as usize & 0x03) << 8) | self.next()? as usize;
+                self.shared(&self.shared_names, index)?
+            }
+            LONG_KEY => {
+                let key = self.terminated_str()?;
+                Self::add_shared(&mut self.shared_names, key);
+                key
+            }
+            0x40..=0x7F => self.shared(&self.shared_names, token as usize & 0x3F)?,
+            // short ascii names of 1 to 64 bytes, then short unicode ones of 2 to 57 bytes
+            0x80..=0xF7 => {
+                let len = (token as usize & 0x3F) + if token < 0xC0 { 1 } else { 2 };
+                let key = self.str(len)?;
+                Self::add_shared(&mut self.shared_names, key);
+                key
+            }
+            _ => return Err(self.error(format!("invalid key token {token:#04x}"))),
+        };
+        Ok(Some(key))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let token = self.next()?;
+        match token {
+            0x01..=0x1F => {
+                visitor.visit_borrowed_str(self.shared(&self.shared_values, token as usize - 1)?)
+            }
+            EMPTY_STRING => visitor.visit_borrowed_str(""),
+            NULL => visitor.visit_unit(),
+            FALSE => visitor.visit_bool(false),
+            TRUE => visitor.visit_bool(true),
+            INT_32 => visitor.visit_i64(self.zigzag_32()? as i64),
+            INT_64 => visitor.visit_i64(self.zigzag_64()?),
+            BIG_INTEGER => match BigInteger::from_bytes(&self.binary_7bit()?) {
+                BigInteger::Signed(value) => visitor.visit_i64(value),
+                BigInteger::Unsigned(value) => visitor.visit_u64(value),
+                BigInteger::Float(value) => visitor.visit_f64(value),
+            },
+            FLOAT_32 => {
+                let float = f32::from_bits(self.bits_7(5)? as u32);
+                // the shortest decimal of the float, as druid would have written it in json
+                visitor.visit_f64(float.to_string().parse().unwrap_or(float as f64))
+            }
+            FLOAT_64 => visitor.visit_f64(f64::from_bits(self.bits_7(10)?)),
+            BIG_DECIMAL => {
+                let scale = self.zigzag_32()?;
+                let unscaled = BigInteger::from_bytes(&self.binary_7bit()?).to_f64();
+                visitor.visit_f64(unscaled / 10f64.powi(scale))
+            }
+            0x40..=0x5F => {
+                visitor.visit_borrowed_str(self.short_value((token as usize & 0x1F) + 1)?)
+            }
+            0x60..=0x7F => {
+                visitor.visit_borrowed_str(self.short_value((token as usize & 0x1F) + 33)?)
+            }
+            0x80..=0x9F => {
+                visitor.visit_borrowed_str(self.short_value((token as usize & 0x1F) + 2)?)
+            }
+            0xA0..=0xBF => {
+                visitor.visit_borrowed_str(self.short_value((token as usize & 0x1F) + 34)?)
+            }
+            0xC0..=0xDF => {
+                let value = (token & 0x1F) as i64;
+                visitor.visit_i64((value >> 1) ^ -(value & 1))
+            }
+            LONG_ASCII | LONG_UNICODE => visitor.visit_borrowed_str(self.terminated_str()?),
+            // binary values are sent in json as base64 strings
+            BINARY_7BIT => visitor.visit_string(base64(&self.binary_7bit()?)),
+            BINARY_RAW => {
+                let len = self.vint()? as usize;
+                visitor.visit_string(base64(self.take(len)?))
+            }
+            0xEC..=0xEF => {
+                let index = ((token as usize & 0x03) << 8) | self.next()? as usize;
+                visitor.visit_borrowed_str(self.shared(&self.shared_values, index)?)
+            }
+            START_ARRAY => {
+                let value = visitor.visit_seq(Elements { de: &mut *self })?;
+                match self.next()? {
+                    END_ARRAY => Ok(value),
+                    _ => Err(self.error("expected the end of the array")),
+                }
+            }
+            START_OBJECT => {
+                let value = visitor.visit_map(Fields { de: &mut *self })?;
+                match self.next()? {
+                    END_OBJECT => Ok(value),
+                    _ => Err(self.error("expected the end of the object")),
+                }
+            }
+            _ => Err(self.error(format!("invalid value token {token:#04x}"))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.peek() == Some(NULL) {
+            self.offset += 1;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        if self.peek() != Some(START_OBJECT) {
+            // unit variants are plain strings
+            let variant: &'de str = Deserialize::deserialize(&mut *self)?;
+            return visitor.visit_enum(variant.into_deserializer());
+        }
+        self.offset += 1;
+        let value = visitor.visit_enum(Variant { de: &mut *self })?;
+        match self.next()? {
+            END_OBJECT => Ok(value),
+            _ => Err(self.error("expected the end of the enum object")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct Elements<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.de.peek() == Some(END_ARRAY) {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+struct Fields<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> MapAccess<'de> for Fields<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.de.key()? {
+            Some(key) => seed
+                .deserialize(BorrowedStrDeserializer::new(key))
+                .map(Some),
+            None => {
+                // the end marker is checked by the caller
+                self.de.offset -= 1;
+                Ok(None)
+            }
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+struct Variant<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> EnumAccess<'de> for Variant<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let key = self
+            .de
+            .key()?
+            .ok_or_else(|| self.de.error("expected an enum variant name"))?;
+        let variant = seed.deserialize(BorrowedStrDeserializer::new(key))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Variant<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::IgnoredAny::deserialize(&mut *self.de).map(|_| ())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(&mut *self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(&mut *self.de, visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_round_trip() {
+        let value = json!({
+            "queryType": "scan",
+            "": "",
+            "ascii": "a".repeat(40),
+            "long": "b".repeat(100),
+            "unicode": "wiki\u{e9}dia",
+            "longUnicode": "\u{e9}".repeat(50),
+            "numbers": [0, -16, 15, 16, -17, 70000, -70000, 1i64 << 40, i64::MIN, u64::MAX, 1.5, -0.25],
+            "nested": {"a": [true, false, null], "b": {}},
+            "k\u{e9}y".repeat(30): 1
+        });
+        let encoded = to_vec(&value).unwrap();
+        assert!(encoded.starts_with(b":)\n\x00"));
+        assert!(is_object(&encoded));
+        assert_eq!(from_slice::<Value>(&encoded).unwrap(), value);
+    }
+
+    #[test]
+    fn test_jackson_tokens() {
+        // {"a":"x","b":"x","a":1.5f} with shared names and values, "x" written once
+        let encoded = [
+            b':',
+            b')',
+            b'\n',
+            0x03,
+            START_OBJECT,
+            0x80,
+            b'a',
+            0x40,
+            b'x',
+            0x80,
+            b'b',
+            0x01,
+            0x40,
+            FLOAT_32,
+            0x03,
+            0x7E,
+            0x00,
+            0x00,
+            0x00,
+            END_OBJECT,
+            END_CONTENT,
+        ];
+        let fields: Vec<(String, Value)> = from_slice::<Value>(&encoded)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![("a".to_string(), json!(1.5)), ("b".to_string(), json!("x"))]
+        );
+    }
+
+    #[test]
+    fn test_binary_and_big_numbers() {
+        let mut encoded = b":)\n\x04".to_vec();
+        encoded.push(START_ARRAY);
+        encoded.push(BINARY_7BIT);
+        write_7bit_binary(&mut encoded, b"druid sketch");
+        encoded.extend([BINARY_RAW, 0x83, b'a', b'b', b'c']);
+        encoded.push(BIG_INTEGER);
+        write_7bit_binary(
+            &mut encoded,
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE],
+        );
+        encoded.extend([BIG_DECIMAL, 0x84]);
+        write_7bit_binary(&mut encoded, &[0x04, 0xD2]);
+        encoded.push(END_ARRAY);
+        assert_eq!(
+            from_slice::<Value>(&encoded).unwrap(),
+            json!([base64(b"druid sketch"), "YWJj", -2, 12.34])
+        );
+    }
+
+    #[test]
+    fn test_enum_and_options() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        enum State {
+            Running,
+            Failed { code: String },
+        }
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Status {
+            state: State,
+            error: Option<State>,
+            count: Option<u8>,
+        }
+        let encoded = to_vec(&json!({
+            "state": "Running",
+            "error": {"Failed": {"code": "oops"}},
+            "count": null
+        }))
+        .unwrap();
+        assert_eq!(
+            from_slice::<Status>(&encoded).unwrap(),
+            Status {
+                state: State::Running,
+                error: Some(State::Failed {
+                    code: "oops".into()
+                }),
+                count: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        assert!(from_slice::<Value>(b"{}").is_err());
+        assert!(from_slice::<Value>(b":)\n\x00\xFA\x80a").is_err());
+        assert!(from_slice::<Value>(b":)\n\x00\x21\x21").is_err());
+    }
+}
@@ -0,0 +1,327 @@
+//! Query results flattened to rows of named columns, the common ground of the columnar
+//! (arrow, polars) conversions.
+
+use crate::query::response::{
+    DruidListResponse, GroupByResponse, MetadataResponse, ScanResponse, SegmentMetadataResponse,
+    TimeBoundaryResponse, TimeseriesResponse,
+};
+use crate::query::row::Row;
+use serde::de::{DeserializeOwned, Error as _};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Name of the column holding the timestamp of aggregated results.
+pub(crate) const TIMESTAMP: &str = "timestamp";
+/// Column druid stores row timestamps in, as returned by scan queries.
+pub(crate) const TIME_COLUMN: &str = "__time";
+
+static NULL: Value = Value::Null;
+
+/// Type of a column guessed from its values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColumnKind {
+    Timestamp,
+    Boolean,
+    Long,
+    Double,
+    StringList,
+    String,
+}
+
+/// Results as columns of json values, in order of first appearance.
+#[derive(Debug, Default)]
+pub(crate) struct JsonTable {
+    pub(crate) columns: Vec<String>,
+    /// Values of each column, as long as `num_rows` once a row is complete.
+    values: Vec<Vec<Value>>,
+    pub(crate) num_rows: usize,
+}
+
+impl JsonTable {
+    /// Values of a column, null for the rows missing it.
+    pub(crate) fn values<'a>(&'a self, column: &str) -> impl Iterator<Item = &'a Value> {
+        let values = match self.columns.iter().position(|name| name == column) {
+            Some(index) => self.values[index].as_slice(),
+            None => &[],
+        };
+        values
+            .iter()
+            .chain(std::iter::repeat_n(&NULL, self.num_rows - values.len()))
+    }
+
+    /// Json integers are longs, other numbers doubles, lists multi-value strings, and texts
+    /// that all parse as ISO 8601 timestamps (like the timestamps of aggregated results) are
+    /// timestamps, as well as druid's `__time` column. Anything else is text.
+    pub(crate) fn infer_kind(&self, column: &str) -> ColumnKind {
+        let mut inferred: Option<ColumnKind> = None;
+        for value in self.values(column) {
+            let kind = match value {
+                Value::Null => continue,
+                Value::Bool(_) => ColumnKind::Boolean,
+                Value::Number(_) if column == TIME_COLUMN => ColumnKind::Timestamp,
+                Value::Number(number) if number.is_i64() || number.is_u64() => ColumnKind::Long,
+                Value::Number(_) => ColumnKind::Double,
+                Value::Array(_) => ColumnKind::StringList,
+                Value::String(text) if chrono::DateTime::parse_from_rfc3339(text).is_ok() => {
+                    ColumnKind::Timestamp
+                }
+                Value::String(_) | Value::Object(_) => ColumnKind::String,
+            };
+            inferred = match (inferred, kind) {
+                (None, kind) => Some(kind),
+                (Some(previous), kind) if previous == kind => Some(kind),
+                (Some(ColumnKind::Long), ColumnKind::Double)
+                | (Some(ColumnKind::Double), ColumnKind::Long) => Some(ColumnKind::Double),
+                // druid sends single-valued rows of multi-value columns as plain strings
+                (Some(ColumnKind::StringList), ColumnKind::String | ColumnKind::Timestamp)
+                | (Some(ColumnKind::String | ColumnKind::Timestamp), ColumnKind::StringList) => {
+                    Some(ColumnKind::StringList)
+                }
+                // mixed types are kept as text
+                _ => return ColumnKind::String,
+            };
+        }
+        inferred.unwrap_or(ColumnKind::String)
+    }
+
+    fn column_index(&mut self, name: &str) -> usize {
+        match self.columns.iter().position(|column| column == name) {
+            Some(index) => index,
+            None => {
+                self.columns.push(name.to_string());
+                self.values.push(vec![Value::Null; self.num_rows]);
+                self.columns.len() - 1
+            }
+        }
+    }
+
+    /// Sets a value of the row being added, the last value set for a column wins.
+    fn set(&mut self, column: &str, value: Value) {
+        let index = self.column_index(column);
+        let values = &mut self.values[index];
+        if values.len() > self.num_rows {
+            values[self.num_rows] = value;
+        } else {
+            values.push(value);
+        }
+    }
+
+    /// Completes the row being added, with nulls for the columns it didn't set.
+    fn end_row(&mut self) {
+        self.num_rows += 1;
+        for values in &mut self.values {
+            values.resize(self.num_rows, Value::Null);
+        }
+    }
+
+    fn push_row(&mut self, row: Map<String, Value>) {
+        for (name, value) in row {
+            self.set(&name, value);
+        }
+        self.end_row();
+    }
+
+    /// Adds a row of an aggregated result: the timestamp followed by the fields of `values`.
+    fn push_timestamped<T: Serialize>(
+        &mut self,
+        timestamp: Option<&str>,
+        values: &T,
+    ) -> Result<(), serde_json::Error> {
+        self.set(
+            TIMESTAMP,
+            timestamp.map_or(Value::Null, |timestamp| timestamp.into()),
+        );
+        for (name, value) in object(serde_json::to_value(values)?)? {
+            self.set(&name, value);
+        }
+        self.end_row();
+        Ok(())
+    }
+}
+
+fn object(value: Value) -> Result<Map<String, Value>, serde_json::Error> {
+    match value {
+        Value::Object(fields) => Ok(fields),
+        other => Err(serde_json::Error::custom(format!(
+            "expected rows serialized as json objects, got {other}"
+        ))),
+    }
+}
+
+/// Flattening of query responses, one row per event.
+pub(crate) trait ToJsonTable {
+    fn to_json_table(&self) -> Result<JsonTable, serde_json::Error>;
+}
+
+impl<T: Serialize + DeserializeOwned> ToJsonTable for [ScanResponse<T>] {
+    fn to_json_table(&self) -> Result<JsonTable, serde_json::Error> {
+        let mut table = JsonTable::default();
+        for response in self {
+            let indexes: Vec<usize> = response
+                .columns
+                .iter()
+                .map(|column| table.column_index(column))
+                .collect();
+            for event in &response.events {
+                match serde_json::to_value(event)? {
+                    // compactedList events are positional, their values go straight to the
+                    // columns of the header
+                    Value::Array(values) => {
+                        for (&index, value) in indexes.iter().zip(values) {
+                            table.values[index].push(value);
+                        }
+                        table.end_row();
+                    }
+                    event => table.push_row(object(event)?),
+                }
+            }
+        }
+        Ok(table)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> ToJsonTable for [GroupByResponse<T>] {
+    fn to_json_table(&self) -> Result<JsonTable, serde_json::Error> {
+        let mut table = JsonTable::default();
+        for response in self {
+            table.push_timestamped(Some(&response.timestamp), &response.event)?;
+        }
+        Ok(table)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> ToJsonTable for [TimeseriesResponse<T>] {
+    fn to_json_table(&self) -> Result<JsonTable, serde_json::Error> {
+        let mut table = JsonTable::default();
+        for response in self {
+            table.push_timestamped(response.timestamp.as_deref(), &response.result)?;
+        }
+        Ok(table)
+    }
+}
+
+/// TopN results, one row per entry of each `result` list.
+impl<T: Serialize + DeserializeOwned> ToJsonTable for [DruidListResponse<T>] {
+    fn to_json_table(&self) -> Result<JsonTable, serde_json::Error> {
+        let mut table = JsonTable::default();
+        for response in self {
+            for result in &response.result {
+                table.push_timestamped(Some(&response.timestamp), result)?;
+            }
+        }
+        Ok(table)
+    }
+}
+
+impl ToJsonTable for [TimeBoundaryResponse] {
+    fn to_json_table(&self) -> Result<JsonTable, serde_json::Error> {
+        let mut table = JsonTable::default();
+        for response in self {
+            table.push_timestamped(Some(&response.timestamp), &response.result)?;
+        }
+        Ok(table)
+    }
+}
+
+/// DataSourceMetadata results, the timestamp followed by the fields of `result`.
+impl<T: Serialize + DeserializeOwned> ToJsonTable for [MetadataResponse<T>] {
+    fn to_json_table(&self) -> Result<JsonTable, serde_json::Error> {
+        let mut table = JsonTable::default();
+        for response in self {
+            table.push_timestamped(Some(&response.timestamp), &response.result)?;
+        }
+        Ok(table)
+    }
+}
+
+/// One row per analyzed segment, nested fields (`columns`, `aggregators`...) as json.
+impl ToJsonTable for [SegmentMetadataResponse] {
+    fn to_json_table(&self) -> Result<JsonTable, serde_json::Error> {
+        let mut table = JsonTable::default();
+        for response in self {
+            table.push_row(object(serde_json::to_value(response)?)?);
+        }
+        Ok(table)
+    }
+}
+
+/// Rows of [`DruidClient::query_rows`](crate::client::DruidClient::query_rows), with their
+/// columns in the query's output order.
+impl ToJsonTable for [Row] {
+    fn to_json_table(&self) -> Result<JsonTable, serde_json::Error> {
+        let mut table = JsonTable::default();
+        for row in self {
+            for (column, value) in row.iter() {
+                table.set(column, value.clone());
+            }
+            table.end_row();
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_flatten_top_n() {
+        let responses: Vec<DruidListResponse<Value>> = serde_json::from_str(
+            r#"[{"timestamp": "2015-09-12T00:00:00.000Z", "result": [{"page": "Main", "count": 3}, {"page": "Talk", "count": 1}]}]"#,
+        )
+        .unwrap();
+        let table = responses.to_json_table().unwrap();
+        assert_eq!(table.columns, vec!["timestamp", "page", "count"]);
+        assert_eq!(table.num_rows, 2);
+        assert_eq!(table.values("page").nth(1).unwrap(), "Talk");
+        assert_eq!(
+            table.values("timestamp").nth(1).unwrap(),
+            "2015-09-12T00:00:00.000Z"
+        );
+    }
+
+    #[test]
+    fn test_flatten_compacted_scan() {
+        let responses: Vec<ScanResponse<Value>> = serde_json::from_str(
+            r#"[{"segmentId": "s1", "columns": ["__time", "page"], "events": [[1442016000000, "Main"], [1442016001000, "Talk"]]}]"#,
+        )
+        .unwrap();
+        let table = responses.to_json_table().unwrap();
+        assert_eq!(table.columns, vec!["__time", "page"]);
+        assert_eq!(table.values("__time").nth(1).unwrap(), 1442016001000i64);
+        assert_eq!(table.infer_kind("__time"), ColumnKind::Timestamp);
+    }
+
+    #[test]
+    fn test_infer_kind() {
+        let responses: Vec<GroupByResponse<Value>> = serde_json::from_str(
+            r#"[
+                {"timestamp": "2015-09-12T00:00:00.000Z", "event": {"count": 3, "ratio": 1, "tags": null, "page": "Main"}},
+                {"timestamp": "2015-09-12T00:00:00.000Z", "event": {"count": 1, "ratio": 0.5, "tags": ["a"], "page": 1}},
+                {"timestamp": "2015-09-12T00:00:00.000Z", "event": {"count": 1, "ratio": 0.5, "tags": "b", "page": "Talk"}}
+            ]"#,
+        )
+        .unwrap();
+        let table = responses.to_json_table().unwrap();
+        assert_eq!(table.infer_kind("timestamp"), ColumnKind::Timestamp);
+        assert_eq!(table.infer_kind("count"), ColumnKind::Long);
+        assert_eq!(table.infer_kind("ratio"), ColumnKind::Double);
+        assert_eq!(table.infer_kind("tags"), ColumnKind::StringList);
+        assert_eq!(table.infer_kind("page"), ColumnKind::String);
+    }
+
+    #[test]
+    fn test_infer_timestamp_from_values() {
+        let responses: Vec<ScanResponse<Value>> = serde_json::from_str(
+            r#"[{"segmentId": "s1", "columns": ["__time", "timestamp", "edited"], "events": [
+                {"__time": 1442016000000, "timestamp": "yesterday", "edited": "2015-09-11T23:00:00Z"},
+                {"__time": 1442016001000, "timestamp": null, "edited": null}
+            ]}]"#,
+        )
+        .unwrap();
+        let table = responses.to_json_table().unwrap();
+        assert_eq!(table.infer_kind("__time"), ColumnKind::Timestamp);
+        assert_eq!(table.infer_kind("timestamp"), ColumnKind::String);
+        assert_eq!(table.infer_kind("edited"), ColumnKind::Timestamp);
+    }
+}
@@ -0,0 +1,45 @@
+//! Lenient conversions of the json values druid returns.
+
+use serde_json::Value;
+
+/// Integers, and doubles holding a whole number.
+pub(crate) fn as_i64(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(number) => number.as_i64().or_else(|| {
+            number
+                .as_f64()
+                .filter(|float| float.fract() == 0.0 && float.abs() < i64::MAX as f64)
+                .map(|float| float as i64)
+        }),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        // druid sends non finite doubles as strings
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Epoch millis, as scan queries return `__time`, or ISO 8601 timestamps.
+pub(crate) fn as_timestamp_millis(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(number) => number.as_i64(),
+        Value::String(text) => chrono::DateTime::parse_from_rfc3339(text)
+            .ok()
+            .map(|time| time.timestamp_millis()),
+        _ => None,
+    }
+}
+
+#[cfg(any(feature = "arrow", feature = "polars"))]
+pub(crate) fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,430 @@
+//! In-process stand-in for a druid broker, to test code using [`DruidClient`](crate::client::DruidClient)
+//! without a running cluster.
+//!
+//! ```rust
+//! use druid_io::client::DruidClientBuilder;
+//! use druid_io::testing::{Expectation, MockDruidServer};
+//! use serde_json::json;
+//!
+//! let server = MockDruidServer::start();
+//! server.mock(
+//!     Expectation::query_type("timeseries")
+//!         .data_source("wikipedia")
+//!         .respond_with_json(json!([{"timestamp": "2015-09-12T00:00:00.000Z", "result": {"count": 3}}])),
+//! );
+//! let client = DruidClientBuilder::new(&server.url()).build();
+//! ```
+
+use crate::client::{normalize_query, QUERY_ID_KEYS};
+use crate::query::response::DruidError;
+#[cfg(feature = "smile")]
+use crate::serialization::smile;
+use futures::channel::oneshot;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const NATIVE_PATH: &str = "/druid/v2";
+
+/// Request received by a [`MockDruidServer`].
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path as sent, percent-encoding included.
+    pub path: String,
+    /// Query string without the `?`, e.g. `full` or `state=true`.
+    pub query: Option<String>,
+    /// Headers by lowercase name.
+    pub headers: HashMap<String, String>,
+    /// Body parsed as json (or smile), `Value::Null` when it was neither.
+    pub body: Value,
+}
+
+#[derive(Debug, Clone)]
+struct MockResponse {
+    status: u16,
+    body: String,
+    delay: Option<Duration>,
+}
+
+/// Which requests an [`Expectation`] answers and with what: native queries, or calls to other
+/// APIs (overlord, coordinator...) with [`Expectation::request`].
+#[derive(Debug, Clone)]
+pub struct Expectation {
+    request: Option<(String, String)>,
+    query_type: Option<String>,
+    data_source: Option<String>,
+    exact: Option<Value>,
+    response: MockResponse,
+    times: Option<usize>,
+}
+
+impl Expectation {
+    /// Matches every query.
+    pub fn any() -> Self {
+        Expectation {
+            request: None,
+            query_type: None,
+            data_source: None,
+            exact: None,
+            response: MockResponse {
+                status: 200,
+                body: "[]".into(),
+                delay: None,
+            },
+            times: None,
+        }
+    }
+
+    /// Matches queries of the given `queryType`, e.g. `"timeseries"` or `"groupBy"`.
+    pub fn query_type(query_type: &str) -> Self {
+        Expectation::any().and_query_type(query_type)
+    }
+
+    /// Matches queries equal to `query` once serialized. Query ids the client generates are
+    /// ignored unless `query` sets one itself.
+    pub fn exact<Q: Serialize>(query: &Q) -> Self {
+        Expectation {
+            exact: Some(serde_json::to_value(query).expect("query should serialize to json")),
+            ..Expectation::any()
+        }
+    }
+
+    /// Matches requests with the method and path (percent-encoded as sent), e.g.
+    /// `Expectation::request("GET", "/druid/indexer/v1/task/index_abc/status")`.
+    pub fn request(method: &str, path: &str) -> Self {
+        Expectation {
+            request: Some((method.to_uppercase(), path.to_string())),
+            ..Expectation::any()
+        }
+    }
+
+    pub fn and_query_type(mut self, query_type: &str) -> Self {
+        self.query_type = Some(query_type.to_string());
+        self
+    }
+
+    /// Only matches queries reading the table (directly, through a union, a join or a subquery).
+    pub fn data_source(mut self, table: &str) -> Self {
+        self.data_source = Some(table.to_string());
+        self
+    }
+
+    /// Waits before answering, e.g. to run into a client timeout.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.response.delay = Some(delay);
+        self
+    }
+
+    /// Stops matching after answering `times` queries.
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    pub fn respond_with<T: Serialize>(self, rows: &T) -> Self {
+        self.respond_with_json(serde_json::to_value(rows).expect("rows should serialize to json"))
+    }
+
+    pub fn respond_with_json(mut self, body: Value) -> Self {
+        self.response = MockResponse {
+            status: 200,
+            body: body.to_string(),
+            ..self.response
+        };
+        self
+    }
+
+    /// Fails matching queries the way druid does, with the error as json body.
+    pub fn respond_with_error(mut self, status: u16, error: DruidError) -> Self {
+        self.response = MockResponse {
+            status,
+            body: serde_json::to_string(&error).expect("druid error should serialize to json"),
+            ..self.response
+        };
+        self
+    }
+
+    /// Answers with an arbitrary status and body, e.g. a `503` from a proxy.
+    pub fn respond_with_status(mut self, status: u16, body: &str) -> Self {
+        self.response = MockResponse {
+            status,
+            body: body.to_string(),
+            ..self.response
+        };
+        self
+    }
+
+    fn matches(&self, request: &RecordedRequest) -> bool {
+        match &self.request {
+            Some((method, path)) => *method == request.method && *path == request.path,
+            None => {
+                request.method == Method::POST.as_str()
+                    && request.path.trim_end_matches('/') == NATIVE_PATH
+                    && self.matches_query(&request.body)
+            }
+        }
+    }
+
+    fn matches_query(&self, query: &Value) -> bool {
+        if let Some(query_type) = &self.query_type {
+            if query.get("queryType").and_then(Value::as_str) != Some(query_type) {
+                return false;
+            }
+        }
+        if let Some(table) = &self.data_source {
+            if !query
+                .get("dataSource")
+                .is_some_and(|data_source| reads_table(data_source, table))
+            {
+                return false;
+            }
+        }
+        match &self.exact {
+            Some(expected) => {
+                let context = |query: &Value, key: &str| {
+                    query
+                        .get("context")
+                        .and_then(|context| context.get(key))
+                        .cloned()
+                };
+                let same_ids = QUERY_ID_KEYS.iter().all(|key| {
+                    context(expected, key).is_none()
+                        || context(expected, key) == context(query, key)
+                });
+                same_ids && normalize_query(query) == normalize_query(expected)
+            }
+            None => true,
+        }
+    }
+}
+
+fn reads_table(data_source: &Value, table: &str) -> bool {
+    match data_source {
+        Value::String(name) => name == table,
+        Value::Object(fields) => match fields.get("type").and_then(Value::as_str) {
+            Some("table") => fields.get("name").and_then(Value::as_str) == Some(table),
+            Some("union") => fields
+                .get("dataSources")
+                .and_then(Value::as_array)
+                .is_some_and(|names| names.iter().any(|name| name == table)),
+            Some("join") => ["left", "right"].iter().any(|side| {
+                fields
+                    .get(*side)
+                    .is_some_and(|side| reads_table(side, table))
+            }),
+            Some("query") => fields
+                .get("query")
+                .and_then(|query| query.get("dataSource"))
+                .is_some_and(|inner| reads_table(inner, table)),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[derive(Default)]
+struct State {
+    expectations: Vec<(Expectation, usize)>,
+    requests: Vec<RecordedRequest>,
+}
+
+impl State {
+    fn respond(&mut self, request: RecordedRequest) -> MockResponse {
+        // the most recently registered expectation wins, like later stubs override earlier ones
+        let matching = self
+            .expectations
+            .iter_mut()
+            .rev()
+            .find(|(expectation, used)| {
+                expectation.times.is_none_or(|times| *used < times) && expectation.matches(&request)
+            });
+        let response = match matching {
+            Some((expectation, used)) => {
+                *used += 1;
+                expectation.response.clone()
+            }
+            // query cancellation
+            None if request.method == Method::DELETE.as_str() => MockResponse {
+                status: 202,
+                body: String::new(),
+                delay: None,
+            },
+            None if request.path.trim_end_matches('/') != NATIVE_PATH => MockResponse {
+                status: 404,
+                body: format!("no mock for {} {}", request.method, request.path),
+                delay: None,
+            },
+            None => MockResponse {
+                status: 500,
+                body: serde_json::json!({
+                    "error": "Unknown exception",
+                    "errorMessage": format!("no expectation matches query {}", request.body),
+                })
+                .to_string(),
+                delay: None,
+            },
+        };
+        self.requests.push(request);
+        response
+    }
+}
+
+/// Broker listening on a local port, answering native queries (`POST /druid/v2`) and calls to
+/// other APIs from the registered [`Expectation`]s and recording every request. Queries nothing
+/// matches fail with a `500` druid error, other paths with a `404`. Smile queries are answered
+/// in smile. It runs on its own thread, so it works with any runtime and stops when dropped.
+pub struct MockDruidServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockDruidServer {
+    /// Starts the server on a free port of `127.0.0.1`.
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("couldn't bind a local port");
+        let addr = listener
+            .local_addr()
+            .expect("bound listener has an address");
+        let state = Arc::new(Mutex::new(State::default()));
+        let (shutdown, stopped) = oneshot::channel::<()>();
+
+        let server_state = state.clone();
+        let thread = std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("couldn't start the mock server runtime");
+            runtime.block_on(async move {
+                listener
+                    .set_nonblocking(true)
+                    .expect("couldn't configure the listener");
+                let make_service = make_service_fn(move |_| {
+                    let state = server_state.clone();
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |request| {
+                            handle(state.clone(), request)
+                        }))
+                    }
+                });
+                let _ = Server::from_tcp(listener)
+                    .expect("couldn't serve on the listener")
+                    .serve(make_service)
+                    .with_graceful_shutdown(async {
+                        let _ = stopped.await;
+                    })
+                    .await;
+            });
+        });
+
+        MockDruidServer {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        }
+    }
+
+    /// Base url to build a client with, e.g. `http://127.0.0.1:34567`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn mock(&self, expectation: Expectation) {
+        self.state
+            .lock()
+            .unwrap()
+            .expectations
+            .push((expectation, 0));
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Forgets expectations and recorded requests.
+    pub fn reset(&self) {
+        *self.state.lock().unwrap() = State::default();
+    }
+}
+
+impl Drop for MockDruidServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let query = request.uri().query().map(str::to_string);
+    let headers = request
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    #[cfg(feature = "smile")]
+    let is_smile = |header| {
+        request
+            .headers()
+            .get(header)
+            .is_some_and(|value| value == smile::CONTENT_TYPE)
+    };
+    #[cfg(feature = "smile")]
+    let (smile_request, smile_response) = (
+        is_smile(hyper::header::CONTENT_TYPE),
+        is_smile(hyper::header::ACCEPT),
+    );
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        #[cfg(feature = "smile")]
+        Ok(body) if smile_request => smile::from_slice(&body).unwrap_or(Value::Null),
+        Ok(body) => serde_json::from_slice(&body).unwrap_or(Value::Null),
+        Err(_) => Value::Null,
+    };
+    let response = state.lock().unwrap().respond(RecordedRequest {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    });
+    if let Some(delay) = response.delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let mut builder = Response::builder()
+        .status(StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR));
+    let json = serde_json::from_str::<Value>(&response.body);
+    #[cfg(feature = "smile")]
+    if let (true, Ok(json)) = (smile_response, &json) {
+        let body = smile::to_vec(json).expect("json converts to smile");
+        return Ok(builder
+            .header(hyper::header::CONTENT_TYPE, smile::CONTENT_TYPE)
+            .body(Body::from(body))
+            .expect("mock response is valid"));
+    }
+    if json.is_ok() {
+        builder = builder.header(hyper::header::CONTENT_TYPE, "application/json");
+    }
+    Ok(builder
+        .body(Body::from(response.body))
+        .expect("mock response is valid"))
+}
@@ -0,0 +1,886 @@
+extern crate druid_io;
+
+use chrono::NaiveDate;
+use druid_io::{
+    client::{AdminClientBuilder, DruidClientError},
+    coordinator::{
+        AuditInfo, DataSourceCompactionConfig, LookupExtractorFactory, LookupSpec, Rule,
+        SegmentSelection,
+    },
+    ingestion::{
+        response::{SupervisorState, TaskState, TaskStatusCode},
+        DataSchema, DimensionSchema, DimensionsSpec, IndexParallelTask, InlineIngestion,
+        InputFormat, InputSource, IoConfig, KafkaIoConfig, PartitionsSpec, SupervisorSpec,
+        TimestampSpec, TuningConfig,
+    },
+    query::{definitions::Interval, sql::SqlQueryBuilder},
+    testing::{Expectation, MockDruidServer, RecordedRequest},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn data_schema() -> DataSchema {
+    DataSchema::new(
+        "wikipedia",
+        TimestampSpec::new("time", "iso"),
+        DimensionsSpec::new(vec![DimensionSchema::string("page")]),
+    )
+}
+
+#[test]
+fn test_submit_task() {
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::request("POST", "/druid/indexer/v1/task")
+            .respond_with_json(json!({"task": "index_parallel_wikipedia_abc"})),
+    );
+    let overlord = AdminClientBuilder::new(&server.url()).overlord();
+    let task = IndexParallelTask::new(
+        data_schema(),
+        IoConfig::new(
+            InputSource::inline(r#"{"time":"2015-09-12T00:00:00Z","page":"Main"}"#),
+            InputFormat::json(),
+        ),
+    );
+
+    let task_id = tokio_test::block_on(overlord.submit_task(&task)).unwrap();
+    assert_eq!(task_id, "index_parallel_wikipedia_abc");
+    let requests = server.requests();
+    assert_eq!(requests[0].headers["content-type"], "application/json");
+    assert_eq!(requests[0].body["type"], "index_parallel");
+    assert_eq!(
+        requests[0].body["spec"]["dataSchema"]["dataSource"],
+        "wikipedia"
+    );
+
+    server.mock(
+        Expectation::request("POST", "/druid/indexer/v1/task").respond_with_status(
+            400,
+            r#"{"error": "Cannot construct instance of `IndexTask`"}"#,
+        ),
+    );
+    let result = tokio_test::block_on(overlord.submit_task(&task));
+    assert!(matches!(
+        result,
+        Err(DruidClientError::ServerError { status: 400, .. })
+    ));
+}
+
+/// Task id with characters that must be percent-encoded in paths.
+const TASK_ID: &str = "index_parallel_wikipedia_2015-09-12T00:00:00.000Z";
+const TASK_PATH: &str =
+    "/druid/indexer/v1/task/index_parallel_wikipedia_2015-09-12T00%3A00%3A00.000Z";
+
+fn task_status(status: &str) -> Value {
+    json!({"task": TASK_ID, "status": {"id": TASK_ID, "statusCode": status, "status": status}})
+}
+
+#[test]
+fn test_task_lifecycle() {
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::request("GET", &format!("{TASK_PATH}/status"))
+            .respond_with_json(task_status("RUNNING")),
+    );
+    server.mock(
+        Expectation::request("GET", &format!("{TASK_PATH}/reports")).respond_with_json(json!({
+            "ingestionStatsAndErrors": {
+                "taskId": TASK_ID,
+                "payload": {"ingestionState": "COMPLETED", "rowStats": {}}
+            }
+        })),
+    );
+    server.mock(
+        Expectation::request("GET", &format!("{TASK_PATH}/log"))
+            .respond_with_status(200, "2015-09-12 INFO Task started"),
+    );
+    server.mock(
+        Expectation::request("POST", &format!("{TASK_PATH}/shutdown"))
+            .respond_with_json(json!({"task": TASK_ID})),
+    );
+    server.mock(
+        Expectation::request("GET", "/druid/indexer/v1/tasks")
+            .respond_with_json(json!([{"id": TASK_ID, "statusCode": "RUNNING"}])),
+    );
+    let overlord = AdminClientBuilder::new(&server.url()).overlord();
+
+    let status = tokio_test::block_on(overlord.task_status(TASK_ID)).unwrap();
+    assert_eq!(status.code(), Some(TaskStatusCode::Running));
+    let report = tokio_test::block_on(overlord.task_report(TASK_ID)).unwrap();
+    assert!(report.ingestion_stats_and_errors.is_some());
+    let log = tokio_test::block_on(overlord.task_log(TASK_ID, -1000)).unwrap();
+    assert_eq!(log, "2015-09-12 INFO Task started");
+    tokio_test::block_on(overlord.shutdown_task(TASK_ID)).unwrap();
+    let tasks =
+        tokio_test::block_on(overlord.list_tasks(Some(TaskState::Running), Some("wikipedia")))
+            .unwrap();
+    assert_eq!(tasks[0].id, TASK_ID);
+
+    let requests = server.requests();
+    assert_eq!(requests[2].query.as_deref(), Some("offset=-1000"));
+    assert_eq!(requests[3].method, "POST");
+    assert_eq!(
+        requests[4].query.as_deref(),
+        Some("state=running&datasource=wikipedia")
+    );
+}
+
+#[test]
+fn test_wait_for_task() {
+    let server = MockDruidServer::start();
+    let status_path = format!("{TASK_PATH}/status");
+    server
+        .mock(Expectation::request("GET", &status_path).respond_with_json(task_status("SUCCESS")));
+    server.mock(
+        Expectation::request("GET", &status_path)
+            .times(2)
+            .respond_with_json(task_status("RUNNING")),
+    );
+    let overlord = AdminClientBuilder::new(&server.url()).overlord();
+
+    let status = tokio_test::block_on(overlord.wait_for_task(
+        TASK_ID,
+        Duration::from_millis(10),
+        Duration::from_secs(5),
+    ))
+    .unwrap();
+    assert_eq!(status.code(), Some(TaskStatusCode::Success));
+    assert_eq!(server.requests().len(), 3);
+
+    let mut failed = task_status("FAILED");
+    failed["status"]["errorMsg"] = json!("No valid rows");
+    server.mock(Expectation::request("GET", &status_path).respond_with_json(failed));
+    let result = tokio_test::block_on(overlord.wait_for_task(
+        TASK_ID,
+        Duration::from_millis(10),
+        Duration::from_secs(5),
+    ));
+    match result {
+        Err(DruidClientError::TaskFailed {
+            task_id, error_msg, ..
+        }) => {
+            assert_eq!(task_id, TASK_ID);
+            assert_eq!(error_msg.as_deref(), Some("No valid rows"));
+        }
+        other => panic!("expected a failed task, got {other:?}"),
+    }
+
+    server
+        .mock(Expectation::request("GET", &status_path).respond_with_json(task_status("RUNNING")));
+    let result = tokio_test::block_on(overlord.wait_for_task(
+        TASK_ID,
+        Duration::from_millis(10),
+        Duration::from_millis(50),
+    ));
+    assert!(matches!(
+        result,
+        Err(DruidClientError::TaskTimeout { task_id, .. }) if task_id == TASK_ID
+    ));
+}
+
+#[derive(Serialize)]
+struct Edit {
+    time: &'static str,
+    page: &'static str,
+}
+
+#[test]
+fn test_ingest_rows() {
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::request("POST", "/druid/indexer/v1/task")
+            .respond_with_json(json!({"task": TASK_ID})),
+    );
+    server.mock(
+        Expectation::request("GET", &format!("{TASK_PATH}/status"))
+            .respond_with_json(task_status("SUCCESS")),
+    );
+    let overlord = AdminClientBuilder::new(&server.url()).overlord();
+    let ingestion = InlineIngestion::new(data_schema())
+        .max_bytes_per_task(100)
+        .poll_interval(Duration::from_millis(10));
+
+    // each line is 46 bytes, two fit in a task; rows are serialized while earlier tasks run
+    let requests_before_row = std::cell::RefCell::new(vec![]);
+    let rows = (0..5).map(|_| {
+        requests_before_row
+            .borrow_mut()
+            .push(server.requests().len());
+        Edit {
+            time: "2015-09-12T00:00:00Z",
+            page: "Main",
+        }
+    });
+    let statuses = tokio_test::block_on(overlord.ingest_rows(rows, &ingestion)).unwrap();
+    assert_eq!(statuses.len(), 3);
+    assert_eq!(*requests_before_row.borrow(), vec![0, 0, 0, 2, 2]);
+
+    let submitted: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|request| request.method == "POST")
+        .map(|request| request.body["spec"]["ioConfig"].clone())
+        .collect();
+    assert_eq!(submitted.len(), 3);
+    // later tasks must not replace the rows of the first ones
+    assert_eq!(submitted[0]["appendToExisting"], false);
+    assert_eq!(submitted[1]["appendToExisting"], true);
+    assert_eq!(submitted[2]["appendToExisting"], true);
+    assert_eq!(
+        submitted[2]["inputSource"]["data"],
+        "{\"time\":\"2015-09-12T00:00:00Z\",\"page\":\"Main\"}\n"
+    );
+}
+
+#[test]
+fn test_partial_ingestion() {
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::request("POST", "/druid/indexer/v1/task")
+            .respond_with_json(json!({"task": TASK_ID})),
+    );
+    let status_path = format!("{TASK_PATH}/status");
+    server.mock(Expectation::request("GET", &status_path).respond_with_json(task_status("FAILED")));
+    server.mock(
+        Expectation::request("GET", &status_path)
+            .times(1)
+            .respond_with_json(task_status("SUCCESS")),
+    );
+    let overlord = AdminClientBuilder::new(&server.url()).overlord();
+    let ingestion = InlineIngestion::new(data_schema())
+        .max_bytes_per_task(100)
+        .poll_interval(Duration::from_millis(10));
+    let rows = (0..5).map(|_| Edit {
+        time: "2015-09-12T00:00:00Z",
+        page: "Main",
+    });
+
+    let result = tokio_test::block_on(overlord.ingest_rows(rows, &ingestion));
+    match result {
+        Err(DruidClientError::PartialIngestion { completed, source }) => {
+            assert_eq!(completed.len(), 1);
+            assert!(matches!(*source, DruidClientError::TaskFailed { .. }));
+        }
+        other => panic!("expected a partial ingestion, got {other:?}"),
+    }
+    // the third chunk is never submitted
+    let submitted = server
+        .requests()
+        .iter()
+        .filter(|request| request.method == "POST")
+        .count();
+    assert_eq!(submitted, 2);
+}
+
+#[test]
+fn test_ingest_rows_with_perfect_rollup() {
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::request("POST", "/druid/indexer/v1/task")
+            .respond_with_json(json!({"task": TASK_ID})),
+    );
+    server.mock(
+        Expectation::request("GET", &format!("{TASK_PATH}/status"))
+            .respond_with_json(task_status("SUCCESS")),
+    );
+    let overlord = AdminClientBuilder::new(&server.url()).overlord();
+    let ingestion = InlineIngestion::new(data_schema())
+        .tuning_config(TuningConfig {
+            partitions_spec: Some(PartitionsSpec::Hashed {
+                num_shards: Some(2),
+                target_rows_per_segment: None,
+                partition_dimensions: vec![],
+            }),
+            force_guaranteed_rollup: Some(true),
+            ..Default::default()
+        })
+        .max_bytes_per_task(100)
+        .poll_interval(Duration::from_millis(10));
+    let rows = |count| {
+        (0..count).map(|_| Edit {
+            time: "2015-09-12T00:00:00Z",
+            page: "Main",
+        })
+    };
+
+    // hashed partitions can't be appended to, nothing is submitted
+    let result = tokio_test::block_on(overlord.ingest_rows(rows(3), &ingestion));
+    assert!(matches!(
+        result,
+        Err(DruidClientError::IngestionTooLarge {
+            max_bytes_per_task: 100
+        })
+    ));
+    assert!(server.requests().is_empty());
+
+    let statuses = tokio_test::block_on(overlord.ingest_rows(rows(2), &ingestion)).unwrap();
+    assert_eq!(statuses.len(), 1);
+}
+
+#[test]
+fn test_supervisors() {
+    let server = MockDruidServer::start();
+    let supervisor_path = "/druid/indexer/v1/supervisor";
+    // supervisor ids are datasource names, which may contain spaces
+    let id_path = format!("{supervisor_path}/wiki%20edits");
+    server.mock(
+        Expectation::request("POST", supervisor_path)
+            .respond_with_json(json!({"id": "wiki edits"})),
+    );
+    server.mock(
+        Expectation::request("GET", supervisor_path).respond_with_json(json!([
+            {"id": "wiki edits", "state": "SUSPENDED", "detailedState": "SUSPENDED", "healthy": true, "suspended": true}
+        ])),
+    );
+    server.mock(
+        Expectation::request("GET", &format!("{id_path}/status")).respond_with_json(json!({
+            "id": "wiki edits",
+            "generationTime": "2015-09-12T00:00:00.000Z",
+            "payload": {
+                "dataSource": "wiki edits",
+                "stream": "wikipedia",
+                "partitions": 2,
+                "aggregateLag": 12,
+                "state": "RUNNING"
+            }
+        })),
+    );
+    for action in ["suspend", "resume", "reset", "terminate"] {
+        server.mock(
+            Expectation::request("POST", &format!("{id_path}/{action}"))
+                .respond_with_json(json!({"id": "wiki edits"})),
+        );
+    }
+    let supervisors = AdminClientBuilder::new(&server.url()).supervisors();
+    let mut data_schema = data_schema();
+    data_schema.data_source = "wiki edits".into();
+    let spec = SupervisorSpec::kafka(
+        data_schema,
+        KafkaIoConfig::new("wikipedia", "localhost:9092", InputFormat::json()),
+    );
+
+    let id = tokio_test::block_on(supervisors.create_or_update(&spec)).unwrap();
+    assert_eq!(id, "wiki edits");
+    let listed = tokio_test::block_on(supervisors.list()).unwrap();
+    assert_eq!(listed[0].state, SupervisorState::Suspended);
+    let status = tokio_test::block_on(supervisors.status("wiki edits")).unwrap();
+    assert_eq!(status.payload.aggregate_lag, Some(12));
+    tokio_test::block_on(supervisors.suspend("wiki edits")).unwrap();
+    tokio_test::block_on(supervisors.resume("wiki edits")).unwrap();
+    tokio_test::block_on(supervisors.reset("wiki edits")).unwrap();
+    tokio_test::block_on(supervisors.terminate("wiki edits")).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].body["type"], "kafka");
+    assert_eq!(requests[1].query.as_deref(), Some("state=true"));
+    let actions: Vec<_> = requests[3..]
+        .iter()
+        .map(|request| format!("{} {}", request.method, request.path))
+        .collect();
+    assert_eq!(
+        actions,
+        ["suspend", "resume", "reset", "terminate"]
+            .map(|action| format!("POST {id_path}/{action}"))
+    );
+
+    let result = tokio_test::block_on(supervisors.status("koalas"));
+    assert!(matches!(
+        result,
+        Err(DruidClientError::ServerError { status: 404, .. })
+    ));
+}
+
+const SEGMENT_ID: &str =
+    "wikipedia_2015-09-12T00:00:00.000Z_2015-09-13T00:00:00.000Z_2019-01-01T00:00:00.000Z";
+
+fn segment() -> Value {
+    json!({
+        "dataSource": "wikipedia",
+        "interval": "2015-09-12T00:00:00.000Z/2015-09-13T00:00:00.000Z",
+        "version": "2019-01-01T00:00:00.000Z",
+        "loadSpec": {"type": "local", "path": "/druid/segments/index.zip"},
+        "dimensions": "page,user",
+        "metrics": "count",
+        "shardSpec": {"type": "numbered", "partitionNum": 0, "partitions": 1},
+        "binaryVersion": 9,
+        "size": 4096,
+        "identifier": SEGMENT_ID
+    })
+}
+
+fn september_12() -> Interval {
+    Interval {
+        from: NaiveDate::from_ymd_opt(2015, 9, 12)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap(),
+        to: NaiveDate::from_ymd_opt(2015, 9, 13)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap(),
+    }
+}
+
+#[test]
+fn test_coordinator_segments() {
+    let server = MockDruidServer::start();
+    let segments_path = "/druid/coordinator/v1/metadata/datasources/wikipedia/segments";
+    server.mock(
+        Expectation::request("GET", "/druid/coordinator/v1/datasources")
+            .respond_with_json(json!(["wikipedia"])),
+    );
+    server.mock(Expectation::request("GET", segments_path).respond_with_json(json!([segment()])));
+    server.mock(Expectation::request("POST", segments_path).respond_with_json(json!([segment()])));
+    server.mock(
+        Expectation::request(
+            "GET",
+            &format!(
+                "{segments_path}/wikipedia_2015-09-12T00%3A00%3A00.000Z_2015-09-13T00%3A00%3A00.000Z_2019-01-01T00%3A00%3A00.000Z"
+            ),
+        )
+        .respond_with_json(segment()),
+    );
+    server.mock(
+        Expectation::request(
+            "POST",
+            "/druid/coordinator/v1/datasources/wikipedia/markUnused",
+        )
+        .respond_with_json(json!({"numChangedSegments": 3})),
+    );
+    server.mock(
+        Expectation::request(
+            "POST",
+            "/druid/coordinator/v1/datasources/wikipedia/markUsed",
+        )
+        .respond_with_json(json!({"numChangedSegments": 1})),
+    );
+    let coordinator = AdminClientBuilder::new(&server.url()).coordinator();
+
+    let data_sources = tokio_test::block_on(coordinator.data_sources()).unwrap();
+    assert_eq!(data_sources, ["wikipedia"]);
+    let segments = tokio_test::block_on(coordinator.segments("wikipedia", &[])).unwrap();
+    assert_eq!(segments[0].dimensions, ["page", "user"]);
+    let segments =
+        tokio_test::block_on(coordinator.segments("wikipedia", &[september_12()])).unwrap();
+    assert_eq!(segments.len(), 1);
+    let segment = tokio_test::block_on(coordinator.segment("wikipedia", SEGMENT_ID)).unwrap();
+    assert_eq!(segment.identifier, SEGMENT_ID);
+    let selection = SegmentSelection::interval(september_12());
+    let changed = tokio_test::block_on(coordinator.mark_unused("wikipedia", &selection)).unwrap();
+    assert_eq!(changed, 3);
+    let selection = SegmentSelection::segment_ids(vec![SEGMENT_ID.to_string()]);
+    let changed = tokio_test::block_on(coordinator.mark_used("wikipedia", &selection)).unwrap();
+    assert_eq!(changed, 1);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].query, None);
+    assert_eq!(requests[1].query.as_deref(), Some("full"));
+    assert_eq!(requests[2].query.as_deref(), Some("full"));
+    assert_eq!(
+        requests[2].body,
+        json!(["2015-09-12T00:00:00/2015-09-13T00:00:00"])
+    );
+    assert_eq!(
+        requests[4].body,
+        json!({"interval": "2015-09-12T00:00:00/2015-09-13T00:00:00"})
+    );
+    assert_eq!(requests[5].body, json!({"segmentIds": [SEGMENT_ID]}));
+}
+
+#[test]
+fn test_coordinator_load_status() {
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::request("GET", "/druid/coordinator/v1/datasources").respond_with_json(json!([{
+            "name": "wikipedia",
+            "properties": {
+                "tiers": {"_default_tier": {"segmentCount": 2, "size": 4096, "replicatedSize": 8192}},
+                "segments": {"count": 2, "size": 4096, "replicatedSize": 8192}
+            }
+        }])),
+    );
+    server.mock(
+        Expectation::request("GET", "/druid/coordinator/v1/loadstatus")
+            .respond_with_json(json!({"wikipedia": 100.0})),
+    );
+    let coordinator = AdminClientBuilder::new(&server.url()).coordinator();
+
+    let summary = tokio_test::block_on(coordinator.data_sources_summary()).unwrap();
+    assert_eq!(summary[0].properties.segments.replicated_size, 8192);
+    let load_status = tokio_test::block_on(coordinator.load_status()).unwrap();
+    assert_eq!(load_status["wikipedia"], 100.0);
+
+    server.mock(
+        Expectation::request("GET", "/druid/coordinator/v1/loadstatus")
+            .respond_with_json(json!({"_default_tier": {"wikipedia": 2}})),
+    );
+    let left = tokio_test::block_on(coordinator.load_status_full()).unwrap();
+    assert_eq!(left["_default_tier"]["wikipedia"], 2);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].query.as_deref(), Some("simple"));
+    assert_eq!(requests[1].query, None);
+    assert_eq!(requests[2].query.as_deref(), Some("full"));
+}
+
+fn assert_audited(request: &RecordedRequest) {
+    assert_eq!(request.headers["x-druid-author"], "ops");
+    assert_eq!(request.headers["x-druid-comment"], "keep a month");
+}
+
+#[test]
+fn test_rules_and_compaction() {
+    let server = MockDruidServer::start();
+    let rules = vec![
+        Rule::load_by_period("P1M", [("_default_tier".to_string(), 2)].into()),
+        Rule::drop_forever(),
+    ];
+    let rules_json = json!([
+        {"type": "loadByPeriod", "period": "P1M", "tieredReplicants": {"_default_tier": 2}},
+        {"type": "dropForever"}
+    ]);
+    // datasource names may contain spaces
+    let rules_path = "/druid/coordinator/v1/rules/wiki%20edits";
+    let compaction_path = "/druid/coordinator/v1/config/compaction";
+    server.mock(Expectation::request("POST", rules_path).respond_with_status(200, ""));
+    server.mock(Expectation::request("GET", rules_path).respond_with_json(rules_json.clone()));
+    server.mock(
+        Expectation::request("GET", "/druid/coordinator/v1/rules").respond_with_json(
+            json!({"_default": [{"type": "loadForever"}], "wiki edits": rules_json}),
+        ),
+    );
+    server.mock(Expectation::request("POST", compaction_path).respond_with_status(200, ""));
+    server.mock(
+        Expectation::request("GET", &format!("{compaction_path}/wiki%20edits")).respond_with_json(
+            json!({"dataSource": "wiki edits", "skipOffsetFromLatest": "P1D", "taskContext": null}),
+        ),
+    );
+    server.mock(
+        Expectation::request("DELETE", &format!("{compaction_path}/wiki%20edits"))
+            .respond_with_status(200, ""),
+    );
+    let coordinator = AdminClientBuilder::new(&server.url()).coordinator();
+    let audit = AuditInfo::new("ops", "keep a month");
+
+    tokio_test::block_on(coordinator.set_rules("wiki edits", &rules, &audit)).unwrap();
+    assert_eq!(
+        tokio_test::block_on(coordinator.rules("wiki edits")).unwrap(),
+        rules
+    );
+    let all_rules = tokio_test::block_on(coordinator.all_rules()).unwrap();
+    assert_eq!(
+        all_rules["_default"],
+        [Rule::load_forever(Default::default())]
+    );
+    let mut config = DataSourceCompactionConfig::new("wiki edits");
+    config.skip_offset_from_latest = Some("P1D".into());
+    tokio_test::block_on(coordinator.set_compaction_config(&config, &audit)).unwrap();
+    let stored = tokio_test::block_on(coordinator.compaction_config("wiki edits")).unwrap();
+    assert_eq!(stored.skip_offset_from_latest.as_deref(), Some("P1D"));
+    tokio_test::block_on(coordinator.delete_compaction_config("wiki edits", &audit)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].body, rules_json);
+    assert_audited(&requests[0]);
+    assert_eq!(
+        requests[3].body,
+        json!({"dataSource": "wiki edits", "skipOffsetFromLatest": "P1D"})
+    );
+    assert_audited(&requests[3]);
+    assert_eq!(requests[5].method, "DELETE");
+    assert_audited(&requests[5]);
+
+    let result = tokio_test::block_on(coordinator.compaction_config("koalas"));
+    assert!(matches!(
+        result,
+        Err(DruidClientError::ServerError { status: 404, .. })
+    ));
+}
+
+#[test]
+fn test_lookups() {
+    let server = MockDruidServer::start();
+    let config_path = "/druid/coordinator/v1/lookups/config";
+    // lookup names are free text
+    let lookup_path = format!("{config_path}/__default/country%2Fnames");
+    let status_path = "/druid/coordinator/v1/lookups/status";
+    let spec_json = json!({
+        "version": "v1",
+        "lookupExtractorFactory": {"type": "map", "map": {"fr": "France"}}
+    });
+    server.mock(Expectation::request("POST", config_path).respond_with_status(202, ""));
+    server.mock(Expectation::request("GET", config_path).respond_with_json(json!(["__default"])));
+    server.mock(
+        Expectation::request("GET", &format!("{config_path}/__default"))
+            .respond_with_json(json!(["country/names"])),
+    );
+    server.mock(Expectation::request("POST", &lookup_path).respond_with_status(202, ""));
+    server.mock(Expectation::request("GET", &lookup_path).respond_with_json(spec_json.clone()));
+    server.mock(Expectation::request("DELETE", &lookup_path).respond_with_status(202, ""));
+    server.mock(
+        Expectation::request("GET", &format!("{status_path}/__default/country%2Fnames"))
+            .respond_with_json(json!({"loaded": false, "pendingNodes": ["historical:8083"]})),
+    );
+    server.mock(
+        Expectation::request("GET", status_path)
+            .respond_with_json(json!({"__default": {"country/names": {"loaded": true}}})),
+    );
+    let lookups = AdminClientBuilder::new(&server.url()).lookups();
+    let audit = AuditInfo::new("ops", "keep a month");
+    let spec = LookupSpec::new(
+        "v1",
+        LookupExtractorFactory::map([("fr".to_string(), "France".to_string())].into()),
+    );
+
+    tokio_test::block_on(lookups.initialize(&audit)).unwrap();
+    assert_eq!(
+        tokio_test::block_on(lookups.tiers()).unwrap(),
+        ["__default"]
+    );
+    assert_eq!(
+        tokio_test::block_on(lookups.list("__default")).unwrap(),
+        ["country/names"]
+    );
+    tokio_test::block_on(lookups.create_or_update("__default", "country/names", &spec, &audit))
+        .unwrap();
+    let stored = tokio_test::block_on(lookups.get("__default", "country/names")).unwrap();
+    assert_eq!(stored.version, "v1");
+    let status = tokio_test::block_on(lookups.status("__default", "country/names")).unwrap();
+    assert_eq!(status.pending_nodes, ["historical:8083"]);
+    let all_status = tokio_test::block_on(lookups.all_status()).unwrap();
+    assert!(all_status["__default"]["country/names"].loaded);
+    tokio_test::block_on(lookups.delete("__default", "country/names", &audit)).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].body, json!({}));
+    assert_audited(&requests[0]);
+    assert_eq!(requests[3].body, spec_json);
+    assert_audited(&requests[3]);
+    assert_eq!(requests[5].query.as_deref(), Some("detailed=true"));
+    assert_eq!(requests[7].method, "DELETE");
+    assert_audited(&requests[7]);
+
+    let result = tokio_test::block_on(lookups.get("__default", "koalas"));
+    assert!(matches!(
+        result,
+        Err(DruidClientError::ServerError { status: 404, .. })
+    ));
+}
+
+const MSQ_TASK_PATH: &str = "/druid/indexer/v1/task/query-abc";
+
+fn msq_report(status: &str, error_report: Value) -> Value {
+    json!({"multiStageQuery": {
+        "taskId": "query-abc",
+        "payload": {"status": {"status": status, "errorReport": error_report}}
+    }})
+}
+
+#[test]
+fn test_msq_task() {
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::request("POST", "/druid/v2/sql/task")
+            .respond_with_json(json!({"taskId": "query-abc", "state": "RUNNING"})),
+    );
+    server.mock(
+        Expectation::request("GET", &format!("{MSQ_TASK_PATH}/status")).respond_with_json(
+            json!({"task": "query-abc", "status": {"id": "query-abc", "statusCode": "SUCCESS"}}),
+        ),
+    );
+    server.mock(
+        Expectation::request("GET", &format!("{MSQ_TASK_PATH}/reports"))
+            .respond_with_json(msq_report("SUCCESS", Value::Null)),
+    );
+    let msq = AdminClientBuilder::new(&server.url()).msq();
+    let mut query =
+        SqlQueryBuilder::new("INSERT INTO wikipedia SELECT * FROM edits PARTITIONED BY DAY")
+            .build();
+    query.context.max_num_tasks = Some(3);
+
+    let task_id = tokio_test::block_on(msq.submit_task(&query)).unwrap();
+    assert_eq!(task_id, "query-abc");
+    assert_eq!(server.requests()[0].body["context"]["maxNumTasks"], 3);
+    let report = tokio_test::block_on(msq.wait_for_task(
+        &task_id,
+        Duration::from_millis(10),
+        Duration::from_secs(5),
+    ))
+    .unwrap();
+    assert_eq!(report.task_id.as_deref(), Some("query-abc"));
+
+    // the error code comes from the report, the task status only has a message
+    server.mock(
+        Expectation::request("GET", &format!("{MSQ_TASK_PATH}/status")).respond_with_json(json!({
+            "task": "query-abc",
+            "status": {"id": "query-abc", "statusCode": "FAILED", "errorMsg": "TooManyBuckets: Too many..."}
+        })),
+    );
+    server.mock(
+        Expectation::request("GET", &format!("{MSQ_TASK_PATH}/reports")).respond_with_json(
+            msq_report(
+                "FAILED",
+                json!({"error": {"errorCode": "TooManyBuckets", "errorMessage": "Too many partition buckets", "maxBuckets": 5000}}),
+            ),
+        ),
+    );
+    let result = tokio_test::block_on(msq.wait_for_task(
+        &task_id,
+        Duration::from_millis(10),
+        Duration::from_secs(5),
+    ));
+    match result {
+        Err(DruidClientError::MsqFailed {
+            query_id,
+            error_msg,
+            error,
+            report,
+        }) => {
+            assert_eq!(query_id, "query-abc");
+            assert_eq!(
+                error_msg.as_deref(),
+                Some("TooManyBuckets: Too many partition buckets")
+            );
+            assert_eq!(error.unwrap().details["maxBuckets"], 5000);
+            assert!(report.is_some());
+        }
+        other => panic!("expected a failed query, got {other:?}"),
+    }
+
+    server.mock(
+        Expectation::request("GET", &format!("{MSQ_TASK_PATH}/status")).respond_with_json(
+            json!({"task": "query-abc", "status": {"id": "query-abc", "statusCode": "RUNNING"}}),
+        ),
+    );
+    let result = tokio_test::block_on(msq.wait_for_task(
+        &task_id,
+        Duration::from_millis(10),
+        Duration::from_millis(50),
+    ));
+    assert!(matches!(result, Err(DruidClientError::TaskTimeout { .. })));
+
+    // a task that isn't a multi-stage query
+    server.mock(
+        Expectation::request("GET", &format!("{MSQ_TASK_PATH}/reports"))
+            .respond_with_json(json!({"killUnusedSegments": {"type": "killUnusedSegments"}})),
+    );
+    match tokio_test::block_on(msq.task_report(&task_id)) {
+        Err(DruidClientError::MissingTaskReport { task_id, report }) => {
+            assert_eq!(task_id, "query-abc");
+            assert_eq!(report, "multiStageQuery");
+        }
+        other => panic!("expected a missing report, got {other:?}"),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct PageCount {
+    page: String,
+    count: u64,
+}
+
+fn statement(state: &str) -> Value {
+    json!({"queryId": "query-abc", "state": state})
+}
+
+#[test]
+fn test_msq_select() {
+    let server = MockDruidServer::start();
+    let statement_path = "/druid/v2/sql/statements/query-abc";
+    let results_path = format!("{statement_path}/results");
+    server.mock(
+        Expectation::request("POST", "/druid/v2/sql/statements")
+            .respond_with_json(statement("ACCEPTED")),
+    );
+    let mut succeeded = statement("SUCCESS");
+    succeeded["result"] = json!({"numTotalRows": 3, "pages": [{"id": 0}, {"id": 1}]});
+    server.mock(Expectation::request("GET", statement_path).respond_with_json(succeeded));
+    server.mock(
+        Expectation::request("GET", statement_path)
+            .times(1)
+            .respond_with_json(statement("RUNNING")),
+    );
+    server.mock(
+        Expectation::request("GET", &results_path)
+            .respond_with_json(json!([{"page": "Talk", "count": 1}])),
+    );
+    server.mock(
+        Expectation::request("GET", &results_path)
+            .times(1)
+            .respond_with_json(json!([{"page": "Main", "count": 3}, {"page": "User", "count": 2}])),
+    );
+    let msq = AdminClientBuilder::new(&server.url()).msq();
+    let query =
+        SqlQueryBuilder::new("SELECT page, COUNT(*) AS \"count\" FROM wikipedia GROUP BY 1")
+            .build();
+
+    let rows = tokio_test::block_on(msq.select::<PageCount>(
+        &query,
+        Duration::from_millis(10),
+        Duration::from_secs(5),
+    ))
+    .unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[2].page, "Talk");
+    assert_eq!(rows[0].count, 3);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].body["context"]["executionMode"], "ASYNC");
+    assert_eq!(requests.len(), 5);
+    assert_eq!(
+        requests[3].query.as_deref(),
+        Some("page=0&resultFormat=object")
+    );
+    assert_eq!(
+        requests[4].query.as_deref(),
+        Some("page=1&resultFormat=object")
+    );
+}
+
+#[test]
+fn test_msq_statement_errors() {
+    let server = MockDruidServer::start();
+    let statement_path = "/druid/v2/sql/statements/query-abc";
+    let mut failed = statement("FAILED");
+    failed["errorDetails"] =
+        json!({"errorCode": "CannotParseExternalData", "errorMessage": "Unable to parse row"});
+    server.mock(Expectation::request("GET", statement_path).respond_with_json(failed));
+    server.mock(Expectation::request("DELETE", statement_path).respond_with_status(202, ""));
+    let msq = AdminClientBuilder::new(&server.url()).msq();
+
+    let result = tokio_test::block_on(msq.wait_for_statement(
+        "query-abc",
+        Duration::from_millis(10),
+        Duration::from_secs(5),
+    ));
+    match result {
+        Err(DruidClientError::MsqFailed {
+            error_msg, report, ..
+        }) => {
+            assert_eq!(
+                error_msg.as_deref(),
+                Some("CannotParseExternalData: Unable to parse row")
+            );
+            assert!(report.is_none());
+        }
+        other => panic!("expected a failed statement, got {other:?}"),
+    }
+
+    server
+        .mock(Expectation::request("GET", statement_path).respond_with_json(statement("RUNNING")));
+    let result = tokio_test::block_on(msq.wait_for_statement(
+        "query-abc",
+        Duration::from_millis(10),
+        Duration::from_millis(50),
+    ));
+    assert!(matches!(
+        result,
+        Err(DruidClientError::TaskTimeout { task_id, .. }) if task_id == "query-abc"
+    ));
+
+    tokio_test::block_on(msq.cancel_statement("query-abc")).unwrap();
+    let cancel = server.requests().pop().unwrap();
+    assert_eq!(cancel.method, "DELETE");
+    assert_eq!(cancel.path, statement_path);
+}
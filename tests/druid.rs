@@ -8,6 +8,7 @@ use druid_io::{
     query::timeseries::Timeseries,
     query::top_n::TopN,
     query::{
+        context::QueryContext,
         definitions::{Aggregation, VirtualColumn},
         definitions::{
             Dimension, Filter, Granularity, Having, Interval, Limit, OrderByColumn, Ordering,
@@ -16,6 +17,7 @@ use druid_io::{
         group_by::{GroupBy, GroupByBuilder},
         search::SearchQuerySpec,
         segment_metadata::{AnalysisType, SegmentMetadata, ToInclude},
+        sql::{SqlParameter, SqlQueryBuilder, SqlResultFormat},
         time_boundary::{TimeBoundType, TimeBoundary},
         DataSource,
     },
@@ -24,7 +26,6 @@ use reqwest::Client;
 use reqwest_middleware::ClientBuilder;
 use serde::Deserialize;
 use serde::Serialize;
-use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct WikiPage {
@@ -36,8 +37,10 @@ struct WikiPage {
 
 #[test]
 fn test_top_n_query() {
-    let mut context = HashMap::new();
-    context.insert("resultAsArray".to_string(), "true".to_string());
+    let context = QueryContext {
+        result_as_array: Some(true),
+        ..Default::default()
+    };
     let top_n = TopN {
         data_source: DataSource::table("wikipedia"),
         dimension: Dimension::default("page"),
@@ -54,11 +57,17 @@ fn test_top_n_query() {
             OutputType::STRING,
         )],
         intervals: vec![Interval {
-            from: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(8, 23, 32, 96),
-            to: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(15, 36, 27, 96),
+            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(8, 23, 32, 96)
+                .unwrap(),
+            to: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(15, 36, 27, 96)
+                .unwrap(),
         }],
         granularity: Granularity::all(),
-        context: context,
+        context,
     };
     let druid_client = DruidClientBuilder::new("http://localhost:8082").build();
     let result = tokio_test::block_on(druid_client.top_n::<WikiPage>(&top_n));
@@ -111,8 +120,14 @@ fn test_group_by() {
         )],
         having: Some(Having::greater_than("count_fraction", 0.01.into())),
         intervals: vec![Interval {
-            from: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(8, 23, 32, 96),
-            to: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(15, 36, 27, 96),
+            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(8, 23, 32, 96)
+                .unwrap(),
+            to: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(15, 36, 27, 96)
+                .unwrap(),
         }],
         subtotal_spec: Default::default(),
         context: Default::default(),
@@ -131,8 +146,10 @@ pub struct TimeAggr {
 }
 #[test]
 fn test_timeseries() {
-    let mut context = HashMap::new();
-    context.insert("grandTotal".to_string(), "true".to_string());
+    let context = QueryContext {
+        grand_total: Some(true),
+        ..Default::default()
+    };
 
     let timeseries = Timeseries {
         data_source: DataSource::table("wikipedia"),
@@ -160,10 +177,16 @@ fn test_timeseries() {
             OutputType::STRING,
         )],
         intervals: vec![Interval {
-            from: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(8, 23, 32, 96),
-            to: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(15, 36, 27, 96),
+            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(8, 23, 32, 96)
+                .unwrap(),
+            to: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(15, 36, 27, 96)
+                .unwrap(),
         }],
-        context: context,
+        context,
     };
     let druid_client = DruidClientBuilder::new("http://localhost:8082").build();
     let result = tokio_test::block_on(druid_client.timeseries::<TimeAggr>(&timeseries));
@@ -222,8 +245,14 @@ fn test_group_by_builder() {
             OutputType::STRING,
         )])
         .intervals(vec![Interval {
-            from: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(8, 23, 32, 96),
-            to: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(15, 36, 27, 96),
+            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(8, 23, 32, 96)
+                .unwrap(),
+            to: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(15, 36, 27, 96)
+                .unwrap(),
         }])
         .add_context("groupByStrategy", "v2")
         // .add_context("resultAsArray", "true")
@@ -243,14 +272,20 @@ fn test_search() {
         filter: None,
         limit: 20,
         intervals: vec![Interval {
-            from: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(8, 23, 32, 96),
-            to: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(15, 36, 27, 96),
+            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(8, 23, 32, 96)
+                .unwrap(),
+            to: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(15, 36, 27, 96)
+                .unwrap(),
         }],
         context: Default::default(),
         granularity: Granularity::all(),
     };
     let druid_client = DruidClientBuilder::new("http://localhost:8082").build();
-    let result = tokio_test::block_on(druid_client.search::<WikiPage>(&search));
+    let result = tokio_test::block_on(druid_client.search(&search));
     println!("{:?}", result.unwrap());
 }
 #[test]
@@ -262,7 +297,7 @@ fn test_time_boundary() {
         bound: TimeBoundType::MinMaxTime,
     };
     let druid_client = DruidClientBuilder::new("http://localhost:8082").build();
-    let result = tokio_test::block_on(druid_client.time_boundary::<WikiPage>(&top_n));
+    let result = tokio_test::block_on(druid_client.time_boundary(&top_n));
     println!("{:?}", result.unwrap());
 }
 #[test]
@@ -277,8 +312,14 @@ fn test_segment_metadata() {
     let segment_query = SegmentMetadata {
         data_source: DataSource::table("countries"),
         intervals: vec![Interval {
-            from: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(8, 23, 32, 96),
-            to: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(15, 36, 27, 96),
+            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(8, 23, 32, 96)
+                .unwrap(),
+            to: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(15, 36, 27, 96)
+                .unwrap(),
         }],
         to_include: ToInclude::All,
         merge: false,
@@ -305,8 +346,14 @@ fn test_client_builder() {
     let segment_query = SegmentMetadata {
         data_source: DataSource::table("countries"),
         intervals: vec![Interval {
-            from: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(8, 23, 32, 96),
-            to: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(15, 36, 27, 96),
+            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(8, 23, 32, 96)
+                .unwrap(),
+            to: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(15, 36, 27, 96)
+                .unwrap(),
         }],
         to_include: ToInclude::All,
         merge: false,
@@ -331,3 +378,60 @@ fn test_client_builder() {
     let result = tokio_test::block_on(druid_client.segment_metadata(&segment_query));
     println!("{:?}", result.unwrap());
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SqlPage {
+    page: String,
+    count: usize,
+}
+#[test]
+fn test_sql() {
+    let druid_client = DruidClientBuilder::new("http://localhost:8082").build();
+    for format in [
+        SqlResultFormat::Object,
+        SqlResultFormat::Array,
+        SqlResultFormat::ObjectLines,
+        SqlResultFormat::ArrayLines,
+        SqlResultFormat::Csv,
+    ] {
+        let sql = SqlQueryBuilder::new(
+            "SELECT page, COUNT(*) AS \"count\" FROM wikipedia WHERE \"user\" = ? GROUP BY page",
+        )
+        .add_parameter(SqlParameter::varchar("Taffe316"))
+        .result_format(format)
+        .header(true)
+        .build();
+        let result = tokio_test::block_on(druid_client.sql::<SqlPage>(&sql));
+        println!("{:?}", result.unwrap());
+    }
+
+    let sql = SqlQueryBuilder::new("SELECT page, \"user\" FROM wikipedia LIMIT 10").build();
+    let result = tokio_test::block_on(druid_client.sql_result_set(&sql));
+    println!("{:?}", result.unwrap());
+}
+
+#[test]
+fn test_cancel() {
+    let druid_client = DruidClientBuilder::new("http://localhost:8082")
+        .cancel_on_drop(true)
+        .build();
+    let result = tokio_test::block_on(druid_client.cancel("druid-io-test-query"));
+    println!("{:?}", result.unwrap());
+}
+
+#[test]
+fn test_response_metadata() {
+    let mut context = QueryContext::default();
+    context.insert("uncoveredIntervalsLimit", 10);
+    let time_boundary = TimeBoundary {
+        data_source: DataSource::table("wikipedia"),
+        filter: None,
+        context,
+        bound: TimeBoundType::MinMaxTime,
+    };
+    let druid_client = DruidClientBuilder::new("http://localhost:8082").build();
+    let result =
+        tokio_test::block_on(druid_client.time_boundary_with_metadata(&time_boundary)).unwrap();
+    assert!(result.metadata.query_id.is_some());
+    println!("{:?}", result);
+}
@@ -0,0 +1,44 @@
+extern crate druid_io;
+extern crate tokio;
+
+use druid_io::{
+    client::AdminClientBuilder,
+    ingestion::{DataSchema, DimensionSchema, DimensionsSpec, InlineIngestion, TimestampSpec},
+};
+use serde::Serialize;
+use std::time::Duration;
+
+#[derive(Serialize, Debug)]
+struct Edit {
+    time: String,
+    page: String,
+    added: i64,
+}
+
+#[test]
+fn test_ingest_rows() {
+    let overlord = AdminClientBuilder::new("http://localhost:8888").overlord();
+    let rows = (0..100).map(|i| Edit {
+        time: format!("2021-01-01T00:{:02}:00Z", i % 60),
+        page: format!("page-{}", i % 7),
+        added: i,
+    });
+    let ingestion = InlineIngestion::new(DataSchema::new(
+        "inline_edits",
+        TimestampSpec::new("time", "iso"),
+        DimensionsSpec::new(vec![
+            DimensionSchema::string("page"),
+            DimensionSchema::long("added"),
+        ]),
+    ))
+    .max_bytes_per_task(2048)
+    .poll_interval(Duration::from_secs(1))
+    .timeout(Duration::from_secs(300));
+
+    let result = tokio_test::block_on(overlord.ingest_rows(rows, &ingestion));
+    let statuses = result.unwrap();
+    assert!(statuses.len() > 1);
+
+    let report = tokio_test::block_on(overlord.task_report(&statuses[0].id)).unwrap();
+    println!("{:?}", report.ingestion_stats_and_errors);
+}
@@ -0,0 +1,315 @@
+extern crate druid_io;
+
+use chrono::NaiveDate;
+use druid_io::{
+    client::{DruidClientBuilder, DruidClientError},
+    query::{
+        definitions::{Aggregation, Dimension, Granularity, Interval},
+        group_by::GroupByBuilder,
+        response::DruidError,
+        sql::SqlQueryBuilder,
+        timeseries::Timeseries,
+        DataSource,
+    },
+    testing::{Expectation, MockDruidServer},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Count {
+    count: usize,
+}
+
+fn timeseries(data_source: &str) -> Timeseries {
+    Timeseries {
+        data_source: DataSource::table(data_source),
+        limit: None,
+        descending: false,
+        granularity: Granularity::all(),
+        filter: None,
+        aggregations: vec![Aggregation::count("count")],
+        post_aggregations: vec![],
+        virtual_columns: vec![],
+        intervals: vec![Interval {
+            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            to: NaiveDate::from_ymd_opt(2015, 9, 13)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        }],
+        context: Default::default(),
+    }
+}
+
+#[test]
+fn test_canned_response() {
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::query_type("timeseries")
+            .data_source("wikipedia")
+            .respond_with_json(json!([
+                {"timestamp": "2015-09-12T00:00:00.000Z", "result": {"count": 39244}}
+            ])),
+    );
+    let client = DruidClientBuilder::new(&server.url()).build();
+
+    let result = tokio_test::block_on(client.timeseries::<Count>(&timeseries("wikipedia")));
+    let rows = result.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert!(format!("{:?}", rows[0]).contains("count: 39244"));
+
+    let result = tokio_test::block_on(client.timeseries::<Count>(&timeseries("koalas")));
+    assert!(matches!(
+        result,
+        Err(DruidClientError::ServerError { status: 500, .. })
+    ));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].path, "/druid/v2");
+    assert_eq!(requests[1].body["dataSource"]["name"], "koalas");
+}
+
+#[test]
+fn test_exact_match_and_errors() {
+    let server = MockDruidServer::start();
+    let query = timeseries("wikipedia");
+    server.mock(Expectation::exact(&query).respond_with_error(
+        429,
+        DruidError {
+            error: Some("Query capacity exceeded".into()),
+            error_message: Some("Too many concurrent queries".into()),
+            ..Default::default()
+        },
+    ));
+    let client = DruidClientBuilder::new(&server.url()).build();
+
+    let result = tokio_test::block_on(client.timeseries::<Count>(&query));
+    assert!(matches!(
+        result,
+        Err(DruidClientError::QueryCapacityExceeded { status: 429, .. })
+    ));
+}
+
+#[test]
+fn test_record_and_replay() {
+    let cassette = std::env::temp_dir().join(format!("cassette-{}.json", std::process::id()));
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::query_type("timeseries").respond_with_json(json!([
+            {"timestamp": "2015-09-12T00:00:00.000Z", "result": {"count": 39244}}
+        ])),
+    );
+
+    let recorder = DruidClientBuilder::new(&server.url())
+        .record(&cassette)
+        .build();
+    let recorded = tokio_test::block_on(recorder.timeseries::<Count>(&timeseries("wikipedia")));
+    assert_eq!(recorded.unwrap().len(), 1);
+    drop(server);
+
+    let player = DruidClientBuilder::new("http://localhost:1")
+        .replay(&cassette)
+        .build();
+    let replayed = tokio_test::block_on(player.timeseries::<Count>(&timeseries("wikipedia")));
+    assert!(format!("{:?}", replayed.unwrap()).contains("count: 39244"));
+
+    let unrecorded = tokio_test::block_on(player.timeseries::<Count>(&timeseries("koalas")));
+    assert!(matches!(
+        unrecorded,
+        Err(DruidClientError::UnrecordedQuery { .. })
+    ));
+    let _ = std::fs::remove_file(cassette);
+}
+
+/// Nothing listens on port 1, connections to it are refused.
+const DEAD_BROKER: &str = "http://127.0.0.1:1";
+
+#[test]
+fn test_failover_to_next_broker() {
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::query_type("timeseries").respond_with_json(json!([
+            {"timestamp": "2015-09-12T00:00:00.000Z", "result": {"count": 39244}}
+        ])),
+    );
+    let client = DruidClientBuilder::with_brokers(&[DEAD_BROKER, &server.url()]).build();
+
+    let rows = tokio_test::block_on(client.timeseries::<Count>(&timeseries("wikipedia"))).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn test_no_failover_for_ingestion() {
+    let server = MockDruidServer::start();
+    let client = DruidClientBuilder::with_brokers(&[DEAD_BROKER, &server.url()]).build();
+
+    let insert =
+        SqlQueryBuilder::new("INSERT INTO w SELECT * FROM wikipedia PARTITIONED BY DAY").build();
+    let result = tokio_test::block_on(client.sql::<serde_json::Value>(&insert));
+    assert!(matches!(
+        result,
+        Err(DruidClientError::HttpConnection { .. })
+    ));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn test_no_broker_available() {
+    let client = DruidClientBuilder::with_brokers(&[]).build();
+    let result = tokio_test::block_on(client.timeseries::<Count>(&timeseries("wikipedia")));
+    assert!(matches!(result, Err(DruidClientError::NoBrokerAvailable)));
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PageCount {
+    page: String,
+    count: usize,
+}
+
+#[test]
+fn test_group_by_result_as_array() {
+    let server = MockDruidServer::start();
+    server.mock(Expectation::query_type("groupBy").respond_with_json(json!([
+        [1442016000000i64, "Main", 3],
+        [1442019600000i64, "Talk", 1]
+    ])));
+    let client = DruidClientBuilder::new(&server.url()).build();
+    let mut query = GroupByBuilder::new(DataSource::table("wikipedia"))
+        .dimensions(vec![Dimension::default("page")])
+        .aggregations(vec![Aggregation::count("count")])
+        .granularity(Granularity::hour())
+        .build();
+    query.context.result_as_array = Some(true);
+
+    let rows = tokio_test::block_on(client.group_by::<PageCount>(&query)).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1].timestamp, "2015-09-12T01:00:00.000Z");
+    assert_eq!(rows[1].event.page, "Talk");
+    assert_eq!(rows[0].event.count, 3);
+}
+
+#[cfg(feature = "smile")]
+#[test]
+fn test_smile_falls_back_to_json() {
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::query_type("timeseries").respond_with_json(json!([
+            {"timestamp": "2015-09-12T00:00:00.000Z", "result": {"count": 39244}}
+        ])),
+    );
+    // a proxy that only forwards json
+    server.mock(
+        Expectation::any()
+            .times(1)
+            .respond_with_status(415, "Unsupported Media Type"),
+    );
+    let client = DruidClientBuilder::new(&server.url()).build();
+
+    let rows = tokio_test::block_on(client.timeseries::<Count>(&timeseries("wikipedia"))).unwrap();
+    assert_eq!(rows[0].result.count, 39244);
+    // the smile query was decoded by the server before being refused
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].body, requests[1].body);
+
+    tokio_test::block_on(client.timeseries::<Count>(&timeseries("wikipedia"))).unwrap();
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[cfg(feature = "smile")]
+#[test]
+fn test_smile_fallback_is_per_broker() {
+    let proxied = MockDruidServer::start();
+    let direct = MockDruidServer::start();
+    for server in [&proxied, &direct] {
+        server.mock(
+            Expectation::query_type("timeseries").respond_with_json(json!([
+                {"timestamp": "2015-09-12T00:00:00.000Z", "result": {"count": 39244}}
+            ])),
+        );
+    }
+    proxied.mock(
+        Expectation::any()
+            .times(1)
+            .respond_with_status(415, "Unsupported Media Type"),
+    );
+    let client = DruidClientBuilder::with_brokers(&[&proxied.url(), &direct.url()]).build();
+    let content_types = |server: &MockDruidServer| {
+        server
+            .requests()
+            .iter()
+            .map(|request| request.headers["content-type"].clone())
+            .collect::<Vec<_>>()
+    };
+
+    // round robin: proxied, direct, proxied
+    for _ in 0..3 {
+        let rows =
+            tokio_test::block_on(client.timeseries::<Count>(&timeseries("wikipedia"))).unwrap();
+        assert_eq!(rows[0].result.count, 39244);
+    }
+    // the refused query was sent again to the same broker, as json
+    assert_eq!(
+        content_types(&proxied),
+        [
+            "application/x-jackson-smile",
+            "application/json",
+            "application/json"
+        ]
+    );
+    assert_eq!(content_types(&direct), ["application/x-jackson-smile"]);
+}
+
+#[test]
+fn test_cancel_on_client_timeout() {
+    let server = MockDruidServer::start();
+    server.mock(
+        Expectation::query_type("timeseries")
+            .delay(Duration::from_secs(2))
+            .respond_with_json(json!([])),
+    );
+    // cancel_on_drop is off, the timeout alone cancels the abandoned query
+    let client = DruidClientBuilder::new(&server.url())
+        .timeout(Duration::from_millis(100))
+        .build();
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let result = runtime.block_on(client.timeseries::<Count>(&timeseries("wikipedia")));
+    let query_id = match result {
+        Err(DruidClientError::Timeout { query_id, .. }) => query_id,
+        other => panic!("expected a timeout, got {other:?}"),
+    };
+    runtime.block_on(async { tokio::time::sleep(Duration::from_millis(300)).await });
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].method, "DELETE");
+    assert_eq!(requests[1].path, format!("/druid/v2/{query_id}"));
+}
+
+#[test]
+fn test_cancel_on_every_broker() {
+    let first = MockDruidServer::start();
+    let second = MockDruidServer::start();
+    let client = DruidClientBuilder::with_brokers(&[&first.url(), &second.url()]).build();
+
+    tokio_test::block_on(client.cancel("abc-123")).unwrap();
+    for server in [&first, &second] {
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "DELETE");
+        assert_eq!(requests[0].path, "/druid/v2/abc-123");
+    }
+
+    // a broker that can't be reached doesn't fail the cancellation
+    let client = DruidClientBuilder::with_brokers(&[DEAD_BROKER, &first.url()]).build();
+    tokio_test::block_on(client.cancel("abc-123")).unwrap();
+    assert_eq!(first.requests().len(), 2);
+}
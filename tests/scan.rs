@@ -11,6 +11,7 @@ use druid_io::{
         DataSource, JoinType,
     },
 };
+use futures::StreamExt;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -41,8 +42,14 @@ fn test_scan_join() {
                     ScanBuilder::new(DataSource::table("countries"))
                         .batch_size(10)
                         .intervals(vec![Interval {
-                            from: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(8, 23, 32, 96),
-                            to: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(15, 36, 27, 96),
+                            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                                .unwrap()
+                                .and_hms_milli_opt(8, 23, 32, 96)
+                                .unwrap(),
+                            to: NaiveDate::from_ymd_opt(2015, 9, 12)
+                                .unwrap()
+                                .and_hms_milli_opt(15, 36, 27, 96)
+                                .unwrap(),
                         }])
                         .columns(vec!["Name".into(), "languages".into()])
                         .build()
@@ -56,8 +63,14 @@ fn test_scan_join() {
     )
     .batch_size(10)
     .intervals(vec![Interval {
-        from: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(8, 23, 32, 96),
-        to: NaiveDate::from_ymd(2015, 9, 12).and_hms_milli(15, 36, 27, 96),
+        from: NaiveDate::from_ymd_opt(2015, 9, 12)
+            .unwrap()
+            .and_hms_milli_opt(8, 23, 32, 96)
+            .unwrap(),
+        to: NaiveDate::from_ymd_opt(2015, 9, 12)
+            .unwrap()
+            .and_hms_milli_opt(15, 36, 27, 96)
+            .unwrap(),
     }])
     .result_format(ResultFormat::List)
     .virtual_columns(vec![VirtualColumn::expression(
@@ -72,3 +85,35 @@ fn test_scan_join() {
     let result = tokio_test::block_on(druid_client.scan::<ScanEvent>(&scan));
     println!("{:?}", result.unwrap());
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+struct WikiEdit {
+    page: String,
+    user: String,
+}
+#[test]
+fn test_scan_stream() {
+    let scan = ScanBuilder::new(DataSource::table("wikipedia"))
+        .batch_size(10)
+        .intervals(vec![Interval {
+            from: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(8, 23, 32, 96)
+                .unwrap(),
+            to: NaiveDate::from_ymd_opt(2015, 9, 12)
+                .unwrap()
+                .and_hms_milli_opt(15, 36, 27, 96)
+                .unwrap(),
+        }])
+        .columns(vec!["page".into(), "user".into()])
+        .limit(100)
+        .build();
+
+    let druid_client = DruidClientBuilder::new("http://localhost:8082").build();
+    let rows: Vec<_> = tokio_test::block_on(druid_client.scan_stream::<WikiEdit>(&scan).collect());
+    assert_eq!(rows.len(), 100);
+    println!(
+        "{:?}",
+        rows.into_iter().collect::<Result<Vec<_>, _>>().unwrap()
+    );
+}